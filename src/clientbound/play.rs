@@ -2,8 +2,8 @@ use crate::common::bit_set::{BitSet, FixedBitSet};
 use crate::common::chat::Chat;
 use crate::common::chunk::Chunk;
 use crate::common::play::{
-    BlockPos, BlockUpdate, ChatBind, CommandNode, Difficulty, GameType, GlobalPos, InteractionHand,
-    ItemStack, Location, MapColorPatch, MessageSignature, PackedMessageBody,
+    BlockPos, BlockUpdate, ChatBind, CommandNode, Difficulty, Direction, GameType, GlobalPos,
+    InteractionHand, ItemStack, Location, MapColorPatch, MessageSignature, PackedMessageBody,
     PackedMessageSignature, SectionPos, SimpleLocation,
 };
 use crate::common::play::{RecipeBookType, RemoteChatSession};
@@ -466,6 +466,119 @@ impl<C: Send + Sync> PacketComponent<C> for SetEquipmentList {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct EntityDataItem {
+    pub id: u8,
+    pub value: EntityDataValue,
+}
+
+#[derive(Debug)]
+pub struct EntityDataList;
+
+impl EntityDataList {
+    pub const EOF_MARKER: u8 = 0xFF;
+}
+
+impl<C: Send + Sync> PacketComponent<C> for EntityDataList {
+    type ComponentType = Vec<EntityDataItem>;
+
+    fn decode<'a, A: AsyncRead + Unpin + Send + Sync + ?Sized>(
+        context: &'a mut C,
+        read: &'a mut A,
+    ) -> PinnedLivelyResult<'a, Self::ComponentType> {
+        Box::pin(async move {
+            let mut items = Vec::new();
+            loop {
+                let id = read.read_u8().await?;
+                if id == Self::EOF_MARKER {
+                    break;
+                }
+                let value = EntityDataValue::decode(context, read).await?;
+                items.push(EntityDataItem { id, value });
+            }
+            Ok(items)
+        })
+    }
+
+    fn encode<'a, A: AsyncWrite + Unpin + Send + Sync + ?Sized>(
+        component_ref: &'a Self::ComponentType,
+        context: &'a mut C,
+        write: &'a mut A,
+    ) -> PinnedLivelyResult<'a, ()> {
+        Box::pin(async move {
+            for item in component_ref {
+                if item.id == Self::EOF_MARKER {
+                    throw_explain!("Entity data id 255 is reserved for the terminator.")
+                }
+                write.write_u8(item.id).await?;
+                EntityDataValue::encode(&item.value, context, write).await?;
+            }
+            write.write_u8(Self::EOF_MARKER).await?;
+            Ok(())
+        })
+    }
+
+    fn size(input: &Self::ComponentType, context: &mut C) -> drax::prelude::Result<Size> {
+        let mut size = Size::Constant(1);
+        for item in input {
+            size = size + Size::Constant(1) + EntityDataValue::size(&item.value, context)?;
+        }
+        Ok(size)
+    }
+}
+
+/// A particle emitted by [`ClientboundPlayRegistry::LevelParticles`]; the id of the particle
+/// type precedes the base on the wire while its payload follows it.
+#[derive(Debug, Clone)]
+pub struct Particle {
+    pub particle_type: ParticleType,
+    pub base: ParticleBase,
+}
+
+impl<C: Send + Sync> PacketComponent<C> for Particle {
+    type ComponentType = Particle;
+
+    fn decode<'a, A: AsyncRead + Unpin + Send + Sync + ?Sized>(
+        context: &'a mut C,
+        read: &'a mut A,
+    ) -> PinnedLivelyResult<'a, Self::ComponentType> {
+        Box::pin(async move {
+            let id = read.read_var_int().await?;
+            let base = ParticleBase::decode(context, read).await?;
+            let mut key = Vec::with_capacity(5);
+            key.write_var_int(id).await?;
+            let mut payload = std::io::Cursor::new(key).chain(&mut *read);
+            let particle_type = ParticleType::decode(context, &mut payload).await?;
+            Ok(Particle {
+                particle_type,
+                base,
+            })
+        })
+    }
+
+    fn encode<'a, A: AsyncWrite + Unpin + Send + Sync + ?Sized>(
+        component_ref: &'a Self::ComponentType,
+        context: &'a mut C,
+        write: &'a mut A,
+    ) -> PinnedLivelyResult<'a, ()> {
+        Box::pin(async move {
+            let mut particle_type = Vec::new();
+            ParticleType::encode(&component_ref.particle_type, context, &mut particle_type).await?;
+            let mut cursor = std::io::Cursor::new(particle_type);
+            write.write_var_int(cursor.read_var_int().await?).await?;
+            ParticleBase::encode(&component_ref.base, context, write).await?;
+            let offset = cursor.position() as usize;
+            write.write_all(&cursor.get_ref()[offset..]).await?;
+            Ok(())
+        })
+    }
+
+    fn size(input: &Self::ComponentType, context: &mut C) -> drax::prelude::Result<Size> {
+        Ok(ParticleBase::size(&input.base, context)?
+            + ParticleType::size(&input.particle_type, context)?)
+    }
+}
+
 #[derive(Debug)]
 pub enum SoundEvent {
     Direct {
@@ -830,6 +943,7 @@ registry! {
             price_multiplier: i32
         },
 
+        #[derive(Clone)]
         enum FilterMask<key: VarInt> {
             PassThrough {},
             FullyFiltered {},
@@ -1205,6 +1319,7 @@ registry! {
             block_updates: Vec<LimitedVec<u8, 2048>>
         },
 
+        #[derive(Clone)]
         struct ParticleBase {
             override_limiter: bool,
             location: SimpleLocation,
@@ -1230,60 +1345,35 @@ registry! {
             }
         },
 
+        #[derive(Clone)]
         enum ParticleType<key: VarInt> {
-            AmbientEntityEffect {
-                base: ParticleBase
-            },
-            AngryVillager {
-                base: ParticleBase
-            },
+            AmbientEntityEffect {},
+            AngryVillager {},
             Block {
-                base: ParticleBase,
                 block_id: VarInt
             },
             BlockMarker {
-                base: ParticleBase,
                 block_id: VarInt
             },
-            Bubble {
-                base: ParticleBase
-            },
-            Cloud {
-                base: ParticleBase
-            },
-            Crit {
-                base: ParticleBase
-            },
-            DamageIndicator {
-                base: ParticleBase
-            },
-            DragonBreath {
-                base: ParticleBase
-            },
-            DrippingLava {
-                base: ParticleBase
-            },
-            FallingLava {
-                base: ParticleBase
-            },
-            LandingLava {
-                base: ParticleBase
-            },
-            DrippingWater {
-                base: ParticleBase
-            },
-            FallingWater {
-                base: ParticleBase
-            },
+            Bubble {},
+            Cloud {},
+            Crit {},
+            DamageIndicator {},
+            DragonBreath {},
+            DrippingLava {},
+            FallingLava {},
+            LandingLava {},
+            DrippingWater {},
+            FallingWater {},
+            /// An RGB colour with components from `0` to `1`, scaled by `scale`.
             Dust {
-                base: ParticleBase,
                 xa: f32,
                 ya: f32,
                 za: f32,
                 scale: f32
             },
+            /// Fades from the `xa`, `ya`, `za` colour to the `to_` colour.
             DustColorTransition {
-                base: ParticleBase,
                 xa: f32,
                 ya: f32,
                 za: f32,
@@ -1292,242 +1382,194 @@ registry! {
                 to_ya: f32,
                 to_za: f32
             },
-            Effect {
-                base: ParticleBase
-            },
-            ElderGuardian {
-                base: ParticleBase
-            },
-            EnchantedHit {
-                base: ParticleBase
-            },
-            Enchant {
-                base: ParticleBase
-            },
-            EndRod {
-                base: ParticleBase
-            },
-            EntityEffect {
-                base: ParticleBase
-            },
-            ExplosionEmitter {
-                base: ParticleBase
-            },
-            Explosion {
-                base: ParticleBase
-            },
-            SonicBoom {
-                base: ParticleBase
-            },
+            Effect {},
+            ElderGuardian {},
+            EnchantedHit {},
+            Enchant {},
+            EndRod {},
+            EntityEffect {},
+            ExplosionEmitter {},
+            Explosion {},
+            SonicBoom {},
             FallingDust {
-                base: ParticleBase,
                 block_id: VarInt
             },
-            Firework {
-                base: ParticleBase
-            },
-            Fishing {
-                base: ParticleBase
-            },
-            Flame {
-                base: ParticleBase
-            },
-            SculkSoul {
-                base: ParticleBase
-            },
+            Firework {},
+            Fishing {},
+            Flame {},
+            SculkSoul {},
+            /// The roll of the particle in radians.
             SculkCharge {
-                base: ParticleBase,
-                scale: f32
-            },
-            SculkChargePop {
-                base: ParticleBase
-            },
-            SoulFireFlame {
-                base: ParticleBase
-            },
-            Soul {
-                base: ParticleBase
-            },
-            Flash {
-                base: ParticleBase
-            },
-            HappyVillager {
-                base: ParticleBase
-            },
-            Composter {
-                base: ParticleBase
-            },
-            Heart {
-                base: ParticleBase
-            },
-            InstantEffect {
-                base: ParticleBase
-            },
+                roll: f32
+            },
+            SculkChargePop {},
+            SoulFireFlame {},
+            Soul {},
+            Flash {},
+            HappyVillager {},
+            Composter {},
+            Heart {},
+            InstantEffect {},
             Item {
-                base: ParticleBase,
                 item: Maybe<ItemStack>
             },
             Vibration {
-                base: ParticleBase,
                 source: PositionSource,
                 arrival_in_ticks: VarInt
             },
-            ItemSlime {
-                base: ParticleBase
-            },
-            ItemSnowball {
-                base: ParticleBase
-            },
-            LargeSmoke {
-                base: ParticleBase
-            },
-            Lava {
-                base: ParticleBase
-            },
-            Mycelium {
-                base: ParticleBase
-            },
-            Note {
-                base: ParticleBase
-            },
-            Poof {
-                base: ParticleBase
-            },
-            Portal {
-                base: ParticleBase
-            },
-            Rain {
-                base: ParticleBase
-            },
-            Smoke {
-                base: ParticleBase
-            },
-            Sneeze {
-                base: ParticleBase
-            },
-            Spit {
-                base: ParticleBase
-            },
-            SquidInk {
-                base: ParticleBase
-            },
-            SweepAttack {
-                base: ParticleBase
-            },
-            TotemOfUndying {
-                base: ParticleBase
-            },
-            Underwater {
-                base: ParticleBase
-            },
-            Splash {
-                base: ParticleBase
-            },
-            Witch {
-                base: ParticleBase
-            },
-            BubblePop {
-                base: ParticleBase
-            },
-            CurrentDown {
-                base: ParticleBase
-            },
-            BubbleColumnUp {
-                base: ParticleBase
-            },
-            Nautilus {
-                base: ParticleBase
-            },
-            Dolphin {
-                base: ParticleBase
-            },
-            CampfireCosySmoke {
-                base: ParticleBase
-            },
-            CampfireSignalSmoke {
-                base: ParticleBase
-            },
-            DrippingHoney {
-                base: ParticleBase
-            },
-            FallingHoney {
-                base: ParticleBase
-            },
-            LandingHoney {
-                base: ParticleBase
-            },
-            FallingNectar {
-                base: ParticleBase
+            ItemSlime {},
+            ItemSnowball {},
+            LargeSmoke {},
+            Lava {},
+            Mycelium {},
+            Note {},
+            Poof {},
+            Portal {},
+            Rain {},
+            Smoke {},
+            Sneeze {},
+            Spit {},
+            SquidInk {},
+            SweepAttack {},
+            TotemOfUndying {},
+            Underwater {},
+            Splash {},
+            Witch {},
+            BubblePop {},
+            CurrentDown {},
+            BubbleColumnUp {},
+            Nautilus {},
+            Dolphin {},
+            CampfireCosySmoke {},
+            CampfireSignalSmoke {},
+            DrippingHoney {},
+            FallingHoney {},
+            LandingHoney {},
+            FallingNectar {},
+            FallingSporeBlossom {},
+            Ash {},
+            CrimsonSpore {},
+            WarpedSpore {},
+            SporeBlossomAir {},
+            DrippingObsidianTear {},
+            FallingObsidianTear {},
+            LandingObsidianTear {},
+            ReversePortal {},
+            WhiteAsh {},
+            SmallFlame {},
+            Snowflake {},
+            DrippingDripstoneLava {},
+            FallingDripstoneLava {},
+            DrippingDripstoneWater {},
+            FallingDripstoneWater {},
+            GlowSquidInk {},
+            Glow {},
+            WaxOn {},
+            WaxOff {},
+            ElectricSpark {},
+            Scrape {},
+            Shriek {
+                delay: VarInt
+            }
+        },
+
+        #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+        enum Pose<key: VarInt> {
+            Standing {},
+            FallFlying {},
+            Sleeping {},
+            Swimming {},
+            SpinAttack {},
+            Crouching {},
+            LongJumping {},
+            Dying {},
+            Croaking {},
+            UsingTongue {},
+            Roaring {},
+            Sniffing {},
+            Emerging {},
+            Digging {}
+        },
+
+        #[derive(Clone)]
+        enum EntityDataValue<key: VarInt> {
+            Byte {
+                value: u8
             },
-            FallingSporeBlossom {
-                base: ParticleBase
+            Int {
+                value: VarInt
             },
-            Ash {
-                base: ParticleBase
+            Long {
+                value: VarLong
             },
-            CrimsonSpore {
-                base: ParticleBase
+            Float {
+                value: f32
             },
-            WarpedSpore {
-                base: ParticleBase
+            String {
+                value: String
             },
-            SporeBlossomAir {
-                base: ParticleBase
+            Component {
+                value: JsonDelegate<Chat>
             },
-            DrippingObsidianTear {
-                base: ParticleBase
+            OptionalComponent {
+                value: Maybe<JsonDelegate<Chat>>
             },
-            FallingObsidianTear {
-                base: ParticleBase
+            ItemStack {
+                value: Maybe<ItemStack>
             },
-            LandingObsidianTear {
-                base: ParticleBase
+            Boolean {
+                value: bool
             },
-            ReversePortal {
-                base: ParticleBase
+            Rotations {
+                x: f32,
+                y: f32,
+                z: f32
             },
-            WhiteAsh {
-                base: ParticleBase
+            BlockPos {
+                value: BlockPos
             },
-            SmallFlame {
-                base: ParticleBase
+            OptionalBlockPos {
+                value: Maybe<BlockPos>
             },
-            Snowflake {
-                base: ParticleBase
+            Direction {
+                value: Direction
             },
-            DrippingDripstoneLava {
-                base: ParticleBase
+            OptionalUuid {
+                value: Maybe<Uuid>
             },
-            FallingDripstoneLava {
-                base: ParticleBase
+            /// A block state id; `0` denotes an absent state.
+            OptionalBlockState {
+                value: VarInt
             },
-            DrippingDripstoneWater {
-                base: ParticleBase
+            CompoundTag {
+                value: EnsuredCompoundTag<0>
             },
-            FallingDripstoneWater {
-                base: ParticleBase
+            Particle {
+                value: ParticleType
             },
-            GlowSquidInk {
-                base: ParticleBase
+            VillagerData {
+                villager_type: VarInt,
+                profession: VarInt,
+                level: VarInt
             },
-            Glow {
-                base: ParticleBase
+            /// An unsigned int offset by one; `0` denotes an absent value.
+            OptionalUnsignedInt {
+                value: VarInt
             },
-            WaxOn {
-                base: ParticleBase
+            Pose {
+                value: Pose
             },
-            WaxOff {
-                base: ParticleBase
+            CatVariant {
+                value: VarInt
             },
-            ElectricSpark {
-                base: ParticleBase
+            FrogVariant {
+                value: VarInt
             },
-            Scrape {
-                base: ParticleBase
+            OptionalGlobalPos {
+                value: Maybe<GlobalPos>
             },
-            Shriek {
-                base: ParticleBase,
-                delay: VarInt
+            PaintingVariant {
+                value: VarInt
             }
         }
     }
@@ -1732,7 +1774,7 @@ registry! {
         },
 
         struct LevelParticles {
-            particle: ParticleType
+            particle: Particle
         },
 
         struct LightUpdate {
@@ -1990,7 +2032,7 @@ registry! {
 
         struct SetEntityData {
             entity_id: VarInt,
-            temp_drain: ByteDrain
+            packed_items: EntityDataList
         },
 
         struct SetEntityLink {
@@ -2151,3 +2193,187 @@ registry! {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::clientbound::play::{
+        EntityDataItem, EntityDataList, EntityDataValue, Particle, ParticleBase, ParticleType,
+        Pose, PositionSource,
+    };
+    use crate::common::chat::Chat;
+    use crate::common::play::{BlockPos, SimpleLocation};
+    use drax::prelude::PacketComponent;
+    use std::io::Cursor;
+
+    fn base() -> ParticleBase {
+        ParticleBase {
+            override_limiter: false,
+            location: SimpleLocation {
+                x: 1.0,
+                y: 64.0,
+                z: -2.5,
+            },
+            x_dist: 0.5,
+            y_dist: 0.5,
+            z_dist: 0.5,
+            max_speed: 0.1,
+            count: 8,
+        }
+    }
+
+    async fn round_trip(particle_type: ParticleType) -> drax::prelude::Result<ParticleType> {
+        let mut cursor = Cursor::new(vec![]);
+        Particle::encode(
+            &Particle {
+                particle_type,
+                base: base(),
+            },
+            &mut (),
+            &mut cursor,
+        )
+        .await?;
+        cursor.set_position(0);
+        let decoded = Particle::decode(&mut (), &mut cursor).await?;
+        assert_eq!(cursor.position() as usize, cursor.get_ref().len());
+        assert_eq!(decoded.base.count, 8);
+        Ok(decoded.particle_type)
+    }
+
+    #[tokio::test]
+    pub async fn particle_payloads() -> drax::prelude::Result<()> {
+        assert!(matches!(
+            round_trip(ParticleType::DustColorTransition {
+                xa: 1.0,
+                ya: 0.0,
+                za: 0.0,
+                scale: 2.0,
+                to_xa: 0.0,
+                to_ya: 0.0,
+                to_za: 1.0,
+            })
+            .await?,
+            ParticleType::DustColorTransition { scale, to_za, .. } if scale == 2.0 && to_za == 1.0
+        ));
+        assert!(matches!(
+            round_trip(ParticleType::Vibration {
+                source: PositionSource::Block {
+                    pos: BlockPos { x: 3, y: 70, z: 9 },
+                },
+                arrival_in_ticks: 20,
+            })
+            .await?,
+            ParticleType::Vibration {
+                source: PositionSource::Block { pos },
+                arrival_in_ticks: 20,
+            } if pos == BlockPos { x: 3, y: 70, z: 9 }
+        ));
+        assert!(matches!(
+            round_trip(ParticleType::Shriek { delay: 15 }).await?,
+            ParticleType::Shriek { delay: 15 }
+        ));
+
+        // the particle id is written before the base, the payload after it
+        let mut bytes = vec![];
+        Particle::encode(
+            &Particle {
+                particle_type: ParticleType::Shriek { delay: 15 },
+                base: base(),
+            },
+            &mut (),
+            &mut bytes,
+        )
+        .await?;
+        assert_eq!(bytes.first(), Some(&92));
+        assert_eq!(bytes.last(), Some(&15));
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn entity_data_round_trip() -> drax::prelude::Result<()> {
+        // a byte at index 0 and a crouching pose at index 6
+        let raw = vec![0x00, 0x00, 0x05, 0x06, 0x13, 0x05, 0xFF];
+        let items = EntityDataList::decode(&mut (), &mut Cursor::new(raw.clone())).await?;
+        assert!(matches!(
+            items.as_slice(),
+            [
+                EntityDataItem {
+                    id: 0,
+                    value: EntityDataValue::Byte { value: 5 }
+                },
+                EntityDataItem {
+                    id: 6,
+                    value: EntityDataValue::Pose {
+                        value: Pose::Crouching {}
+                    }
+                }
+            ]
+        ));
+        let mut encoded = vec![];
+        EntityDataList::encode(&items, &mut (), &mut encoded).await?;
+        assert_eq!(encoded, raw);
+
+        let items = vec![
+            EntityDataItem {
+                id: 2,
+                value: EntityDataValue::OptionalComponent { value: None },
+            },
+            EntityDataItem {
+                id: 3,
+                value: EntityDataValue::OptionalComponent {
+                    value: Some(Chat::text("Steve")),
+                },
+            },
+            EntityDataItem {
+                id: 8,
+                value: EntityDataValue::OptionalBlockPos {
+                    value: Some(BlockPos { x: -4, y: 12, z: 9 }),
+                },
+            },
+            EntityDataItem {
+                id: 9,
+                value: EntityDataValue::OptionalUuid { value: None },
+            },
+            EntityDataItem {
+                id: 10,
+                value: EntityDataValue::Particle {
+                    value: ParticleType::Dust {
+                        xa: 1.0,
+                        ya: 0.5,
+                        za: 0.0,
+                        scale: 1.0,
+                    },
+                },
+            },
+            EntityDataItem {
+                id: 11,
+                value: EntityDataValue::Pose {
+                    value: Pose::Sleeping {},
+                },
+            },
+        ];
+        let mut encoded = vec![];
+        EntityDataList::encode(&items, &mut (), &mut encoded).await?;
+        assert_eq!(encoded.last(), Some(&EntityDataList::EOF_MARKER));
+        let decoded = EntityDataList::decode(&mut (), &mut Cursor::new(encoded.clone())).await?;
+        assert_eq!(decoded.len(), items.len());
+        assert!(matches!(
+            decoded[4].value,
+            EntityDataValue::Particle {
+                value: ParticleType::Dust { ya, .. }
+            } if ya == 0.5
+        ));
+        let mut re_encoded = vec![];
+        EntityDataList::encode(&decoded, &mut (), &mut re_encoded).await?;
+        assert_eq!(re_encoded, encoded);
+
+        // the terminator cannot be used as an index
+        let reserved = vec![EntityDataItem {
+            id: EntityDataList::EOF_MARKER,
+            value: EntityDataValue::Boolean { value: true },
+        }];
+        assert!(EntityDataList::encode(&reserved, &mut (), &mut vec![])
+            .await
+            .is_err());
+        Ok(())
+    }
+}
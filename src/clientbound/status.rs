@@ -1,13 +1,13 @@
 use crate::common::chat::Chat;
 use drax::transport::packet::serde_json::JsonDelegate;
 
-#[derive(Debug, serde_derive::Serialize, serde_derive::Deserialize)]
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
 pub struct Player {
     pub id: String,
     pub name: String,
 }
 
-#[derive(Debug, serde_derive::Serialize, serde_derive::Deserialize)]
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
 pub struct StatusPlayers {
     pub max: isize,
     pub online: isize,
@@ -15,13 +15,13 @@ pub struct StatusPlayers {
     pub sample: Vec<Player>,
 }
 
-#[derive(Debug, serde_derive::Serialize, serde_derive::Deserialize)]
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
 pub struct StatusVersion {
     pub name: String,
     pub protocol: i32,
 }
 
-#[derive(Debug, serde_derive::Serialize, serde_derive::Deserialize)]
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
 pub struct StatusResponse {
     pub description: Chat,
     pub players: StatusPlayers,
@@ -6,7 +6,7 @@ use drax::PinnedLivelyResult;
 
 const ADDRESS_BITS_PER_WORD: i32 = 6;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BitSet {
     pub words: Vec<u64>,
     words_in_use: usize,
@@ -223,9 +223,17 @@ impl<C: Send + Sync, const N: i32> PacketComponent<C> for FixedBitSet<N> {
         write: &'a mut A,
     ) -> PinnedLivelyResult<'a, ()> {
         Box::pin(async move {
-            write
-                .write_all(&component_ref.to_byte_array().await?[0..Self::FLOORED_SIZE])
-                .await?;
+            let mut bytes = component_ref.to_byte_array().await?;
+            if bytes.len() > Self::FLOORED_SIZE {
+                drax::throw_explain!(format!(
+                    "BitSet is larger than expected size ({} > {}).",
+                    bytes.len(),
+                    Self::FLOORED_SIZE
+                ))
+            }
+            // the byte array stops at the highest set bit
+            bytes.resize(Self::FLOORED_SIZE, 0);
+            write.write_all(&bytes).await?;
             Ok(())
         })
     }
@@ -1,9 +1,20 @@
 use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[cfg(feature = "play")]
+use crate::clientbound::play::ChatFormatting;
+
+pub mod markup;
+pub mod render;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Score {
     name: String,
     objective: String,
+    /// The score to display, present when the server resolved it in place.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    value: Option<String>,
 }
 
 impl Score {
@@ -11,8 +22,14 @@ impl Score {
         Self {
             name: name.into(),
             objective: objective.into(),
+            value: None,
         }
     }
+
+    pub fn with_value<S: Into<String>>(mut self, value: S) -> Self {
+        self.value = Some(value.into());
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -99,16 +116,279 @@ impl ClickEvent {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "action")]
+pub struct ItemTooltip {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<i32>,
+    /// The item's NBT in its SNBT notation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EntityTooltip {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    pub id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<Box<Chat>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "action", try_from = "RawHoverEvent")]
 pub enum HoverEvent {
-    #[serde(rename = "open_url")]
+    #[serde(rename = "show_text")]
     ShowText { contents: Box<Chat> },
+    #[serde(rename = "show_item")]
+    ShowItem { contents: ItemTooltip },
+    #[serde(rename = "show_entity")]
+    ShowEntity { contents: EntityTooltip },
 }
 
 impl HoverEvent {
     pub fn show_text(contents: Box<Chat>) -> Self {
         Self::ShowText { contents }
     }
+
+    pub fn show_item<S: Into<String>>(id: S, count: Option<i32>, tag: Option<String>) -> Self {
+        Self::ShowItem {
+            contents: ItemTooltip {
+                id: id.into(),
+                count,
+                tag,
+            },
+        }
+    }
+
+    pub fn show_entity<S: Into<String>>(entity_type: S, id: Uuid, name: Option<Box<Chat>>) -> Self {
+        Self::ShowEntity {
+            contents: EntityTooltip {
+                entity_type: entity_type.into(),
+                id,
+                name,
+            },
+        }
+    }
+}
+
+/// A hover event as sent by servers, which may still use the legacy `value` field instead of
+/// `contents`. <br />
+///
+/// Legacy item and entity tooltips are a text component holding SNBT such as
+/// `{id:"minecraft:stone",Count:1b}`.
+#[derive(Deserialize)]
+struct RawHoverEvent {
+    action: String,
+    contents: Option<Value>,
+    value: Option<Value>,
+}
+
+impl TryFrom<RawHoverEvent> for HoverEvent {
+    type Error = String;
+
+    fn try_from(raw: RawHoverEvent) -> Result<Self, Self::Error> {
+        match (raw.action.as_str(), raw.contents, raw.value) {
+            ("show_text", Some(contents), _) | ("show_text", None, Some(contents)) => {
+                Ok(Self::ShowText {
+                    contents: Box::new(
+                        serde_json::from_value(contents).map_err(|err| err.to_string())?,
+                    ),
+                })
+            }
+            ("show_item", Some(Value::String(id)), _) => Ok(Self::show_item(id, None, None)),
+            ("show_item", Some(contents), _) => Ok(Self::ShowItem {
+                contents: serde_json::from_value(contents).map_err(|err| err.to_string())?,
+            }),
+            ("show_item", None, Some(value)) => {
+                let entries = snbt_entries(&legacy_text(&value))
+                    .ok_or_else(|| "Malformed legacy item tooltip.".to_string())?;
+                Ok(Self::show_item(
+                    snbt_value(&entries, "id")
+                        .ok_or_else(|| "Legacy item tooltip without id.".to_string())?,
+                    snbt_value(&entries, "Count").and_then(|count| {
+                        count
+                            .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+                            .parse()
+                            .ok()
+                    }),
+                    entries
+                        .iter()
+                        .find(|(key, _)| key == "tag")
+                        .map(|(_, tag)| tag.clone()),
+                ))
+            }
+            ("show_entity", Some(contents), _) => Ok(Self::show_entity(
+                contents
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "Entity tooltip without type.".to_string())?,
+                contents
+                    .get("id")
+                    .and_then(uuid_from_value)
+                    .ok_or_else(|| "Entity tooltip without a valid id.".to_string())?,
+                match contents.get("name") {
+                    Some(name) => Some(Box::new(
+                        serde_json::from_value(name.clone()).map_err(|err| err.to_string())?,
+                    )),
+                    None => None,
+                },
+            )),
+            ("show_entity", None, Some(value)) => {
+                let entries = snbt_entries(&legacy_text(&value))
+                    .ok_or_else(|| "Malformed legacy entity tooltip.".to_string())?;
+                let id = entries
+                    .iter()
+                    .find(|(key, _)| key == "id")
+                    .and_then(|(_, id)| uuid_from_snbt(id))
+                    .ok_or_else(|| "Legacy entity tooltip without a valid id.".to_string())?;
+                Ok(Self::show_entity(
+                    snbt_value(&entries, "type")
+                        .ok_or_else(|| "Legacy entity tooltip without type.".to_string())?,
+                    id,
+                    snbt_value(&entries, "name").map(|name| {
+                        Box::new(serde_json::from_str(&name).unwrap_or_else(|_| Chat::text(name)))
+                    }),
+                ))
+            }
+            (action, _, _) => Err(format!("Unsupported hover event {}.", action)),
+        }
+    }
+}
+
+/// The plain text of a legacy `value`, which is itself a text component.
+fn legacy_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(values) => values.iter().map(legacy_text).collect(),
+        Value::Object(object) => {
+            let mut text = object
+                .get("text")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            if let Some(Value::Array(extra)) = object.get("extra") {
+                text.extend(extra.iter().map(legacy_text));
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+/// Splits the top level of an SNBT compound into its keys and raw values.
+fn snbt_entries(input: &str) -> Option<Vec<(String, String)>> {
+    let inner = input.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let mut entries = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+    let mut push = |entry: &str| -> Option<()> {
+        if entry.trim().is_empty() {
+            return Some(());
+        }
+        let split = top_level_colon(entry)?;
+        entries.push((
+            snbt_unquote(&entry[..split]),
+            entry[split + 1..].trim().to_string(),
+        ));
+        Some(())
+    };
+    for (idx, c) in inner.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '{' | '[') => depth += 1,
+            (None, '}' | ']') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                push(&inner[start..idx])?;
+                start = idx + 1;
+            }
+            _ => (),
+        }
+    }
+    push(&inner[start..])?;
+    Some(entries)
+}
+
+fn top_level_colon(entry: &str) -> Option<usize> {
+    let mut quote = None;
+    for (idx, c) in entry.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ':') => return Some(idx),
+            _ => (),
+        }
+    }
+    None
+}
+
+fn snbt_unquote(value: &str) -> String {
+    let value = value.trim();
+    match value.chars().next() {
+        Some(quote @ ('"' | '\'')) if value.len() >= 2 && value.ends_with(quote) => {
+            let mut out = String::new();
+            let mut escaped = false;
+            for c in value[1..value.len() - 1].chars() {
+                if !escaped && c == '\\' {
+                    escaped = true;
+                    continue;
+                }
+                escaped = false;
+                out.push(c);
+            }
+            out
+        }
+        _ => value.to_string(),
+    }
+}
+
+fn snbt_value(entries: &[(String, String)], key: &str) -> Option<String> {
+    entries
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| snbt_unquote(value))
+}
+
+fn uuid_from_ints(ints: &[i64]) -> Option<Uuid> {
+    match ints {
+        [a, b, c, d] => Some(Uuid::from_u128(
+            ((*a as u32 as u128) << 96)
+                | ((*b as u32 as u128) << 64)
+                | ((*c as u32 as u128) << 32)
+                | (*d as u32 as u128),
+        )),
+        _ => None,
+    }
+}
+
+/// Entity ids are either a hyphenated string or an array of four ints.
+fn uuid_from_value(value: &Value) -> Option<Uuid> {
+    match value {
+        Value::String(id) => Uuid::parse_str(id).ok(),
+        Value::Array(ints) => {
+            uuid_from_ints(&ints.iter().map(Value::as_i64).collect::<Option<Vec<_>>>()?)
+        }
+        _ => None,
+    }
+}
+
+fn uuid_from_snbt(value: &str) -> Option<Uuid> {
+    match value.trim().strip_prefix("[I;") {
+        Some(ints) => uuid_from_ints(
+            &ints
+                .strip_suffix(']')?
+                .split(',')
+                .map(|int| int.trim().parse().ok())
+                .collect::<Option<Vec<_>>>()?,
+        ),
+        None => Uuid::parse_str(&snbt_unquote(value)).ok(),
+    }
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
@@ -436,6 +716,382 @@ impl Chat {
     }
 }
 
+/// The section sign vanilla uses to introduce legacy formatting codes.
+pub const SECTION_SIGN: char = '\u{a7}';
+
+/// The legacy codes and RGB values of the named colours, in `ChatFormatting` order.
+const LEGACY_COLORS: [(char, &str, u32); 16] = [
+    ('0', "black", 0x000000),
+    ('1', "dark_blue", 0x0000aa),
+    ('2', "dark_green", 0x00aa00),
+    ('3', "dark_aqua", 0x00aaaa),
+    ('4', "dark_red", 0xaa0000),
+    ('5', "dark_purple", 0xaa00aa),
+    ('6', "gold", 0xffaa00),
+    ('7', "gray", 0xaaaaaa),
+    ('8', "dark_gray", 0x555555),
+    ('9', "blue", 0x5555ff),
+    ('a', "green", 0x55ff55),
+    ('b', "aqua", 0x55ffff),
+    ('c', "red", 0xff5555),
+    ('d', "light_purple", 0xff55ff),
+    ('e', "yellow", 0xffff55),
+    ('f', "white", 0xffffff),
+];
+
+/// Legacy formatting codes in `ChatFormatting` order, following the colours.
+const LEGACY_FORMATTING_CODES: [char; 6] = ['k', 'l', 'm', 'n', 'o', 'r'];
+
+impl Style {
+    /// Applies a legacy formatting code; as in vanilla colours and `r` reset every other
+    /// format. Returns `false` for unknown codes.
+    pub fn apply_legacy_code(&mut self, code: char) -> bool {
+        let code = code.to_ascii_lowercase();
+        if let Some((_, color, _)) = LEGACY_COLORS.iter().find(|(other, _, _)| *other == code) {
+            *self = Style::default();
+            self.color(*color);
+            return true;
+        }
+        match code {
+            'k' => self.obfuscated(true),
+            'l' => self.bold(true),
+            'm' => self.strikethrough(true),
+            'n' => self.underlined(true),
+            'o' => self.italic(true),
+            'r' => {
+                *self = Style::default();
+                self
+            }
+            _ => return false,
+        };
+        true
+    }
+
+    /// The legacy codes recreating the colour and formatting of this style. Hex colours use
+    /// the `x` sequence understood by Bukkit, e.g. `§x§f§f§0§0§0§0`.
+    pub fn to_legacy(&self, marker: char) -> String {
+        let mut out = String::new();
+        match self.color.as_deref() {
+            Some(color) if color.starts_with('#') && color.len() == 7 => {
+                out.push(marker);
+                out.push('x');
+                for c in color[1..].chars() {
+                    out.push(marker);
+                    out.push(c.to_ascii_lowercase());
+                }
+            }
+            Some(color) => {
+                if let Some((code, _, _)) = LEGACY_COLORS.iter().find(|(_, name, _)| *name == color)
+                {
+                    out.push(marker);
+                    out.push(*code);
+                }
+            }
+            None => (),
+        }
+        for (code, enabled) in LEGACY_FORMATTING_CODES.iter().zip(self.legacy_flags()) {
+            if enabled {
+                out.push(marker);
+                out.push(*code);
+            }
+        }
+        out
+    }
+
+    fn legacy_flags(&self) -> [bool; 5] {
+        [
+            self.obfuscated.unwrap_or(false),
+            self.bold.unwrap_or(false),
+            self.strikethrough.unwrap_or(false),
+            self.underlined.unwrap_or(false),
+            self.italic.unwrap_or(false),
+        ]
+    }
+
+    /// The colour and formatting of this style with unset values taken from the parent.
+    fn inherit_legacy(&self, parent: &Style) -> Style {
+        Style {
+            color: self.color.clone().or_else(|| parent.color.clone()),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underlined: self.underlined.or(parent.underlined),
+            strikethrough: self.strikethrough.or(parent.strikethrough),
+            obfuscated: self.obfuscated.or(parent.obfuscated),
+            ..Style::default()
+        }
+    }
+}
+
+/// The RGB value of a named or `#rrggbb` colour.
+pub fn color_rgb(color: &str) -> Option<u32> {
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 => u32::from_str_radix(hex, 16).ok(),
+        Some(_) => None,
+        None => LEGACY_COLORS
+            .iter()
+            .find(|(_, name, _)| *name == color)
+            .map(|(_, _, rgb)| *rgb),
+    }
+}
+
+/// Reads a hex colour following a legacy `x` or `#` code, returning it with the number of
+/// characters it spans including the leading code.
+fn legacy_hex(chars: &[char], marker: char) -> Option<(String, usize)> {
+    let (digits, consumed): (Vec<char>, usize) = match chars.first()?.to_ascii_lowercase() {
+        'x' => (
+            chars
+                .get(1..13)?
+                .chunks(2)
+                .map(|pair| (pair[0] == marker).then_some(pair[1]))
+                .collect::<Option<_>>()?,
+            13,
+        ),
+        '#' => (chars.get(1..7)?.to_vec(), 7),
+        _ => return None,
+    };
+    if !digits.iter().all(char::is_ascii_hexdigit) {
+        return None;
+    }
+    Some((
+        format!(
+            "#{}",
+            digits
+                .iter()
+                .map(char::to_ascii_lowercase)
+                .collect::<String>()
+        ),
+        consumed,
+    ))
+}
+
+/// Removes every legacy formatting code from the input.
+pub fn strip_legacy_codes(input: &str, marker: char) -> String {
+    Chat::from_legacy(input, marker).to_plain_string()
+}
+
+impl Chat {
+    fn base(&self) -> Option<&BaseChat> {
+        match self {
+            Chat::Text { base, .. } => Some(base),
+            Chat::Translatable { base, .. } => Some(base),
+            Chat::Score { base, .. } => Some(base),
+            Chat::Selector { base, .. } => Some(base),
+            Chat::Keybind { base, .. } => Some(base),
+            Chat::NbtContents { base, .. } => Some(base),
+            _ => None,
+        }
+    }
+
+    /// Parses text using legacy formatting codes introduced by `marker`, usually `§` or `&`,
+    /// into `Text` components. <br />
+    ///
+    /// Hex colours are read from both the `§x§r§r§g§g§b§b` and the `§#rrggbb` notation; codes
+    /// which are not understood are kept as text.
+    pub fn from_legacy(input: &str, marker: char) -> Self {
+        let chars: Vec<char> = input.chars().collect();
+        let mut components = Vec::new();
+        let mut style = Style::default();
+        let mut text = String::new();
+        let mut idx = 0;
+        while idx < chars.len() {
+            if chars[idx] == marker && idx + 1 < chars.len() {
+                let mut next = style.clone();
+                let consumed = match legacy_hex(&chars[idx + 1..], marker) {
+                    Some((color, consumed)) => {
+                        next = Style::default();
+                        next.color(color);
+                        Some(consumed)
+                    }
+                    None => next.apply_legacy_code(chars[idx + 1]).then_some(1),
+                };
+                if let Some(consumed) = consumed {
+                    if !text.is_empty() {
+                        components.push(Chat::Text {
+                            text: std::mem::take(&mut text),
+                            base: BaseChat {
+                                style,
+                                ..BaseChat::default()
+                            },
+                        });
+                    }
+                    style = next;
+                    idx += consumed + 1;
+                    continue;
+                }
+            }
+            text.push(chars[idx]);
+            idx += 1;
+        }
+        if !text.is_empty() {
+            components.push(Chat::Text {
+                text,
+                base: BaseChat {
+                    style,
+                    ..BaseChat::default()
+                },
+            });
+        }
+        match components.len() {
+            0 => Chat::text(""),
+            1 => components.remove(0),
+            _ => Chat::Text {
+                text: String::new(),
+                base: BaseChat {
+                    extra: Some(components),
+                    ..BaseChat::default()
+                },
+            },
+        }
+    }
+
+    /// Flattens the component tree into text with section sign formatting codes. <br />
+    ///
+    /// Only the colour and formatting survive; translatable components are written as their
+    /// key and NBT components are left out.
+    pub fn to_legacy(&self) -> String {
+        let mut out = String::new();
+        let mut last = Style::default();
+        let mut write = |chat: &Chat, style: &Style| {
+            let content = match chat.legacy_content() {
+                Some(content) if !content.is_empty() => content,
+                _ => return,
+            };
+            if style.color != last.color || style.legacy_flags() != last.legacy_flags() {
+                // formatting can only be turned off by a reset, as can a colour which is
+                // replaced by one without a legacy code
+                let flag_cleared = last
+                    .legacy_flags()
+                    .into_iter()
+                    .zip(style.legacy_flags())
+                    .any(|(was, is)| was && !is);
+                let color = Style {
+                    color: style.color.clone(),
+                    ..Style::default()
+                };
+                if flag_cleared
+                    || (last.color.is_some() && color.to_legacy(SECTION_SIGN).is_empty())
+                {
+                    out.push(SECTION_SIGN);
+                    out.push('r');
+                }
+                out.push_str(&style.to_legacy(SECTION_SIGN));
+                last = style.clone();
+            }
+            out.push_str(content);
+        };
+        self.walk_styled(&Style::default(), &mut write);
+        out
+    }
+
+    fn legacy_content(&self) -> Option<&str> {
+        match self {
+            Chat::Literal(text) => Some(text),
+            Chat::Text { text, .. } => Some(text),
+            Chat::Translatable { translatable, .. } => Some(translatable),
+            Chat::Score { score, .. } => Some(&score.name),
+            Chat::Selector { selector, .. } => Some(selector),
+            Chat::Keybind { keybind, .. } => Some(keybind),
+            _ => None,
+        }
+    }
+
+    /// Visits every component of the tree in display order along with the style it is
+    /// displayed in.
+    fn walk_styled<F: FnMut(&Chat, &Style)>(&self, parent: &Style, visit: &mut F) {
+        if let Chat::ChatArr(arr) = self {
+            // the first element of an array is the parent of the others
+            if let Some((first, rest)) = arr.split_first() {
+                first.walk_styled(parent, visit);
+                let parent = match first.base() {
+                    Some(base) => base.style.inherit_legacy(parent),
+                    None => parent.clone(),
+                };
+                for chat in rest {
+                    chat.walk_styled(&parent, visit);
+                }
+            }
+            return;
+        }
+        let style = match self.base() {
+            Some(base) => base.style.inherit_legacy(parent),
+            None => parent.clone(),
+        };
+        visit(self, &style);
+        if let Some(extra) = self.base().and_then(|base| base.extra.as_ref()) {
+            for chat in extra {
+                chat.walk_styled(&style, visit);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "play")]
+const CHAT_FORMATTINGS: [ChatFormatting; 22] = [
+    ChatFormatting::Black {},
+    ChatFormatting::DarkBlue {},
+    ChatFormatting::DarkGreen {},
+    ChatFormatting::DarkAqua {},
+    ChatFormatting::DarkRed {},
+    ChatFormatting::DarkPurple {},
+    ChatFormatting::Gold {},
+    ChatFormatting::Gray {},
+    ChatFormatting::DarkGray {},
+    ChatFormatting::Blue {},
+    ChatFormatting::Green {},
+    ChatFormatting::Aqua {},
+    ChatFormatting::Red {},
+    ChatFormatting::LightPurple {},
+    ChatFormatting::Yellow {},
+    ChatFormatting::White {},
+    ChatFormatting::Obfuscated {},
+    ChatFormatting::Bold {},
+    ChatFormatting::StrikeThrough {},
+    ChatFormatting::Underline {},
+    ChatFormatting::Italic {},
+    ChatFormatting::Reset {},
+];
+
+#[cfg(feature = "play")]
+impl ChatFormatting {
+    fn index(&self) -> usize {
+        CHAT_FORMATTINGS
+            .iter()
+            .position(|other| other == self)
+            .unwrap_or_default()
+    }
+
+    pub fn legacy_code(&self) -> char {
+        let idx = self.index();
+        match LEGACY_COLORS.get(idx) {
+            Some((code, _, _)) => *code,
+            None => LEGACY_FORMATTING_CODES[idx - LEGACY_COLORS.len()],
+        }
+    }
+
+    pub fn from_legacy_code(code: char) -> Option<Self> {
+        let code = code.to_ascii_lowercase();
+        LEGACY_COLORS
+            .iter()
+            .map(|(code, _, _)| *code)
+            .chain(LEGACY_FORMATTING_CODES)
+            .position(|other| other == code)
+            .map(|idx| CHAT_FORMATTINGS[idx])
+    }
+
+    /// The name of the colour as used in a `Style`, or `None` for formats.
+    pub fn color_name(&self) -> Option<&'static str> {
+        LEGACY_COLORS.get(self.index()).map(|(_, name, _)| *name)
+    }
+}
+
+#[cfg(feature = "play")]
+impl Style {
+    pub fn formatting(&mut self, formatting: ChatFormatting) -> &mut Self {
+        self.apply_legacy_code(formatting.legacy_code());
+        self
+    }
+}
+
 pub enum ChatBuilder {
     Literal(String),
     ChatArr(Vec<Chat>),
@@ -730,3 +1386,120 @@ macro_rules! combine {
         $crate::common::chat::ChatBuilder::text("").color($color).append_extra(vec![$($chat),+])
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use crate::common::chat::{strip_legacy_codes, Chat, HoverEvent, SECTION_SIGN};
+
+    #[test]
+    pub fn hover_events() {
+        let uuid = Uuid::parse_str("0b7e9a4c-3f2e-4a36-9a9e-3b5c8f1d2e4f").unwrap();
+        let modern = json!({
+            "text": "",
+            "extra": [
+                {
+                    "text": "item",
+                    "hoverEvent": {
+                        "action": "show_item",
+                        "contents": {"id": "minecraft:diamond_sword", "count": 1, "tag": "{Damage:3}"}
+                    }
+                },
+                {
+                    "text": "entity",
+                    "hoverEvent": {
+                        "action": "show_entity",
+                        "contents": {"type": "minecraft:pig", "id": uuid.to_string(), "name": {"text": "Bob"}}
+                    }
+                },
+                {
+                    "text": "text",
+                    "hoverEvent": {"action": "show_text", "contents": {"text": "hi"}}
+                }
+            ]
+        });
+        let chat: Chat = serde_json::from_value(modern.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&chat).unwrap(), modern);
+
+        let legacy: HoverEvent = serde_json::from_value(json!({
+            "action": "show_item",
+            "value": {"text": "{id:\"minecraft:stone\",Count:2b,tag:{display:{Name:'{\"text\":\"a, b\"}'}}}"}
+        }))
+        .unwrap();
+        assert!(matches!(
+            legacy,
+            HoverEvent::ShowItem { contents } if contents.id == "minecraft:stone"
+                && contents.count == Some(2)
+                && contents.tag.as_deref() == Some("{display:{Name:'{\"text\":\"a, b\"}'}}")
+        ));
+
+        let legacy: HoverEvent = serde_json::from_value(json!({
+            "action": "show_entity",
+            "value": "{type:\"minecraft:pig\",id:\"0b7e9a4c-3f2e-4a36-9a9e-3b5c8f1d2e4f\",name:'{\"text\":\"Bob\"}'}"
+        }))
+        .unwrap();
+        assert!(matches!(
+            &legacy,
+            HoverEvent::ShowEntity { contents } if contents.entity_type == "minecraft:pig"
+                && contents.id == uuid
+        ));
+        assert_eq!(
+            serde_json::to_value(&legacy).unwrap(),
+            json!({
+                "action": "show_entity",
+                "contents": {"type": "minecraft:pig", "id": uuid.to_string(), "name": {"text": "Bob"}}
+            })
+        );
+
+        assert!(serde_json::from_value::<HoverEvent>(json!({
+            "action": "show_entity",
+            "value": "{id:\"0b7e9a4c-3f2e-4a36-9a9e-3b5c8f1d2e4f\",name:'{\"text\":\"Bob\"}'}"
+        }))
+        .is_err());
+
+        let legacy: HoverEvent =
+            serde_json::from_value(json!({"action": "show_text", "value": "hi"})).unwrap();
+        assert!(matches!(legacy, HoverEvent::ShowText { .. }));
+    }
+
+    #[test]
+    pub fn legacy_codes() {
+        let chat = Chat::from_legacy("&aGreen &lbold&r plain &x&F&F&0&0&0&0hex &#00ff00x", '&');
+        assert_eq!(
+            chat.to_legacy(),
+            "\u{a7}aGreen \u{a7}a\u{a7}lbold\u{a7}r plain \u{a7}x\u{a7}f\u{a7}f\u{a7}0\u{a7}0\u{a7}0\u{a7}0hex \u{a7}x\u{a7}0\u{a7}0\u{a7}f\u{a7}f\u{a7}0\u{a7}0x"
+        );
+        assert_eq!(
+            Chat::from_legacy(&chat.to_legacy(), SECTION_SIGN).to_legacy(),
+            chat.to_legacy()
+        );
+        assert_eq!(
+            strip_legacy_codes("\u{a7}cRed \u{a7}zkept", SECTION_SIGN),
+            "Red \u{a7}zkept"
+        );
+
+        let mut nested = Chat::text("a");
+        nested.color("red");
+        let mut child = Chat::text("b");
+        child.bold(true);
+        nested.push_extra(child);
+        nested.push_extra(Chat::text("c"));
+        assert_eq!(nested.to_legacy(), "\u{a7}ca\u{a7}c\u{a7}lb\u{a7}r\u{a7}cc");
+
+        let mut bold = Chat::text("a");
+        bold.color("#123456");
+        bold.bold(true);
+        let mut plain = Chat::text("b");
+        plain.bold(false);
+        bold.push_extra(plain);
+        let mut unknown = Chat::text("c");
+        unknown.color("not_a_color");
+        bold.push_extra(unknown);
+        assert_eq!(
+            bold.to_legacy(),
+            "\u{a7}x\u{a7}1\u{a7}2\u{a7}3\u{a7}4\u{a7}5\u{a7}6\u{a7}la\u{a7}r\u{a7}x\u{a7}1\u{a7}2\u{a7}3\u{a7}4\u{a7}5\u{a7}6b\u{a7}r\u{a7}lc"
+        );
+    }
+}
@@ -0,0 +1,722 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::common::chat::{color_rgb, BaseChat, Chat, ClickEvent, HoverEvent, Style};
+
+const COLOR_ALIASES: [&str; 3] = ["color", "colour", "c"];
+const DECORATIONS: [(&str, &[&str]); 5] = [
+    ("bold", &["b"]),
+    ("italic", &["i", "em"]),
+    ("underlined", &["u"]),
+    ("strikethrough", &["st"]),
+    ("obfuscated", &["obf"]),
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    Text(String),
+    Open {
+        name: String,
+        args: Vec<String>,
+        raw: String,
+    },
+    Close {
+        name: String,
+        raw: String,
+    },
+}
+
+/// What an opening tag resolves to.
+enum Resolved {
+    /// A component inserted in place of the tag.
+    Insert(Chat),
+    /// A styled component which encloses everything up to its closing tag.
+    Open(Chat),
+    /// Recolours the enclosed text from one colour to the next.
+    Gradient(Vec<u32>),
+    Reset,
+}
+
+struct Frame {
+    name: String,
+    node: Chat,
+    children: Vec<Chat>,
+    gradient: Option<Vec<u32>>,
+}
+
+/// A parser for tag based markup in the style of MiniMessage, such as
+/// `<red><bold>Hello</bold> <click:run_command:/spawn>spawn</click>`. <br />
+///
+/// Supported tags are colours (`<red>`, `<#ff0000>`, `<color:red>`), decorations (`<bold>`,
+/// `<!italic>`, ...), `<click:action:value>`, `<hover:show_text:'markup'>`, `<insert:text>`,
+/// `<font:key>`, `<lang:key:args...>`, `<key:keybind>`, `<gradient:colors...>`, `<newline>` and
+/// `<reset>`, plus any registered placeholder. Tags which are not understood are kept as text
+/// and `\<` escapes a tag.
+#[derive(Debug, Clone, Default)]
+pub struct Markup {
+    placeholders: HashMap<String, Chat>,
+}
+
+impl Markup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a component inserted for `<name>`; placeholders take precedence over the
+    /// built-in tags.
+    pub fn placeholder<S: Into<String>, C: Into<Chat>>(mut self, name: S, value: C) -> Self {
+        self.placeholders
+            .insert(name.into().to_lowercase(), value.into());
+        self
+    }
+
+    pub fn parse(&self, input: &str) -> Chat {
+        let mut stack = vec![Frame {
+            name: String::new(),
+            node: Chat::text(""),
+            children: vec![],
+            gradient: None,
+        }];
+        for token in tokenize(input) {
+            match token {
+                Token::Text(text) => push_child(&mut stack, Chat::text(text)),
+                Token::Open { name, args, raw } => match self.resolve(&name, &args) {
+                    Some(Resolved::Insert(chat)) => push_child(&mut stack, chat),
+                    Some(Resolved::Open(node)) => stack.push(Frame {
+                        name: canonical_name(&name),
+                        node,
+                        children: vec![],
+                        gradient: None,
+                    }),
+                    Some(Resolved::Gradient(colors)) => stack.push(Frame {
+                        name: canonical_name(&name),
+                        node: Chat::text(""),
+                        children: vec![],
+                        gradient: Some(colors),
+                    }),
+                    Some(Resolved::Reset) => close_frames(&mut stack, 1),
+                    None => push_child(&mut stack, Chat::text(raw)),
+                },
+                Token::Close { name, raw } => {
+                    let name = canonical_name(&name);
+                    match stack.iter().rposition(|frame| frame.name == name) {
+                        Some(idx) if idx > 0 => close_frames(&mut stack, idx),
+                        _ => push_child(&mut stack, Chat::text(raw)),
+                    }
+                }
+            }
+        }
+        close_frames(&mut stack, 1);
+        let mut children = stack.pop().unwrap().children;
+        match children.len() {
+            0 => Chat::text(""),
+            1 => children.remove(0),
+            _ => Chat::Text {
+                text: String::new(),
+                base: BaseChat {
+                    extra: Some(children),
+                    ..BaseChat::default()
+                },
+            },
+        }
+    }
+
+    fn resolve(&self, name: &str, args: &[String]) -> Option<Resolved> {
+        let lower = name.to_lowercase();
+        if args.is_empty() {
+            if let Some(placeholder) = self.placeholders.get(&lower) {
+                return Some(Resolved::Insert(placeholder.clone()));
+            }
+        }
+        let styled = |func: &dyn Fn(&mut Style)| {
+            let mut node = Chat::text("");
+            node.modify_style(|style| {
+                func(style);
+                style
+            });
+            Some(Resolved::Open(node))
+        };
+
+        if color_rgb(&lower).is_some() && args.is_empty() {
+            return styled(&|style| {
+                style.color(lower.clone());
+            });
+        }
+        if let Some((decoration, negated)) = decoration(&lower) {
+            return styled(&|style| set_decoration(style, decoration, !negated));
+        }
+        match (lower.as_str(), args) {
+            (color, [value]) if COLOR_ALIASES.contains(&color) => {
+                let value = value.to_lowercase();
+                color_rgb(&value)?;
+                styled(&|style| {
+                    style.color(value.clone());
+                })
+            }
+            ("click", [action, value @ ..]) if !value.is_empty() => {
+                let value = value.join(":");
+                let event = match action.to_lowercase().as_str() {
+                    "open_url" => ClickEvent::open_url(value),
+                    "open_file" => ClickEvent::open_file(value),
+                    "run_command" => ClickEvent::run_command(value),
+                    "suggest_command" => ClickEvent::suggest_command(value),
+                    "change_page" => ClickEvent::change_page(value),
+                    "copy_to_clipboard" => ClickEvent::copy_to_clipboard(value),
+                    _ => return None,
+                };
+                styled(&|style| {
+                    style.click_event(event.clone());
+                })
+            }
+            ("hover", [action, value @ ..]) if !value.is_empty() => {
+                let event = match (action.to_lowercase().as_str(), value) {
+                    ("show_text", value) => {
+                        HoverEvent::show_text(Box::new(self.parse(&value.join(":"))))
+                    }
+                    ("show_item", [id, rest @ ..]) => HoverEvent::show_item(
+                        id.clone(),
+                        rest.first().and_then(|count| count.parse().ok()),
+                        rest.get(1).cloned(),
+                    ),
+                    ("show_entity", [entity_type, id, rest @ ..]) => HoverEvent::show_entity(
+                        entity_type.clone(),
+                        Uuid::parse_str(id).ok()?,
+                        rest.first().map(|name| Box::new(self.parse(name))),
+                    ),
+                    _ => return None,
+                };
+                styled(&|style| {
+                    style.hover_event(event.clone());
+                })
+            }
+            ("insert" | "insertion", [value]) => styled(&|style| {
+                style.insertion(value.clone());
+            }),
+            ("font", value) if !value.is_empty() => {
+                let font = value.join(":");
+                styled(&|style| {
+                    style.font(font.clone());
+                })
+            }
+            ("lang" | "tr" | "translate", [key, with @ ..]) => {
+                Some(Resolved::Insert(Chat::translatable(
+                    key.clone(),
+                    (!with.is_empty())
+                        .then(|| with.iter().map(|arg| Box::new(self.parse(arg))).collect()),
+                )))
+            }
+            ("key" | "keybind", [key]) => Some(Resolved::Insert(Chat::keybind(key.clone()))),
+            ("newline" | "br", []) => Some(Resolved::Insert(Chat::text("\n"))),
+            ("gradient", colors) => {
+                let mut stops = colors
+                    .iter()
+                    .map(|color| color_rgb(&color.to_lowercase()))
+                    .collect::<Option<Vec<u32>>>()?;
+                if stops.len() < 2 {
+                    stops = vec![0xffffff, 0x000000];
+                }
+                Some(Resolved::Gradient(stops))
+            }
+            ("reset", []) => Some(Resolved::Reset),
+            _ => None,
+        }
+    }
+
+    /// Writes the component tree as markup which parses back into an equivalent tree.
+    /// Score, selector and NBT components are written as plain text.
+    pub fn serialize(chat: &Chat) -> String {
+        let mut out = String::new();
+        write_markup(chat, &mut out);
+        out
+    }
+}
+
+impl Chat {
+    /// Parses markup without placeholders; see [`Markup`].
+    pub fn from_markup(input: &str) -> Self {
+        Markup::default().parse(input)
+    }
+
+    pub fn to_markup(&self) -> String {
+        Markup::serialize(self)
+    }
+}
+
+fn decoration(name: &str) -> Option<(&'static str, bool)> {
+    let (name, negated) = match name.strip_prefix('!') {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    DECORATIONS
+        .iter()
+        .find(|(decoration, aliases)| *decoration == name || aliases.contains(&name))
+        .map(|(decoration, _)| (*decoration, negated))
+}
+
+fn set_decoration(style: &mut Style, decoration: &str, value: bool) {
+    match decoration {
+        "bold" => style.bold(value),
+        "italic" => style.italic(value),
+        "underlined" => style.underlined(value),
+        "strikethrough" => style.strikethrough(value),
+        _ => style.obfuscated(value),
+    };
+}
+
+/// The name a closing tag has to use, so that `</red>` closes `<color:red>` and `</b>` closes
+/// `<!bold>`.
+fn canonical_name(name: &str) -> String {
+    let lower = name.to_lowercase();
+    if color_rgb(&lower).is_some() || COLOR_ALIASES.contains(&lower.as_str()) {
+        return "color".to_string();
+    }
+    if let Some((decoration, _)) = decoration(&lower) {
+        return decoration.to_string();
+    }
+    match lower.as_str() {
+        "insertion" => "insert".to_string(),
+        _ => lower,
+    }
+}
+
+/// Adds a component to the innermost frame, merging adjacent unstyled text.
+fn push_child(stack: &mut [Frame], chat: Chat) {
+    let children = &mut stack.last_mut().unwrap().children;
+    if let (
+        Some(Chat::Text {
+            text: previous,
+            base: previous_base,
+        }),
+        Chat::Text { text, base },
+    ) = (children.last_mut(), &chat)
+    {
+        if is_plain(previous_base) && is_plain(base) {
+            previous.push_str(text);
+            return;
+        }
+    }
+    children.push(chat);
+}
+
+/// Closes every frame from the given depth, attaching each to its parent.
+fn close_frames(stack: &mut Vec<Frame>, depth: usize) {
+    while stack.len() > depth {
+        let frame = stack.pop().unwrap();
+        let chat = fold(frame);
+        push_child(stack, chat);
+    }
+}
+
+fn fold(frame: Frame) -> Chat {
+    let Frame {
+        mut node,
+        mut children,
+        gradient,
+        ..
+    } = frame;
+    if let Some(stops) = gradient {
+        let total = children.iter().map(text_length).sum::<usize>();
+        let mut index = 0;
+        children = children
+            .into_iter()
+            .map(|child| apply_gradient(child, &stops, total, &mut index))
+            .collect();
+    }
+    let single_text = match children.as_slice() {
+        [Chat::Text { text, base }] if is_plain(base) => Some(text.clone()),
+        _ => None,
+    };
+    if let (Some(single_text), Chat::Text { text, .. }) = (single_text, &mut node) {
+        if text.is_empty() {
+            *text = single_text;
+            children.clear();
+        }
+    }
+    if !children.is_empty() {
+        node.append_extra(children);
+    }
+    node
+}
+
+fn is_plain(base: &BaseChat) -> bool {
+    let style = &base.style;
+    base.extra.is_none()
+        && base.click_event.is_none()
+        && base.hover_event.is_none()
+        && style.color.is_none()
+        && style.bold.is_none()
+        && style.italic.is_none()
+        && style.underlined.is_none()
+        && style.strikethrough.is_none()
+        && style.obfuscated.is_none()
+        && style.insertion.is_none()
+        && style.font.is_none()
+        && style.hover_event.is_none()
+        && style.click_event.is_none()
+}
+
+fn text_length(chat: &Chat) -> usize {
+    match chat {
+        Chat::Literal(text) => text.chars().count(),
+        Chat::Text { text, base } => {
+            text.chars().count() + base.extra.iter().flatten().map(text_length).sum::<usize>()
+        }
+        Chat::ChatArr(arr) => arr.iter().map(text_length).sum(),
+        _ => chat
+            .base()
+            .and_then(|base| base.extra.as_ref())
+            .map(|extra| extra.iter().map(text_length).sum())
+            .unwrap_or_default(),
+    }
+}
+
+fn gradient_color(stops: &[u32], index: usize, total: usize) -> String {
+    let position = if total <= 1 {
+        0.0
+    } else {
+        index as f64 / (total - 1) as f64 * (stops.len() - 1) as f64
+    };
+    let segment = (position.floor() as usize).min(stops.len() - 2);
+    let fraction = position - segment as f64;
+    let channel = |shift: u32| {
+        let from = ((stops[segment] >> shift) & 0xff) as f64;
+        let to = ((stops[segment + 1] >> shift) & 0xff) as f64;
+        (from + (to - from) * fraction).round() as u32
+    };
+    format!(
+        "#{:06x}",
+        (channel(16) << 16) | (channel(8) << 8) | channel(0)
+    )
+}
+
+/// Splits the text of the component into one component per character, coloured along the
+/// gradient. Components with a colour of their own keep it.
+fn apply_gradient(chat: Chat, stops: &[u32], total: usize, index: &mut usize) -> Chat {
+    let (text, mut base) = match chat {
+        Chat::Literal(text) => (text, BaseChat::default()),
+        Chat::Text { text, base } if base.style.color.is_none() => (text, base),
+        chat => {
+            *index += text_length(&chat);
+            return chat;
+        }
+    };
+    let mut characters: Vec<Chat> = text
+        .chars()
+        .map(|c| {
+            let mut chat = Chat::text(c.to_string());
+            chat.color(gradient_color(stops, *index, total));
+            *index += 1;
+            chat
+        })
+        .collect();
+    if let Some(extra) = base.extra.take() {
+        characters.extend(
+            extra
+                .into_iter()
+                .map(|child| apply_gradient(child, stops, total, index)),
+        );
+    }
+    base.extra = Some(characters);
+    Chat::Text {
+        text: String::new(),
+        base,
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        match chars[idx] {
+            '\\' if matches!(chars.get(idx + 1), Some('<' | '\\')) => {
+                text.push(chars[idx + 1]);
+                idx += 2;
+            }
+            '<' => match read_tag(&chars[idx..]) {
+                Some((token, consumed)) => {
+                    if !text.is_empty() {
+                        tokens.push(Token::Text(std::mem::take(&mut text)));
+                    }
+                    tokens.push(token);
+                    idx += consumed;
+                }
+                None => {
+                    text.push('<');
+                    idx += 1;
+                }
+            },
+            c => {
+                text.push(c);
+                idx += 1;
+            }
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    tokens
+}
+
+/// Reads the tag at the start of the input, returning it with the number of characters it
+/// spans.
+fn read_tag(chars: &[char]) -> Option<(Token, usize)> {
+    let mut parts = vec![String::new()];
+    let mut quote = None;
+    let mut idx = 1;
+    loop {
+        let c = *chars.get(idx)?;
+        idx += 1;
+        match (quote, c) {
+            (Some(_), '\\') if matches!(chars.get(idx), Some('\'' | '"' | '\\')) => {
+                parts.last_mut().unwrap().push(chars[idx]);
+                idx += 1;
+            }
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => parts.last_mut().unwrap().push(c),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ':') => parts.push(String::new()),
+            (None, '>') => break,
+            (None, '<') => return None,
+            (None, c) => parts.last_mut().unwrap().push(c),
+        }
+    }
+    let raw: String = chars[..idx].iter().collect();
+    let mut name = parts.remove(0);
+    if let Some(stripped) = name.strip_prefix('/') {
+        let name = stripped.to_string();
+        return valid_name(&name).then_some((Token::Close { name, raw }, idx));
+    }
+    if parts.is_empty() {
+        if let Some(stripped) = name.strip_suffix('/') {
+            name = stripped.to_string();
+        }
+    } else if let Some(last) = parts.last_mut() {
+        if last.ends_with('/') && !raw.ends_with("'/>") && !raw.ends_with("\"/>") {
+            last.pop();
+        }
+    }
+    valid_name(&name).then_some((
+        Token::Open {
+            name,
+            args: parts,
+            raw,
+        },
+        idx,
+    ))
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '#' | '!'))
+}
+
+fn quote(arg: &str) -> String {
+    let mut out = String::from("'");
+    for c in arg.chars() {
+        if matches!(c, '\'' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('\'');
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        if matches!(c, '<' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// The opening and closing tags recreating the style.
+fn style_tags(style: &Style) -> Vec<(String, String)> {
+    let mut tags = Vec::new();
+    if let Some(color) = &style.color {
+        tags.push((format!("<{}>", color), format!("</{}>", color)));
+    }
+    for (name, value) in [
+        ("bold", style.bold),
+        ("italic", style.italic),
+        ("underlined", style.underlined),
+        ("strikethrough", style.strikethrough),
+        ("obfuscated", style.obfuscated),
+    ] {
+        if let Some(value) = value {
+            let prefix = if value { "" } else { "!" };
+            tags.push((format!("<{}{}>", prefix, name), format!("</{}>", name)));
+        }
+    }
+    if let Some(font) = &style.font {
+        tags.push((format!("<font:{}>", quote(font)), "</font>".to_string()));
+    }
+    if let Some(insertion) = &style.insertion {
+        tags.push((
+            format!("<insert:{}>", quote(insertion)),
+            "</insert>".to_string(),
+        ));
+    }
+    if let Some(click_event) = &style.click_event {
+        let (action, value) = match click_event {
+            ClickEvent::OpenUrl { value } => ("open_url", value),
+            ClickEvent::OpenFile { value } => ("open_file", value),
+            ClickEvent::RunCommand { value } => ("run_command", value),
+            ClickEvent::SuggestCommand { value } => ("suggest_command", value),
+            ClickEvent::ChangePage { value } => ("change_page", value),
+            ClickEvent::CopyToClipboard { value } => ("copy_to_clipboard", value),
+        };
+        tags.push((
+            format!("<click:{}:{}>", action, quote(value)),
+            "</click>".to_string(),
+        ));
+    }
+    if let Some(hover_event) = &style.hover_event {
+        let args = match hover_event {
+            HoverEvent::ShowText { contents } => {
+                format!("show_text:{}", quote(&contents.to_markup()))
+            }
+            HoverEvent::ShowItem { contents } => {
+                let mut args = format!("show_item:{}", quote(&contents.id));
+                if let Some(count) = contents.count {
+                    args.push_str(&format!(":{}", count));
+                    if let Some(tag) = &contents.tag {
+                        args.push_str(&format!(":{}", quote(tag)));
+                    }
+                }
+                args
+            }
+            HoverEvent::ShowEntity { contents } => {
+                let mut args = format!(
+                    "show_entity:{}:{}",
+                    quote(&contents.entity_type),
+                    contents.id
+                );
+                if let Some(name) = &contents.name {
+                    args.push_str(&format!(":{}", quote(&name.to_markup())));
+                }
+                args
+            }
+        };
+        tags.push((format!("<hover:{}>", args), "</hover>".to_string()));
+    }
+    tags
+}
+
+fn write_markup(chat: &Chat, out: &mut String) {
+    let base = match chat {
+        Chat::Literal(text) => {
+            out.push_str(&escape(text));
+            return;
+        }
+        Chat::ChatArr(arr) => {
+            arr.iter().for_each(|chat| write_markup(chat, out));
+            return;
+        }
+        chat => chat.base().unwrap(),
+    };
+    let tags = style_tags(&base.style);
+    for (open, _) in &tags {
+        out.push_str(open);
+    }
+    match chat {
+        Chat::Translatable {
+            translatable, with, ..
+        } => {
+            out.push_str("<lang:");
+            out.push_str(&quote(translatable));
+            for arg in with.iter().flatten() {
+                out.push(':');
+                out.push_str(&quote(&arg.to_markup()));
+            }
+            out.push('>');
+        }
+        Chat::Keybind { keybind, .. } => {
+            out.push_str(&format!("<key:{}>", quote(keybind)));
+        }
+        chat => {
+            if let Some(content) = chat.legacy_content() {
+                out.push_str(&escape(content));
+            }
+        }
+    }
+    for chat in base.extra.iter().flatten() {
+        write_markup(chat, out);
+    }
+    for (_, close) in tags.iter().rev() {
+        out.push_str(close);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::chat::markup::Markup;
+    use crate::common::chat::Chat;
+
+    fn json(chat: &Chat) -> serde_json::Value {
+        serde_json::to_value(chat).unwrap()
+    }
+
+    #[test]
+    pub fn markup_round_trip() {
+        let chat = Chat::from_markup(
+            "<red><bold>Hello</bold> <click:run_command:/spawn>spawn</click></red> \\<b> \
+             <hover:show_text:'<green>Hi, <player>!'><lang:chat.type.text:'<yellow>a':b></hover>",
+        );
+        let expected = serde_json::json!({
+            "text": "",
+            "extra": [
+                {
+                    "text": "",
+                    "color": "red",
+                    "extra": [
+                        {"text": "Hello", "bold": true},
+                        {"text": " "},
+                        {"text": "spawn", "clickEvent": {"action": "run_command", "value": "/spawn"}}
+                    ]
+                },
+                {"text": " <b> "},
+                {
+                    "text": "",
+                    "hoverEvent": {
+                        "action": "show_text",
+                        "contents": {"text": "Hi, <player>!", "color": "green"}
+                    },
+                    "extra": [
+                        {"translate": "chat.type.text", "with": [{"text": "a", "color": "yellow"}, {"text": "b"}]}
+                    ]
+                }
+            ]
+        });
+        assert_eq!(json(&chat), expected);
+        assert_eq!(json(&Chat::from_markup(&chat.to_markup())), expected);
+
+        let chat = Markup::new()
+            .placeholder("player", Chat::text("Steve"))
+            .parse("<gradient:#ff0000:#0000ff>a<player></gradient>");
+        assert_eq!(
+            json(&chat),
+            serde_json::json!({
+                "text": "",
+                "extra": [
+                    {"text": "", "extra": [
+                        {"text": "a", "color": "#ff0000"},
+                        {"text": "S", "color": "#cc0033"},
+                        {"text": "t", "color": "#990066"},
+                        {"text": "e", "color": "#660099"},
+                        {"text": "v", "color": "#3300cc"},
+                        {"text": "e", "color": "#0000ff"}
+                    ]}
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::common::chat::{color_rgb, Chat, Style};
+
+/// The SGR codes of the named colours in a 16 colour terminal.
+const ANSI_COLORS: [(&str, u8); 16] = [
+    ("black", 30),
+    ("dark_blue", 34),
+    ("dark_green", 32),
+    ("dark_aqua", 36),
+    ("dark_red", 31),
+    ("dark_purple", 35),
+    ("gold", 33),
+    ("gray", 37),
+    ("dark_gray", 90),
+    ("blue", 94),
+    ("green", 92),
+    ("aqua", 96),
+    ("red", 91),
+    ("light_purple", 95),
+    ("yellow", 93),
+    ("white", 97),
+];
+const ANSI_RESET: &str = "\u{1b}[0m";
+
+/// A table of translations in the format of the vanilla language files such as `en_us.json`.
+#[derive(Debug, Clone, Default)]
+pub struct Language {
+    translations: HashMap<String, String>,
+}
+
+impl Language {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_json(slice: &[u8]) -> serde_json::Result<Self> {
+        Ok(Self {
+            translations: serde_json::from_slice(slice)?,
+        })
+    }
+
+    /// Loads a language file, for example `assets/minecraft/lang/en_us.json` extracted from the
+    /// client jar.
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Self::from_json(&std::fs::read(path)?)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        self.translations.insert(key.into(), value.into());
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.translations.get(key).map(String::as_str)
+    }
+
+    /// Translates the key and substitutes `%s` and `%1$s` with the given arguments. Unknown
+    /// keys are returned as is, like vanilla does.
+    pub fn format<S: AsRef<str>>(&self, key: &str, args: &[S]) -> String {
+        let template = self.get(key).unwrap_or(key);
+        match parse_template(template, args.len()) {
+            Some(pieces) => pieces
+                .into_iter()
+                .map(|piece| match piece {
+                    Piece::Text(text) => text,
+                    Piece::Arg(idx) => args[idx].as_ref().to_string(),
+                })
+                .collect(),
+            None => template.to_string(),
+        }
+    }
+
+    /// Renders the text of the component tree without any formatting.
+    pub fn render_plain(&self, chat: &Chat) -> String {
+        let mut segments = Vec::new();
+        self.segments(chat, &Style::default(), &mut segments);
+        segments.into_iter().map(|(_, text)| text).collect()
+    }
+
+    /// Renders the component tree for a terminal using ANSI escape codes. Named colours use
+    /// the 16 standard colours while hex colours require a terminal supporting 24 bit colour.
+    pub fn render_ansi(&self, chat: &Chat) -> String {
+        let mut segments = Vec::new();
+        self.segments(chat, &Style::default(), &mut segments);
+        let mut out = String::new();
+        let mut last = String::new();
+        for (style, text) in segments {
+            let codes = ansi_codes(&style);
+            if codes != last {
+                out.push_str(ANSI_RESET);
+                out.push_str(&codes);
+                last = codes;
+            }
+            out.push_str(&text);
+        }
+        if !last.is_empty() {
+            out.push_str(ANSI_RESET);
+        }
+        out
+    }
+
+    /// Collects the rendered text of the tree with the style it is displayed in.
+    fn segments(&self, chat: &Chat, parent: &Style, out: &mut Vec<(Style, String)>) {
+        chat.walk_styled(parent, &mut |chat, style| match chat {
+            Chat::Translatable {
+                translatable, with, ..
+            } => {
+                let with = with.as_deref().unwrap_or_default();
+                let template = self.get(translatable).unwrap_or(translatable);
+                match parse_template(template, with.len()) {
+                    Some(pieces) => {
+                        for piece in pieces {
+                            match piece {
+                                Piece::Text(text) => push_segment(out, style, &text),
+                                Piece::Arg(idx) => self.segments(&with[idx], style, out),
+                            }
+                        }
+                    }
+                    None => push_segment(out, style, template),
+                }
+            }
+            Chat::Keybind { keybind, .. } => {
+                push_segment(out, style, self.get(keybind).unwrap_or(keybind))
+            }
+            Chat::Score { score, .. } => {
+                if let Some(value) = &score.value {
+                    push_segment(out, style, value);
+                }
+            }
+            Chat::NbtContents { .. } => (),
+            // selectors are resolved by the server before sending; an unresolved one is shown
+            // as is
+            chat => {
+                if let Some(content) = chat.legacy_content() {
+                    push_segment(out, style, content);
+                }
+            }
+        });
+    }
+}
+
+fn push_segment(out: &mut Vec<(Style, String)>, style: &Style, text: &str) {
+    if !text.is_empty() {
+        out.push((style.clone(), text.to_string()));
+    }
+}
+
+enum Piece {
+    Text(String),
+    Arg(usize),
+}
+
+/// Splits a translation into text and argument references, following vanilla's
+/// `%(?:(\d+)\$)?([A-Za-z%]|$)` format. Returns `None` for malformed templates or references
+/// to missing arguments.
+fn parse_template(template: &str, args: usize) -> Option<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut next_arg = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        let mut digits = String::new();
+        while let Some(digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+            digits.push(*digit);
+            chars.next();
+        }
+        let explicit = if digits.is_empty() {
+            None
+        } else if chars.next_if_eq(&'$').is_some() {
+            Some(digits.parse::<usize>().ok()?.checked_sub(1)?)
+        } else {
+            return None;
+        };
+        match (explicit, chars.next()) {
+            (None, Some('%')) => text.push('%'),
+            (explicit, Some('s')) => {
+                let idx = match explicit {
+                    Some(idx) => idx,
+                    None => {
+                        next_arg += 1;
+                        next_arg - 1
+                    }
+                };
+                if idx >= args {
+                    return None;
+                }
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Arg(idx));
+            }
+            _ => return None,
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Some(pieces)
+}
+
+fn ansi_codes(style: &Style) -> String {
+    let mut codes = Vec::new();
+    if let Some(color) = &style.color {
+        match ANSI_COLORS.iter().find(|(name, _)| *name == color.as_str()) {
+            Some((_, code)) => codes.push(code.to_string()),
+            None => {
+                if let Some(rgb) = color_rgb(color) {
+                    codes.push(format!(
+                        "38;2;{};{};{}",
+                        (rgb >> 16) & 0xff,
+                        (rgb >> 8) & 0xff,
+                        rgb & 0xff
+                    ));
+                }
+            }
+        }
+    }
+    for (enabled, code) in [
+        (style.bold, "1"),
+        (style.italic, "3"),
+        (style.underlined, "4"),
+        (style.strikethrough, "9"),
+    ] {
+        if enabled.unwrap_or(false) {
+            codes.push(code.to_string());
+        }
+    }
+    if codes.is_empty() {
+        String::new()
+    } else {
+        format!("\u{1b}[{}m", codes.join(";"))
+    }
+}
+
+impl Chat {
+    /// The text of the component without formatting; translatable components show their key.
+    pub fn to_plain_string(&self) -> String {
+        Language::default().render_plain(self)
+    }
+
+    pub fn to_plain_string_with(&self, language: &Language) -> String {
+        language.render_plain(self)
+    }
+
+    /// The component formatted for a terminal; see [`Language::render_ansi`].
+    pub fn to_ansi_string(&self) -> String {
+        Language::default().render_ansi(self)
+    }
+
+    pub fn to_ansi_string_with(&self, language: &Language) -> String {
+        language.render_ansi(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::chat::render::Language;
+    use crate::common::chat::{Chat, Score};
+
+    #[test]
+    pub fn render_translations() {
+        let language = Language::from_json(
+            br#"{
+                "chat.type.text": "<%s> %s",
+                "commands.swap": "%2$s before %1$s at 100%%",
+                "key.jump": "Jump",
+                "broken": "%d items"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            language.format("commands.swap", &["a", "b"]),
+            "b before a at 100%"
+        );
+        assert_eq!(language.format("broken", &["1"]), "%d items");
+        assert_eq!(language.format("missing.key", &["1"]), "missing.key");
+
+        let mut name = Chat::text("Steve");
+        name.color("gold");
+        let mut chat = Chat::translatable(
+            "chat.type.text",
+            Some(vec![Box::new(name), Box::new(Chat::text("hello"))]),
+        );
+        chat.bold(true);
+        chat.push_extra(Chat::text(" "));
+        chat.push_extra(Chat::keybind("key.jump"));
+        chat.push_extra(Chat::text(" "));
+        chat.push_extra(Chat::selector("@p", None));
+        chat.push_extra(Chat::score(Score::new("Alex", "kills")));
+        chat.push_extra(Chat::score(Score::new("Alex", "kills").with_value("3")));
+
+        assert_eq!(chat.to_plain_string(), "chat.type.text key.jump @p3");
+        assert_eq!(
+            chat.to_plain_string_with(&language),
+            "<Steve> hello Jump @p3"
+        );
+        assert_eq!(
+            chat.to_ansi_string_with(&language),
+            "\u{1b}[0m\u{1b}[1m<\u{1b}[0m\u{1b}[33;1mSteve\u{1b}[0m\u{1b}[1m> hello Jump @p3\u{1b}[0m"
+        );
+    }
+}
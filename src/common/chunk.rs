@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 
 use drax::nbt::{EnsuredCompoundTag, Tag};
 use drax::prelude::{
@@ -10,18 +11,84 @@ use drax::transport::packet::option::Maybe;
 use drax::{components, err_explain, throw_explain, PinnedLivelyResult};
 
 use crate::common::bit_storage::{BitSetValidationError, BitStorage};
-use crate::common::play::ceil_log_2;
+use crate::common::chunk::edit::BlockEntity;
+use crate::common::chunk::light::ChunkLight;
+use crate::common::play::{ceil_log_2, BlockPos};
+use crate::common::registry::{RegistryKey, GLOBAL_REGISTRIES};
+
+pub mod anvil;
+pub mod edit;
+pub mod light;
+pub mod schematic;
+
+static AIR_STATES: std::sync::LazyLock<Vec<i32>> = std::sync::LazyLock::new(|| {
+    ["minecraft:air", "minecraft:cave_air", "minecraft:void_air"]
+        .iter()
+        .filter_map(|key| GLOBAL_REGISTRIES.blocks.default_state_id(key))
+        .collect()
+});
+
+/// Whether the state is one of the air blocks, which do not count towards a section's blocks.
+pub fn is_air_state(state_id: i32) -> bool {
+    state_id == 0 || AIR_STATES.contains(&state_id)
+}
+
+// blocks filled with water without being waterlogged
+const WATER_FILLED: &[&str] = &[
+    "minecraft:seagrass",
+    "minecraft:tall_seagrass",
+    "minecraft:kelp",
+    "minecraft:kelp_plant",
+    "minecraft:bubble_column",
+];
+
+static MOTION_BLOCKING_STATES: std::sync::LazyLock<Vec<bool>> = std::sync::LazyLock::new(|| {
+    // the blocks of 1.19.3 whose material does not block motion
+    let non_motion_blocking: HashSet<String> =
+        serde_json::from_slice(include_bytes!("./registry/non_motion_blocking.json"))
+            .expect("bundled non_motion_blocking.json is a list of block names");
+    let blocks = &GLOBAL_REGISTRIES.blocks;
+    let mut table = Vec::new();
+    for block in blocks.blocks() {
+        let fluid = matches!(block.key(), "minecraft:water" | "minecraft:lava")
+            || WATER_FILLED.contains(&block.key());
+        let blocks_motion = fluid || !non_motion_blocking.contains(block.key());
+        for state_id in block.min_state_id()..=block.max_state_id() {
+            let waterlogged = blocks
+                .state(state_id)
+                .map_or(false, |state| state.get("waterlogged") == Some("true"));
+            if table.len() <= state_id as usize {
+                table.resize(state_id as usize + 1, false);
+            }
+            table[state_id as usize] = blocks_motion || waterlogged;
+        }
+    }
+    table
+});
+
+/// Whether the state counts towards the `MOTION_BLOCKING` heightmap; that is blocks which
+/// block motion or contain a fluid.
+pub fn is_motion_blocking(state_id: i32) -> bool {
+    !is_air_state(state_id)
+        && MOTION_BLOCKING_STATES
+            .get(state_id as usize)
+            .copied()
+            .unwrap_or(true)
+}
 
 pub enum Index {
-    NewSize(u8),
+    NewSize(usize),
     CurrentIndex(i32),
 }
 
 impl Index {
-    pub fn current(self) -> i32 {
+    pub fn current(self) -> std::result::Result<i32, BitSetValidationError> {
         match self {
-            Index::NewSize(_) => panic!("Unexpected resize."),
-            Index::CurrentIndex(idx) => idx,
+            Index::NewSize(size) => Err(BitSetValidationError(format!(
+                "Unexpected resize to {} entries.",
+                size
+            ))),
+            Index::CurrentIndex(idx) => Ok(idx),
         }
     }
 }
@@ -71,6 +138,25 @@ impl Strategy {
         }
     }
 
+    /// The bits per entry vanilla uses for a palette holding `size` distinct values; sizes
+    /// beyond the indirect limit use the direct (global) palette.
+    pub const fn bits_for_palette_size(self, size: usize) -> i32 {
+        let bits = ceil_log_2(size as i32);
+        match self {
+            Strategy::Section => match size {
+                0 | 1 => 0,
+                _ if bits <= 4 => 4,
+                _ if bits <= 8 => bits,
+                _ => Strategy::SECTION_DIRECT_ENTRY_SIZE,
+            },
+            Strategy::Biome => match size {
+                0 | 1 => 0,
+                _ if bits <= 3 => bits,
+                _ => Strategy::BIOME_DIRECT_ENTRY_SIZE,
+            },
+        }
+    }
+
     pub const fn retrieve_index(self, x: i32, y: i32, z: i32) -> i32 {
         let bits = match self {
             Strategy::Section => Strategy::SECTION_SIZE_BITS,
@@ -89,11 +175,19 @@ pub enum Palette {
 }
 
 impl Palette {
-    fn get(&self, id_index: i32) -> i32 {
+    fn get(&self, id_index: i32) -> std::result::Result<i32, BitSetValidationError> {
         match self {
-            Palette::SingleValue { block_type_id } => *block_type_id,
-            Palette::Indirect { palette, .. } => palette[id_index as usize].clone(),
-            Palette::Direct => id_index.into(),
+            Palette::SingleValue { block_type_id } => Ok(*block_type_id),
+            Palette::Indirect { palette, .. } => {
+                palette.get(id_index as usize).copied().ok_or_else(|| {
+                    BitSetValidationError(format!(
+                        "Palette index {} out of bounds for {} entries.",
+                        id_index,
+                        palette.len()
+                    ))
+                })
+            }
+            Palette::Direct => Ok(id_index),
         }
     }
 
@@ -112,7 +206,7 @@ impl Palette {
                         return Index::CurrentIndex(index as i32);
                     }
                 }
-                return Index::NewSize(palette.len() as u8 + 1);
+                return Index::NewSize(palette.len() + 1);
             }
             Palette::Direct => Index::CurrentIndex(block_id.into()),
         }
@@ -141,24 +235,107 @@ pub struct PaletteContainer {
 }
 
 impl PaletteContainer {
+    /// Creates a container where every entry holds the same value.
+    pub fn single(strategy: Strategy, value: i32) -> Self {
+        Self {
+            bits_per_entry: 0,
+            palette: Palette::SingleValue {
+                block_type_id: value,
+            },
+            storage: BitStorage::ZeroStorage {
+                size: strategy.locked_entry_count(),
+                raw: vec![],
+            },
+        }
+    }
+
+    /// Creates a container holding the given values, one per entry in index order, with the
+    /// palette vanilla would pick for them.
+    pub fn from_values(
+        strategy: Strategy,
+        values: &[i32],
+    ) -> std::result::Result<Self, BitSetValidationError> {
+        if values.len() != strategy.locked_entry_count() as usize {
+            return Err(BitSetValidationError(format!(
+                "Expected {} values but got {}.",
+                strategy.locked_entry_count(),
+                values.len()
+            )));
+        }
+        let mut palette = Vec::new();
+        let mut indexes = HashMap::new();
+        for value in values {
+            indexes.entry(*value).or_insert_with(|| {
+                palette.push(*value);
+                palette.len() as i32 - 1
+            });
+        }
+        let bits = strategy.bits_for_palette_size(palette.len());
+        if bits == 0 {
+            return Ok(Self::single(
+                strategy,
+                palette.first().copied().unwrap_or(0),
+            ));
+        }
+        let direct = bits == strategy.entry_size();
+        let mut storage = BitStorage::new(strategy.locked_entry_count(), bits);
+        for (idx, value) in values.iter().enumerate() {
+            storage.set(idx as i32, if direct { *value } else { indexes[value] })?;
+        }
+        Ok(Self {
+            bits_per_entry: bits as u8,
+            palette: if direct {
+                Palette::Direct
+            } else {
+                Palette::Indirect { palette }
+            },
+            storage,
+        })
+    }
+
+    /// Returns the value of every entry in index order.
+    pub fn values(
+        &self,
+        strategy: Strategy,
+    ) -> std::result::Result<Vec<i32>, BitSetValidationError> {
+        (0..strategy.locked_entry_count())
+            .map(|idx| self.get(idx))
+            .collect()
+    }
+
+    /// Rebuilds the palette from the values still in use, dropping stale entries and picking
+    /// the smallest bits per entry; uniform containers become a single value.
+    pub fn compact(
+        &mut self,
+        strategy: Strategy,
+    ) -> std::result::Result<(), BitSetValidationError> {
+        *self = Self::from_values(strategy, &self.values(strategy)?)?;
+        Ok(())
+    }
+
     pub fn get(&self, index: i32) -> std::result::Result<i32, BitSetValidationError> {
         let out = self.storage.get(index)?;
-        Ok(self.palette.get(out))
+        self.palette.get(out)
     }
 
-    pub fn copy_to_new_linear(&self, new_value: i32) -> Palette {
+    pub fn copy_to_new_linear(
+        &self,
+        new_value: i32,
+    ) -> std::result::Result<Palette, BitSetValidationError> {
         match &self.palette {
-            Palette::SingleValue { block_type_id } => Palette::Indirect {
+            Palette::SingleValue { block_type_id } => Ok(Palette::Indirect {
                 palette: vec![*block_type_id, new_value],
-            },
+            }),
             Palette::Indirect { palette, .. } => {
                 let mut new_palette = palette.clone();
                 new_palette.push(new_value);
-                return Palette::Indirect {
+                return Ok(Palette::Indirect {
                     palette: new_palette,
-                };
+                });
             }
-            Palette::Direct => unreachable!(),
+            Palette::Direct => Err(BitSetValidationError(
+                "Direct palettes cannot be copied to a linear palette.".to_string(),
+            )),
         }
     }
 
@@ -169,21 +346,23 @@ impl PaletteContainer {
     ) -> std::result::Result<i32, BitSetValidationError> {
         match self.palette.id_for(block_id) {
             Index::NewSize(new_size) => {
-                let bits_per_entry = strategy.bit_size(new_size);
-                let new_palette = match new_size {
-                    0 | 1 => unreachable!(),
-                    2 | 3 => self.copy_to_new_linear(block_id),
-                    4 if matches!(strategy, Strategy::Section) => self.copy_to_new_linear(block_id),
-                    x if x <= 8 && matches!(strategy, Strategy::Section) => {
-                        self.copy_to_new_linear(block_id)
-                    }
-                    _ => Palette::Direct,
+                if new_size < 2 {
+                    return Err(BitSetValidationError(format!(
+                        "Unexpected resize to {} entries.",
+                        new_size
+                    )));
+                }
+                let bits_per_entry = strategy.bits_for_palette_size(new_size);
+                let new_palette = if bits_per_entry == strategy.entry_size() {
+                    Palette::Direct
+                } else {
+                    self.copy_to_new_linear(block_id)?
                 };
                 let mut new_bitset = BitStorage::new(strategy.locked_entry_count(), bits_per_entry);
                 for idx in 0..self.storage.size() {
                     let out = self.storage.get(idx)?;
                     if matches!(new_palette, Palette::Direct) {
-                        new_bitset.set(idx, self.palette.get(out))?;
+                        new_bitset.set(idx, self.palette.get(out)?)?;
                     } else {
                         new_bitset.set(idx, out)?;
                     }
@@ -191,7 +370,7 @@ impl PaletteContainer {
                 self.bits_per_entry = bits_per_entry as u8;
                 self.palette = new_palette;
                 self.storage = new_bitset;
-                Ok(self.palette.id_for(block_id).current())
+                self.palette.id_for(block_id).current()
             }
             Index::CurrentIndex(map) => Ok(map),
         }
@@ -339,7 +518,22 @@ impl ChunkSection {
     }
 
     fn decrement_non_empty_block_count(&mut self) {
-        self.block_count -= 1;
+        self.block_count = self.block_count.saturating_sub(1);
+    }
+
+    /// The number of blocks in the section which are not air.
+    pub fn block_count(&self) -> u16 {
+        self.block_count
+    }
+
+    fn recalculate_block_count(&mut self) -> std::result::Result<(), BitSetValidationError> {
+        self.block_count = self
+            .states
+            .values(Strategy::Section)?
+            .into_iter()
+            .filter(|state_id| !is_air_state(*state_id))
+            .count() as u16;
+        Ok(())
     }
 
     fn get_block_id(
@@ -359,7 +553,25 @@ impl ChunkSection {
         block_id: i32,
     ) -> std::result::Result<i32, BitSetValidationError> {
         let state_index = Strategy::Section.retrieve_index(x, y, z);
-        self.states.set(Strategy::Section, state_index, block_id)
+        let previous = self.states.get(state_index)?;
+        if previous != block_id {
+            self.states.set(Strategy::Section, state_index, block_id)?;
+            match (is_air_state(previous), is_air_state(block_id)) {
+                (true, false) => self.increment_non_empty_block_count(),
+                (false, true) => self.decrement_non_empty_block_count(),
+                _ => (),
+            }
+        }
+        Ok(previous)
+    }
+
+    fn compact(&mut self) -> std::result::Result<(), BitSetValidationError> {
+        self.states.compact(Strategy::Section)?;
+        self.biomes.compact(Strategy::Biome)
+    }
+
+    fn biome_index(x: i32, y: i32, z: i32) -> i32 {
+        Strategy::Biome.retrieve_index((x & 0xF) >> 2, (y & 0xF) >> 2, (z & 0xF) >> 2)
     }
 
     fn rewrite_plane(
@@ -376,7 +588,8 @@ impl ChunkSection {
                 indexes.push(idx);
             }
         }
-        self.states.set_all(Strategy::Section, indexes, block_id)
+        self.states.set_all(Strategy::Section, indexes, block_id)?;
+        self.recalculate_block_count()
     }
 }
 
@@ -436,14 +649,7 @@ impl HeightMaps {
         Ok(())
     }
 
-    async fn decode<R: AsyncRead + Unpin + Send + Sync + ?Sized>(
-        read: &mut R,
-        height: i32,
-    ) -> Result<Self>
-    where
-        Self: Sized,
-    {
-        let tag = EnsuredCompoundTag::<0>::decode(&mut (), read).await?;
+    fn from_tag(tag: Option<Tag>, height: i32) -> Result<Self> {
         match tag {
             Some(Tag::CompoundTag(tag)) => {
                 let mut world_surface = None;
@@ -463,9 +669,9 @@ impl HeightMaps {
                     };
                 }
                 for (k, v) in &tag {
-                    if format!("WORLD_SURFACE").eq(k) {
+                    if k == "WORLD_SURFACE" {
                         assign_inner!(world_surface, v, height);
-                    } else if format!("MOTION_BLOCKING").eq(k) {
+                    } else if k == "MOTION_BLOCKING" {
                         assign_inner!(motion_blocking, v, height);
                     }
                 }
@@ -497,6 +703,16 @@ impl HeightMaps {
 }
 
 impl HeightMaps {
+    fn empty(height: i32) -> Self {
+        let mut height_maps = HeightMaps {
+            world_surface: BitStorage::new(256, ceil_log_2(height + 1)),
+            motion_blocking: BitStorage::new(256, ceil_log_2(height + 1)),
+            cached_compound_tag: None,
+        };
+        height_maps.cache_compound_tag();
+        height_maps
+    }
+
     pub(crate) fn cache_compound_tag(&mut self) {
         let mut tag = Vec::new();
         tag.push((
@@ -514,33 +730,20 @@ impl HeightMaps {
         x + (z * 16)
     }
 
-    fn get_first_available(
-        bit_set: &BitStorage,
-        index: i32,
-    ) -> std::result::Result<i32, BitSetValidationError> {
-        bit_set.get(index).map(|x| x + DEFAULT_WORLD_MIN)
+    fn storage(&self, motion_blocking: bool) -> &BitStorage {
+        if motion_blocking {
+            &self.motion_blocking
+        } else {
+            &self.world_surface
+        }
     }
 
-    fn update_inner(
-        &mut self,
-        x: i32,
-        y: i32,
-        z: i32,
-        new_block: i32,
-    ) -> std::result::Result<(), BitSetValidationError> {
-        let index = Self::get_index(x, z);
-        let opaque = new_block != 0;
-
-        for bit_set in vec![&mut self.world_surface, &mut self.motion_blocking] {
-            let first_available = Self::get_first_available(&bit_set, index)?;
-            if y > first_available + 1 && opaque {
-                bit_set.set(index, (y + 1) - DEFAULT_WORLD_MIN)?;
-            }
+    fn storage_mut(&mut self, motion_blocking: bool) -> &mut BitStorage {
+        if motion_blocking {
+            &mut self.motion_blocking
+        } else {
+            &mut self.world_surface
         }
-
-        self.cache_compound_tag();
-
-        Ok(())
     }
 }
 
@@ -552,6 +755,8 @@ pub struct Chunk {
     chunk_z: i32,
     height_maps: HeightMaps,
     chunk_sections: Vec<ChunkSection>,
+    light: ChunkLight,
+    compact_on_encode: bool,
 }
 
 impl PartialEq for Chunk {
@@ -563,12 +768,63 @@ impl PartialEq for Chunk {
 }
 
 const BLOCKS_PER_SECTION: i32 = 16;
-const DEFAULT_WORLD_MIN: i32 = -64;
-const DEFAULT_WORLD_HEIGHT: i32 = 320;
+
+/// The vertical extent of a dimension, as described by the `min_y` and `height` of its
+/// dimension type.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct WorldHeight {
+    min_y: i32,
+    height: i32,
+}
+
+impl Default for WorldHeight {
+    fn default() -> Self {
+        Self::OVERWORLD
+    }
+}
+
+impl WorldHeight {
+    pub const OVERWORLD: WorldHeight = WorldHeight {
+        min_y: -64,
+        height: 384,
+    };
+
+    /// Both the minimum y and the height have to be multiples of 16.
+    pub fn new(min_y: i32, height: i32) -> std::result::Result<Self, BitSetValidationError> {
+        if min_y % BLOCKS_PER_SECTION != 0 || height % BLOCKS_PER_SECTION != 0 || height <= 0 {
+            return Err(BitSetValidationError(format!(
+                "World height {} from {} is not made of whole sections.",
+                height, min_y
+            )));
+        }
+        Ok(Self { min_y, height })
+    }
+
+    pub const fn min_y(&self) -> i32 {
+        self.min_y
+    }
+
+    pub const fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// The first y above the world.
+    pub const fn max_y(&self) -> i32 {
+        self.min_y + self.height
+    }
+
+    pub const fn sections(&self) -> usize {
+        (self.height / BLOCKS_PER_SECTION) as usize
+    }
+}
 
 impl Chunk {
     pub fn new(x: i32, z: i32) -> Self {
-        Self::using_world_height(x, z, DEFAULT_WORLD_MIN, DEFAULT_WORLD_HEIGHT)
+        Self::in_world(x, z, WorldHeight::OVERWORLD)
+    }
+
+    pub fn in_world(x: i32, z: i32, world_height: WorldHeight) -> Self {
+        Self::using_world_height(x, z, world_height.min_y, world_height.max_y())
     }
 
     pub fn x(&self) -> i32 {
@@ -591,6 +847,8 @@ impl Chunk {
             chunk_z: z,
             height_maps: self.height_maps.clone(),
             chunk_sections: self.chunk_sections.clone(),
+            light: self.light.clone(),
+            compact_on_encode: self.compact_on_encode,
         }
     }
 
@@ -600,38 +858,19 @@ impl Chunk {
         for _ in 0..sections {
             section_vec.push(ChunkSection {
                 block_count: 0,
-                states: PaletteContainer {
-                    bits_per_entry: 0,
-                    palette: Palette::SingleValue { block_type_id: 0 },
-                    storage: BitStorage::ZeroStorage {
-                        size: Strategy::SECTION_DIRECT_ENTRY_SIZE,
-                        raw: vec![],
-                    },
-                },
-                biomes: PaletteContainer {
-                    bits_per_entry: 0,
-                    palette: Palette::SingleValue { block_type_id: 0 },
-                    storage: BitStorage::ZeroStorage {
-                        size: Strategy::BIOME_DIRECT_ENTRY_SIZE,
-                        raw: vec![],
-                    },
-                },
+                states: PaletteContainer::single(Strategy::Section, 0),
+                biomes: PaletteContainer::single(Strategy::Biome, 0),
             })
         }
-        let height = max_height - min_height;
-        let mut height_maps = HeightMaps {
-            world_surface: BitStorage::new(256, ceil_log_2(height + 1)),
-            motion_blocking: BitStorage::new(256, ceil_log_2(height + 1)),
-            cached_compound_tag: Some(Tag::CompoundTag(Vec::new())),
-        };
-        height_maps.cache_compound_tag();
         Self {
             chunk_x: x,
             chunk_z: z,
             min_height,
             max_height,
-            height_maps,
+            height_maps: HeightMaps::empty(max_height - min_height),
             chunk_sections: section_vec,
+            light: ChunkLight::new(sections as usize),
+            compact_on_encode: false,
         }
     }
 
@@ -672,17 +911,16 @@ impl Chunk {
     ) -> std::result::Result<(), BitSetValidationError> {
         let section_index = Self::get_section_index(self.min_height, y);
         if section_index < 0 || self.chunk_sections.len() <= section_index as usize {
-            return Err(BitSetValidationError(format!("Out of range.")));
+            return Err(BitSetValidationError("Out of range.".to_string()));
         }
         let section = &mut self.chunk_sections[section_index as usize];
         section.rewrite_plane(y & 0xF, block_id)?;
-        // todo: recalculate section block count better here
-        section.block_count += 16 * 16;
-        for x in 0..15 {
-            for z in 0..15 {
-                self.height_maps.update_inner(x, y, z, block_id)?;
+        for x in 0..16 {
+            for z in 0..16 {
+                self.update_heightmaps(x, y, z, block_id)?;
             }
         }
+        self.height_maps.cache_compound_tag();
         Ok(())
     }
 
@@ -695,31 +933,351 @@ impl Chunk {
     ) -> std::result::Result<(), BitSetValidationError> {
         let section_index = Self::get_section_index(self.min_height, y);
         if section_index < 0 || self.chunk_sections.len() <= section_index as usize {
-            return Err(BitSetValidationError(format!("Out of range.")));
+            return Err(BitSetValidationError("Out of range.".to_string()));
         }
         let section = &mut self.chunk_sections[section_index as usize];
-        let mutated = section.set_block_id(x & 0xF, y & 0xF, z & 0xF, block_id)?;
-        if mutated != block_id || mutated == -1 {
-            let x_and = x & 0xF;
-            let z_and = z & 0xF;
-            self.height_maps.update_inner(x_and, y, z_and, block_id)?;
-            if block_id == 0 {
-                section.decrement_non_empty_block_count();
+        let previous = section.set_block_id(x & 0xF, y & 0xF, z & 0xF, block_id)?;
+        if previous != block_id {
+            self.update_heightmaps(x & 0xF, y, z & 0xF, block_id)?;
+            self.height_maps.cache_compound_tag();
+        }
+        Ok(())
+    }
+
+    fn qualifies_for_heightmap(state_id: i32, motion_blocking: bool) -> bool {
+        if motion_blocking {
+            is_motion_blocking(state_id)
+        } else {
+            !is_air_state(state_id)
+        }
+    }
+
+    /// Finds the first y above the highest block at or below `from_y` counting towards the
+    /// heightmap, or the minimum height if there is none.
+    fn column_height(
+        &self,
+        x: i32,
+        z: i32,
+        from_y: i32,
+        motion_blocking: bool,
+    ) -> std::result::Result<i32, BitSetValidationError> {
+        for y in (self.min_height..=from_y).rev() {
+            if Self::qualifies_for_heightmap(self.get_block_id(x, y, z)?, motion_blocking) {
+                return Ok(y + 1);
+            }
+        }
+        Ok(self.min_height)
+    }
+
+    fn update_heightmaps(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        block_id: i32,
+    ) -> std::result::Result<(), BitSetValidationError> {
+        let index = HeightMaps::get_index(x, z);
+        for motion_blocking in [false, true] {
+            let height = self.height_maps.storage(motion_blocking).get(index)? + self.min_height;
+            let new_height = if Self::qualifies_for_heightmap(block_id, motion_blocking) {
+                height.max(y + 1)
+            } else if y + 1 == height {
+                // the highest block was removed so look for the next one below
+                self.column_height(x, z, y - 1, motion_blocking)?
             } else {
-                section.increment_non_empty_block_count();
+                height
+            };
+            if new_height != height {
+                self.height_maps
+                    .storage_mut(motion_blocking)
+                    .set(index, new_height - self.min_height)?;
             }
-            Ok(())
-        } else {
-            // we didn't mutate the thing at all
-            Ok(())
         }
+        Ok(())
+    }
+
+    /// Recomputes both heightmaps from the blocks of the chunk; `WORLD_SURFACE` tracks the
+    /// highest non-air block and `MOTION_BLOCKING` the highest block which blocks motion or
+    /// holds a fluid.
+    pub fn recalculate_heightmaps(&mut self) -> std::result::Result<(), BitSetValidationError> {
+        for x in 0..16 {
+            for z in 0..16 {
+                let index = HeightMaps::get_index(x, z);
+                for motion_blocking in [false, true] {
+                    let height = self.column_height(x, z, self.max_height - 1, motion_blocking)?;
+                    self.height_maps
+                        .storage_mut(motion_blocking)
+                        .set(index, height - self.min_height)?;
+                }
+            }
+        }
+        self.height_maps.cache_compound_tag();
+        Ok(())
+    }
+
+    /// The first y above the highest non-air block of the column.
+    pub fn world_surface(&self, x: i32, z: i32) -> std::result::Result<i32, BitSetValidationError> {
+        Ok(self
+            .height_maps
+            .world_surface
+            .get(HeightMaps::get_index(x & 0xF, z & 0xF))?
+            + self.min_height)
+    }
+
+    /// The first y above the highest motion blocking block of the column.
+    pub fn motion_blocking(
+        &self,
+        x: i32,
+        z: i32,
+    ) -> std::result::Result<i32, BitSetValidationError> {
+        Ok(self
+            .height_maps
+            .motion_blocking
+            .get(HeightMaps::get_index(x & 0xF, z & 0xF))?
+            + self.min_height)
+    }
+
+    /// Recounts the non-air blocks of every section.
+    pub fn recalculate_block_counts(&mut self) -> std::result::Result<(), BitSetValidationError> {
+        for section in &mut self.chunk_sections {
+            section.recalculate_block_count()?;
+        }
+        Ok(())
+    }
+
+    fn section_for(&self, y: i32) -> std::result::Result<usize, BitSetValidationError> {
+        let section_index = Self::get_section_index(self.min_height, y);
+        if section_index < 0 || self.chunk_sections.len() <= section_index as usize {
+            return Err(BitSetValidationError("Out of range.".to_string()));
+        }
+        Ok(section_index as usize)
+    }
+
+    /// The biome of the 4x4x4 cell containing the given block.
+    pub fn get_biome(
+        &self,
+        x: i32,
+        y: i32,
+        z: i32,
+    ) -> std::result::Result<i32, BitSetValidationError> {
+        let section = &self.chunk_sections[self.section_for(y)?];
+        section.biomes.get(ChunkSection::biome_index(x, y, z))
+    }
+
+    /// Sets the biome of the whole 4x4x4 cell containing the given block.
+    pub fn set_biome(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        biome_id: i32,
+    ) -> std::result::Result<(), BitSetValidationError> {
+        let section_index = self.section_for(y)?;
+        let section = &mut self.chunk_sections[section_index];
+        section.biomes.set(
+            Strategy::Biome,
+            ChunkSection::biome_index(x, y, z),
+            biome_id,
+        )?;
+        Ok(())
+    }
+
+    /// Sets the biome of every cell touched by the box between both corners, inclusive; the
+    /// box is clamped to the chunk.
+    pub fn fill_biome(
+        &mut self,
+        from: BlockPos,
+        to: BlockPos,
+        biome_id: i32,
+    ) -> std::result::Result<(), BitSetValidationError> {
+        let clamp_xz = |value: i32, base: i32| (value - base).clamp(0, 15);
+        let (base_x, base_z) = (
+            Self::position_coord_from(self.chunk_x),
+            Self::position_coord_from(self.chunk_z),
+        );
+        let (min_x, max_x) = (
+            clamp_xz(from.x.min(to.x), base_x),
+            clamp_xz(from.x.max(to.x), base_x),
+        );
+        let (min_z, max_z) = (
+            clamp_xz(from.z.min(to.z), base_z),
+            clamp_xz(from.z.max(to.z), base_z),
+        );
+        let min_y = from.y.min(to.y).max(self.min_height);
+        let max_y = from.y.max(to.y).min(self.max_height - 1);
+        if min_y > max_y
+            || from.x.max(to.x) < base_x
+            || from.x.min(to.x) > base_x + 15
+            || from.z.max(to.z) < base_z
+            || from.z.min(to.z) > base_z + 15
+        {
+            return Ok(());
+        }
+
+        for section_y in Self::section_coord_from(min_y)..=Self::section_coord_from(max_y) {
+            let section_min = Self::position_coord_from(section_y);
+            let mut indexes = Vec::new();
+            for y in (min_y.max(section_min) >> 2)..=(max_y.min(section_min + 15) >> 2) {
+                for z in (min_z >> 2)..=(max_z >> 2) {
+                    for x in (min_x >> 2)..=(max_x >> 2) {
+                        indexes.push(Strategy::Biome.retrieve_index(x, y & 0x3, z));
+                    }
+                }
+            }
+            let section_index = self.section_for(section_min)?;
+            self.chunk_sections[section_index].biomes.set_all(
+                Strategy::Biome,
+                indexes,
+                biome_id,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The name of the biome at the given block, resolved through the biome registry.
+    pub fn get_biome_name(
+        &self,
+        x: i32,
+        y: i32,
+        z: i32,
+    ) -> std::result::Result<Option<String>, BitSetValidationError> {
+        Ok(RegistryKey::Biomes.global(self.get_biome(x, y, z)?))
+    }
+
+    /// Sets the biome at the given block by its name, such as `minecraft:plains`.
+    pub fn set_biome_name(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        biome: &str,
+    ) -> std::result::Result<(), BitSetValidationError> {
+        match RegistryKey::Biomes.global(biome) {
+            Some(biome_id) => self.set_biome(x, y, z, biome_id),
+            None => Err(BitSetValidationError(format!("Unknown biome {}.", biome))),
+        }
+    }
+
+    /// Compacts the palettes of every section, see [`PaletteContainer::compact`].
+    pub fn compact(&mut self) -> std::result::Result<(), BitSetValidationError> {
+        for section in &mut self.chunk_sections {
+            section.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Whether sections are compacted on the fly when the chunk is encoded, leaving the
+    /// chunk itself untouched. This trades encoding time for smaller chunk packets.
+    pub fn set_compact_on_encode(&mut self, compact_on_encode: bool) {
+        self.compact_on_encode = compact_on_encode;
+    }
+
+    fn sections_for_encoding(
+        &self,
+    ) -> std::result::Result<Cow<'_, [ChunkSection]>, BitSetValidationError> {
+        if !self.compact_on_encode {
+            return Ok(Cow::Borrowed(&self.chunk_sections));
+        }
+        let mut sections = self.chunk_sections.clone();
+        for section in &mut sections {
+            section.compact()?;
+        }
+        Ok(Cow::Owned(sections))
     }
 
     pub fn height(&self) -> i32 {
         self.max_height - self.min_height
     }
+
+    pub fn world_height(&self) -> WorldHeight {
+        WorldHeight {
+            min_y: self.min_height,
+            height: self.height(),
+        }
+    }
+
+    /// Places the chunk into a world of the given height, which has to have as many sections
+    /// as the chunk. <br />
+    ///
+    /// Chunk packets do not carry the minimum y of the dimension, so chunks decoded as packet
+    /// components assume the overworld minimum until moved into their actual dimension.
+    pub fn with_world_height(
+        mut self,
+        world_height: WorldHeight,
+    ) -> std::result::Result<Self, BitSetValidationError> {
+        if world_height.sections() != self.chunk_sections.len() {
+            return Err(BitSetValidationError(format!(
+                "World height {} does not match the {} sections of the chunk.",
+                world_height.height,
+                self.chunk_sections.len()
+            )));
+        }
+        self.min_height = world_height.min_y;
+        self.max_height = world_height.max_y();
+        Ok(self)
+    }
+
+    /// Decodes a chunk of a dimension with the given height, reading exactly its sections.
+    pub async fn decode_in_world<C: Send + Sync, A: AsyncRead + Unpin + Send + Sync + ?Sized>(
+        context: &mut C,
+        read: &mut A,
+        world_height: WorldHeight,
+    ) -> Result<Chunk> {
+        Self::decode_sections(context, read, Some(world_height)).await
+    }
+
+    async fn decode_sections<C: Send + Sync, A: AsyncRead + Unpin + Send + Sync + ?Sized>(
+        context: &mut C,
+        read: &mut A,
+        world_height: Option<WorldHeight>,
+    ) -> Result<Chunk> {
+        let chunk_x = i32::decode(context, read).await?;
+        let chunk_z = i32::decode(context, read).await?;
+
+        let height_maps_tag = EnsuredCompoundTag::<0>::decode(&mut (), read).await?;
+
+        let data_size = read.read_var_int().await?;
+
+        let mut frame = read.take(data_size as u64);
+
+        let mut chunk_data =
+            Vec::with_capacity(world_height.unwrap_or(WorldHeight::OVERWORLD).sections());
+        match world_height {
+            Some(world_height) => {
+                for _ in 0..world_height.sections() {
+                    chunk_data.push(ChunkSection::decode(context, &mut frame).await?);
+                }
+            }
+            // without a known height every section in the data belongs to the chunk
+            None => {
+                while frame.limit() > 0 {
+                    chunk_data.push(ChunkSection::decode(context, &mut frame).await?);
+                }
+            }
+        }
+        // skip anything left over so the rest of the packet stays aligned
+        tokio::io::copy(&mut frame, &mut tokio::io::sink()).await?;
+
+        let world_height = world_height.unwrap_or(WorldHeight {
+            min_y: WorldHeight::OVERWORLD.min_y,
+            height: chunk_data.len() as i32 * BLOCKS_PER_SECTION,
+        });
+        Ok(Self {
+            chunk_x,
+            chunk_z,
+            min_height: world_height.min_y,
+            max_height: world_height.max_y(),
+            height_maps: HeightMaps::from_tag(height_maps_tag, world_height.height)?,
+            light: ChunkLight::new(chunk_data.len()),
+            compact_on_encode: false,
+            chunk_sections: chunk_data,
+        })
+    }
 }
 
+/// Chunks decoded as packet components read every section in the data and assume the
+/// overworld minimum y; [`Chunk::decode_in_world`] or [`Chunk::with_world_height`] place them
+/// into their actual dimension.
 impl<C: Send + Sync> PacketComponent<C> for Chunk {
     type ComponentType = Chunk;
 
@@ -727,29 +1285,7 @@ impl<C: Send + Sync> PacketComponent<C> for Chunk {
         context: &'a mut C,
         read: &'a mut A,
     ) -> PinnedLivelyResult<'a, Self::ComponentType> {
-        Box::pin(async move {
-            let chunk_x = i32::decode(context, read).await?;
-            let chunk_z = i32::decode(context, read).await?;
-
-            let height_maps = HeightMaps::decode(read, DEFAULT_WORLD_HEIGHT).await?;
-
-            let data_size = read.read_var_int().await?;
-
-            let mut frame = read.take(data_size as u64);
-
-            let mut chunk_data = Vec::with_capacity(24);
-            for _ in 0..24 {
-                chunk_data.push(ChunkSection::decode(context, &mut frame).await?);
-            }
-            Ok(Self {
-                chunk_x,
-                chunk_z,
-                min_height: DEFAULT_WORLD_MIN,
-                max_height: DEFAULT_WORLD_HEIGHT,
-                height_maps,
-                chunk_sections: chunk_data,
-            })
-        })
+        Box::pin(Chunk::decode_sections(context, read, None))
     }
 
     fn encode<'a, A: AsyncWrite + Unpin + Send + Sync + ?Sized>(
@@ -761,10 +1297,12 @@ impl<C: Send + Sync> PacketComponent<C> for Chunk {
             i32::encode(&component_ref.chunk_x, context, write).await?;
             i32::encode(&component_ref.chunk_z, context, write).await?;
             HeightMaps::encode(&component_ref.height_maps, write).await?;
+            let sections = component_ref
+                .sections_for_encoding()
+                .map_err(|err| err_explain!(err.0))?;
             write
                 .write_var_int(
-                    component_ref
-                        .chunk_sections
+                    sections
                         .iter()
                         .map(|x| ChunkSection::size(x, context))
                         .try_fold(Size::Dynamic(0), |acc, x| {
@@ -775,7 +1313,7 @@ impl<C: Send + Sync> PacketComponent<C> for Chunk {
                         })?,
                 )
                 .await?;
-            for chunk_section in &component_ref.chunk_sections {
+            for chunk_section in sections.iter() {
                 ChunkSection::encode(chunk_section, context, write).await?;
             }
             Ok(())
@@ -786,7 +1324,10 @@ impl<C: Send + Sync> PacketComponent<C> for Chunk {
         let mut size = Size::Constant(8);
         size = size + input.height_maps.precondition_size()?;
         let mut v_size = Size::Dynamic(0);
-        for section in &input.chunk_sections {
+        let sections = input
+            .sections_for_encoding()
+            .map_err(|err| err_explain!(err.0))?;
+        for section in sections.iter() {
             v_size = v_size + ChunkSection::size(section, context)?;
         }
         size = size + v_size;
@@ -825,29 +1366,44 @@ impl BasicRegion {
 #[derive(Default)]
 pub struct CachedLevel {
     chunk_cache: HashMap<(i32, i32), Chunk>,
+    world_height: WorldHeight,
+    dirty_blocks: HashSet<BlockPos>,
+    dirty_chunks: HashSet<(i32, i32)>,
+    // chunks whose blocks changed since their light was last computed
+    unlit_chunks: HashSet<(i32, i32)>,
+    block_entities: HashMap<BlockPos, BlockEntity>,
+    dirty_block_entities: HashSet<BlockPos>,
 }
 
 impl CachedLevel {
+    /// Creates a level whose missing chunks are filled with empty chunks of the given height.
+    pub fn with_world_height(world_height: WorldHeight) -> Self {
+        Self {
+            world_height,
+            ..Self::default()
+        }
+    }
+
+    pub fn world_height(&self) -> WorldHeight {
+        self.world_height
+    }
+
     pub fn insert_chunk(&mut self, chunk: Chunk) {
         self.chunk_cache
             .insert((chunk.chunk_x, chunk.chunk_z), chunk);
     }
 
     pub fn clone_cached_or_insert(&mut self, x: i32, z: i32) -> Chunk {
-        if self.chunk_cache.contains_key(&(x, z)) {
-            self.chunk_cache.get(&(x, z)).cloned().unwrap()
-        } else {
-            let new_chunk = Chunk::new(x, z);
-            self.chunk_cache.insert((x, z), new_chunk.clone());
-            new_chunk
-        }
+        self.chunk_cache
+            .entry((x, z))
+            .or_insert_with(|| Chunk::in_world(x, z, self.world_height))
+            .clone()
     }
 
     pub fn clone_cached(&self, x: i32, z: i32) -> Chunk {
-        if self.chunk_cache.contains_key(&(x, z)) {
-            self.chunk_cache.get(&(x, z)).cloned().unwrap()
-        } else {
-            Chunk::new(x, z)
+        match self.chunk_cache.get(&(x, z)) {
+            Some(chunk) => chunk.clone(),
+            None => Chunk::in_world(x, z, self.world_height),
         }
     }
 
@@ -875,7 +1431,7 @@ impl CachedLevel {
     }
 
     pub fn insert_region(&mut self, region: BasicRegion) {
-        let empty = Chunk::new(0, 0);
+        let empty = Chunk::in_world(0, 0, self.world_height);
         for region_x in region.chunks {
             for chunk in region_x {
                 if let Some(chunk) = chunk {
@@ -893,3 +1449,205 @@ impl CachedLevel {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use drax::prelude::{PacketComponent, Size};
+
+    use crate::clientbound::play::{ClientboundPlayRegistry, LevelChunkData};
+    use crate::common::chunk::{is_motion_blocking, Chunk, Palette, WorldHeight};
+    use crate::common::play::BlockPos;
+
+    #[tokio::test]
+    pub async fn custom_world_height() -> drax::prelude::Result<()> {
+        let world_height = WorldHeight::new(0, 256).unwrap();
+        let mut chunk = Chunk::in_world(4, 7, world_height);
+        chunk.set_block_id(1, 200, 2, 1).unwrap();
+
+        let mut bytes = Vec::new();
+        Chunk::encode(&chunk, &mut (), &mut bytes).await?;
+        bytes.extend_from_slice(&[0xAB]);
+
+        let mut read = std::io::Cursor::new(bytes.clone());
+        let decoded = Chunk::decode(&mut (), &mut read).await?;
+        assert_eq!(decoded.height(), 256);
+        let decoded = decoded.with_world_height(world_height).unwrap();
+        assert_eq!(decoded, chunk);
+        assert_eq!(decoded.get_block_id(1, 200, 2).unwrap(), 1);
+        assert_eq!(read.position() as usize, bytes.len() - 1);
+
+        let mut read = std::io::Cursor::new(bytes);
+        let decoded = Chunk::decode_in_world(&mut (), &mut read, world_height).await?;
+        assert_eq!(decoded.world_height(), world_height);
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn decode_with_world_height() -> drax::prelude::Result<()> {
+        let nether = WorldHeight::new(0, 256).unwrap();
+        let mut chunk = Chunk::in_world(-3, 9, nether);
+        chunk.set_block_id(1, 200, 2, 1).unwrap();
+        let packet = ClientboundPlayRegistry::LevelChunkWithLight {
+            chunk_data: LevelChunkData {
+                chunk: chunk.clone(),
+                block_entities: vec![],
+            },
+            light_data: chunk.light_update_data(true)?,
+        };
+
+        let mut bytes = Vec::new();
+        ClientboundPlayRegistry::encode(&packet, &mut (), &mut bytes).await?;
+        let decoded =
+            ClientboundPlayRegistry::decode(&mut (), &mut std::io::Cursor::new(bytes)).await?;
+        match decoded {
+            ClientboundPlayRegistry::LevelChunkWithLight { chunk_data, .. } => {
+                assert_eq!(chunk_data.chunk.height(), 256);
+                let decoded = chunk_data.chunk.with_world_height(nether).unwrap();
+                assert_eq!(decoded.world_height(), nether);
+                assert_eq!(decoded, chunk);
+                assert_eq!(decoded.get_block_id(1, 200, 2).unwrap(), 1);
+            }
+            _ => panic!("Decoded a different packet."),
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn motion_blocking_states() {
+        let blocks = &crate::common::registry::GLOBAL_REGISTRIES.blocks;
+        let motion_blocking =
+            |state: &str| is_motion_blocking(blocks.parse_state_id(state).unwrap());
+        assert!(motion_blocking("stone"));
+        assert!(motion_blocking("cactus"));
+        assert!(motion_blocking("water"));
+        assert!(motion_blocking("kelp"));
+        assert!(motion_blocking("oak_sign"));
+        assert!(!motion_blocking("dead_bush"));
+        assert!(!motion_blocking("flower_pot"));
+        assert!(!motion_blocking("potted_dead_bush"));
+        assert!(!motion_blocking("potted_cactus"));
+        assert!(!motion_blocking("white_candle"));
+        assert!(!motion_blocking("sea_pickle[waterlogged=false]"));
+        assert!(motion_blocking("sea_pickle[waterlogged=true]"));
+        assert!(!motion_blocking("light[waterlogged=false]"));
+        assert!(motion_blocking("light[waterlogged=true]"));
+        assert!(motion_blocking("oak_stairs[waterlogged=false]"));
+    }
+
+    #[test]
+    pub fn compact_palettes() {
+        let mut chunk = Chunk::new(0, 0);
+        for value in 1..20 {
+            chunk
+                .set_block_id(value % 16, 5, value / 16, value)
+                .unwrap();
+        }
+        for value in 1..20 {
+            chunk.set_block_id(value % 16, 5, value / 16, 0).unwrap();
+        }
+        chunk.set_block_id(3, 5, 3, 7).unwrap();
+        let section = &chunk.chunk_sections[4];
+        assert_eq!(section.states.bits_per_entry, 5);
+
+        let mut encoded = chunk.clone();
+        encoded.set_compact_on_encode(true);
+        let full = Chunk::size(&chunk, &mut ()).unwrap();
+        let compacted = Chunk::size(&encoded, &mut ()).unwrap();
+        let value = |size| match size {
+            Size::Dynamic(x) | Size::Constant(x) => x,
+        };
+        assert!(value(compacted) < value(full));
+
+        chunk.compact().unwrap();
+        let section = &chunk.chunk_sections[4];
+        assert_eq!(section.states.bits_per_entry, 4);
+        assert_eq!(chunk.get_block_id(3, 5, 3).unwrap(), 7);
+        assert_eq!(chunk.get_block_id(4, 5, 0).unwrap(), 0);
+
+        chunk.set_block_id(3, 5, 3, 0).unwrap();
+        chunk.compact().unwrap();
+        assert_eq!(
+            chunk.chunk_sections[4].states.palette,
+            Palette::SingleValue { block_type_id: 0 }
+        );
+    }
+
+    #[test]
+    pub fn heightmaps_and_block_counts() {
+        let blocks = &crate::common::registry::GLOBAL_REGISTRIES.blocks;
+        let stone = blocks.default_state_id("stone").unwrap();
+        let cave_air = blocks.default_state_id("cave_air").unwrap();
+        let torch = blocks.default_state_id("torch").unwrap();
+
+        let mut chunk = Chunk::new(0, 0);
+        chunk.rewrite_plane(10, stone).unwrap();
+        chunk.rewrite_plane(10, stone).unwrap();
+        assert_eq!(chunk.chunk_sections[4].block_count(), 256);
+        chunk.set_block_id(2, 11, 2, cave_air).unwrap();
+        chunk.set_block_id(2, 12, 2, torch).unwrap();
+        assert_eq!(chunk.chunk_sections[4].block_count(), 257);
+        assert_eq!(chunk.world_surface(2, 2).unwrap(), 13);
+        assert_eq!(chunk.motion_blocking(2, 2).unwrap(), 11);
+        assert_eq!(chunk.world_surface(15, 15).unwrap(), 11);
+
+        chunk.set_block_id(2, 12, 2, 0).unwrap();
+        chunk.set_block_id(2, 10, 2, 0).unwrap();
+        assert_eq!(chunk.chunk_sections[4].block_count(), 255);
+        assert_eq!(chunk.world_surface(2, 2).unwrap(), -64);
+
+        let before = chunk.clone();
+        chunk.recalculate_heightmaps().unwrap();
+        chunk.recalculate_block_counts().unwrap();
+        for (x, z) in [(2, 2), (15, 15), (0, 7)] {
+            assert_eq!(
+                chunk.world_surface(x, z).unwrap(),
+                before.world_surface(x, z).unwrap()
+            );
+            assert_eq!(
+                chunk.motion_blocking(x, z).unwrap(),
+                before.motion_blocking(x, z).unwrap()
+            );
+        }
+        assert_eq!(chunk, before);
+    }
+
+    #[test]
+    pub fn biomes() {
+        let mut chunk = Chunk::new(-1, 2);
+        chunk.set_biome_name(5, 70, 6, "minecraft:desert").unwrap();
+        assert_eq!(
+            chunk.get_biome_name(7, 71, 4).unwrap().as_deref(),
+            Some("minecraft:desert")
+        );
+        assert_eq!(chunk.get_biome(8, 70, 6).unwrap(), 0);
+
+        // enough distinct biomes to move the section to the direct palette
+        for (idx, biome_id) in (1..10).enumerate() {
+            chunk
+                .set_biome(idx as i32 * 4 % 16, 0, idx as i32 / 4 * 4, biome_id)
+                .unwrap();
+        }
+        assert_eq!(chunk.get_biome(4, 0, 0).unwrap(), 2);
+        assert_eq!(chunk.get_biome(0, 0, 8).unwrap(), 9);
+
+        chunk
+            .fill_biome(
+                BlockPos {
+                    x: -20,
+                    y: -64,
+                    z: 32,
+                },
+                BlockPos {
+                    x: -13,
+                    y: -57,
+                    z: 35,
+                },
+                12,
+            )
+            .unwrap();
+        assert_eq!(chunk.get_biome(-16, -64, 32).unwrap(), 12);
+        assert_eq!(chunk.get_biome(-13, -57, 35).unwrap(), 12);
+        assert_ne!(chunk.get_biome(-12, -57, 35).unwrap(), 12);
+        assert_ne!(chunk.get_biome(-13, -56, 35).unwrap(), 12);
+    }
+}
@@ -0,0 +1,938 @@
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+use drax::nbt::{EnsuredCompoundTag, Tag};
+use drax::prelude::{PacketComponent, Result};
+use drax::{err_explain, throw_explain};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+
+use crate::common::bit_storage::BitStorage;
+use crate::common::chunk::edit::BlockEntity;
+use crate::common::chunk::light::{LightLayer, NibbleArray};
+use crate::common::chunk::{
+    is_air_state, CachedLevel, Chunk, ChunkSection, HeightMaps, PaletteContainer, Strategy,
+};
+use crate::common::play::{ceil_log_2, BlockPos};
+use crate::common::registry::block::BlockState;
+use crate::common::registry::{RegistryKey, GLOBAL_REGISTRIES};
+
+/// The data version of chunks written by 1.19.3.
+pub const DATA_VERSION: i32 = 3218;
+
+const SECTOR_SIZE: usize = 4096;
+const HEADER_SIZE: usize = SECTOR_SIZE * 2;
+const CHUNKS_PER_REGION: usize = 32 * 32;
+const MAX_SECTORS_PER_CHUNK: usize = 255;
+const EXTERNAL_CHUNK_FLAG: u8 = 0x80;
+
+pub(super) const COMPOUND_TAG_ID: u8 = 10;
+pub(super) const STRING_TAG_ID: u8 = 8;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RegionCompression {
+    Gzip,
+    Zlib,
+    Uncompressed,
+}
+
+impl RegionCompression {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(RegionCompression::Gzip),
+            2 => Some(RegionCompression::Zlib),
+            3 => Some(RegionCompression::Uncompressed),
+            _ => None,
+        }
+    }
+
+    pub fn id(self) -> u8 {
+        match self {
+            RegionCompression::Gzip => 1,
+            RegionCompression::Zlib => 2,
+            RegionCompression::Uncompressed => 3,
+        }
+    }
+
+    pub(super) fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(match self {
+            RegionCompression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            RegionCompression::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            RegionCompression::Uncompressed => data.to_vec(),
+        })
+    }
+
+    pub(super) fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        match self {
+            RegionCompression::Gzip => {
+                GzDecoder::new(data).read_to_end(&mut out)?;
+            }
+            RegionCompression::Zlib => {
+                ZlibDecoder::new(data).read_to_end(&mut out)?;
+            }
+            RegionCompression::Uncompressed => out.extend_from_slice(data),
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RegionEntry {
+    timestamp: u32,
+    compression: RegionCompression,
+    data: Vec<u8>,
+}
+
+/// An Anvil region file (`r.<x>.<z>.mca`) holding up to 32x32 compressed chunks. <br />
+///
+/// Chunks are addressed by their absolute chunk coordinates; only the lower five bits are
+/// used to locate them in the region. Chunks stored in external `.mcc` files are not supported.
+#[derive(Debug, Clone)]
+pub struct RegionFile {
+    entries: Vec<Option<RegionEntry>>,
+}
+
+impl Default for RegionFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RegionFile {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![None; CHUNKS_PER_REGION],
+        }
+    }
+
+    /// The region coordinates containing the given chunk coordinates.
+    pub const fn region_coords(chunk_x: i32, chunk_z: i32) -> (i32, i32) {
+        (chunk_x >> 5, chunk_z >> 5)
+    }
+
+    const fn index(chunk_x: i32, chunk_z: i32) -> usize {
+        ((chunk_x & 31) + (chunk_z & 31) * 32) as usize
+    }
+
+    fn read_u32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut region = Self::new();
+        if bytes.is_empty() {
+            return Ok(region);
+        }
+        if bytes.len() < HEADER_SIZE {
+            throw_explain!(format!(
+                "Region file is {} bytes, too small to hold its header.",
+                bytes.len()
+            ))
+        }
+        for idx in 0..CHUNKS_PER_REGION {
+            let location = Self::read_u32(bytes, idx * 4);
+            if location == 0 {
+                continue;
+            }
+            let start = (location >> 8) as usize * SECTOR_SIZE;
+            let sectors = (location & 0xFF) as usize;
+            let end = start + sectors * SECTOR_SIZE;
+            if start < HEADER_SIZE || start + 5 > bytes.len() {
+                throw_explain!(format!(
+                    "Chunk {} points to sector {} outside of the region file.",
+                    idx,
+                    location >> 8
+                ))
+            }
+            let length = Self::read_u32(bytes, start) as usize;
+            if length == 0 || start + 4 + length > end.min(bytes.len()) {
+                throw_explain!(format!(
+                    "Chunk {} has length {} which does not fit its {} sectors.",
+                    idx, length, sectors
+                ))
+            }
+            let compression_id = bytes[start + 4];
+            if compression_id & EXTERNAL_CHUNK_FLAG != 0 {
+                throw_explain!(format!(
+                    "Chunk {} is stored in an external file, which is not supported.",
+                    idx
+                ))
+            }
+            let compression = match RegionCompression::from_id(compression_id) {
+                Some(compression) => compression,
+                None => throw_explain!(format!(
+                    "Chunk {} uses unknown compression {}.",
+                    idx, compression_id
+                )),
+            };
+            region.entries[idx] = Some(RegionEntry {
+                timestamp: Self::read_u32(bytes, SECTOR_SIZE + idx * 4),
+                compression,
+                data: bytes[start + 5..start + 4 + length].to_vec(),
+            });
+        }
+        Ok(region)
+    }
+
+    /// Lays out every chunk in consecutive sectors after the header.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut out = vec![0u8; HEADER_SIZE];
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let entry = match entry {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let sectors = (entry.data.len() + 5).div_ceil(SECTOR_SIZE);
+            if sectors > MAX_SECTORS_PER_CHUNK {
+                throw_explain!(format!(
+                    "Chunk {} needs {} sectors but at most {} are allowed.",
+                    idx, sectors, MAX_SECTORS_PER_CHUNK
+                ))
+            }
+            let location = ((out.len() / SECTOR_SIZE) as u32) << 8 | sectors as u32;
+            out[idx * 4..idx * 4 + 4].copy_from_slice(&location.to_be_bytes());
+            out[SECTOR_SIZE + idx * 4..SECTOR_SIZE + idx * 4 + 4]
+                .copy_from_slice(&entry.timestamp.to_be_bytes());
+
+            let start = out.len();
+            out.extend_from_slice(&(entry.data.len() as u32 + 1).to_be_bytes());
+            out.push(entry.compression.id());
+            out.extend_from_slice(&entry.data);
+            out.resize(start + sectors * SECTOR_SIZE, 0);
+        }
+        Ok(out)
+    }
+
+    /// Opens the region file at the given path; a missing file yields an empty region.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => Self::from_bytes(&bytes),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn contains(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.entries[Self::index(chunk_x, chunk_z)].is_some()
+    }
+
+    /// The last modification time of the chunk in epoch seconds.
+    pub fn timestamp(&self, chunk_x: i32, chunk_z: i32) -> Option<u32> {
+        self.entries[Self::index(chunk_x, chunk_z)]
+            .as_ref()
+            .map(|entry| entry.timestamp)
+    }
+
+    pub fn remove(&mut self, chunk_x: i32, chunk_z: i32) {
+        self.entries[Self::index(chunk_x, chunk_z)] = None;
+    }
+
+    pub async fn read_chunk_nbt(&self, chunk_x: i32, chunk_z: i32) -> Result<Option<Tag>> {
+        let entry = match &self.entries[Self::index(chunk_x, chunk_z)] {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let mut data = Cursor::new(entry.compression.decompress(&entry.data)?);
+        EnsuredCompoundTag::<0>::decode(&mut (), &mut data).await
+    }
+
+    pub async fn write_chunk_nbt(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        tag: &Tag,
+        compression: RegionCompression,
+        timestamp: u32,
+    ) -> Result<()> {
+        let mut data = Vec::new();
+        EnsuredCompoundTag::<0>::encode(&Some(tag.clone()), &mut (), &mut data).await?;
+        self.entries[Self::index(chunk_x, chunk_z)] = Some(RegionEntry {
+            timestamp,
+            compression,
+            data: compression.compress(&data)?,
+        });
+        Ok(())
+    }
+
+    /// Reads and converts the chunk at the given coordinates for a world spanning
+    /// `min_height..max_height`.
+    pub async fn read_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        min_height: i32,
+        max_height: i32,
+    ) -> Result<Option<Chunk>> {
+        match self.read_chunk_nbt(chunk_x, chunk_z).await? {
+            Some(tag) => Chunk::from_nbt(&tag, min_height, max_height).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn write_chunk(
+        &mut self,
+        chunk: &Chunk,
+        compression: RegionCompression,
+        timestamp: u32,
+    ) -> Result<()> {
+        let tag = chunk.to_nbt([])?;
+        self.write_chunk_nbt(chunk.chunk_x, chunk.chunk_z, &tag, compression, timestamp)
+            .await
+    }
+}
+
+pub(super) fn compound_get<'a>(tag: &'a Tag, name: &str) -> Option<&'a Tag> {
+    match tag {
+        Tag::CompoundTag(entries) => entries
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+pub(super) fn compound_int(tag: &Tag, name: &str) -> Option<i32> {
+    match compound_get(tag, name)? {
+        Tag::TagByte(value) => Some(*value as i8 as i32),
+        Tag::TagShort(value) => Some(*value as i32),
+        Tag::TagInt(value) => Some(*value),
+        _ => None,
+    }
+}
+
+pub(super) fn compound_string<'a>(tag: &'a Tag, name: &str) -> Option<&'a str> {
+    match compound_get(tag, name)? {
+        Tag::TagString(value) => Some(value),
+        _ => None,
+    }
+}
+
+pub(super) fn compound_list<'a>(tag: &'a Tag, name: &str) -> &'a [Tag] {
+    match compound_get(tag, name) {
+        Some(Tag::TagList(_, values)) => values,
+        _ => &[],
+    }
+}
+
+fn compound_longs<'a>(tag: &'a Tag, name: &str) -> Option<&'a Vec<i64>> {
+    match compound_get(tag, name)? {
+        Tag::TagLongArray(values) => Some(values),
+        _ => None,
+    }
+}
+
+pub(super) fn string_tag<S: Into<String>>(value: S) -> Tag {
+    Tag::TagString(value.into())
+}
+
+pub(super) fn list_tag(type_id: u8, values: Vec<Tag>) -> Tag {
+    Tag::TagList(if values.is_empty() { 0 } else { type_id }, values)
+}
+
+pub(super) fn without_keys(tag: &Tag, keys: &[&str]) -> Tag {
+    match tag {
+        Tag::CompoundTag(entries) => Tag::CompoundTag(
+            entries
+                .iter()
+                .filter(|(key, _)| !keys.contains(&key.as_str()))
+                .cloned()
+                .collect(),
+        ),
+        _ => Tag::CompoundTag(vec![]),
+    }
+}
+
+/// The keys of a stored block entity which are not part of its data.
+const BLOCK_ENTITY_KEYS: [&str; 5] = ["id", "x", "y", "z", "keepPacked"];
+
+/// Reads the block entities of a chunk stored in a region file with their world positions.
+pub fn block_entities_from_nbt(tag: &Tag) -> Result<Vec<(BlockPos, BlockEntity)>> {
+    let mut block_entities = Vec::new();
+    for block_entity in compound_list(tag, "block_entities") {
+        let pos = match (
+            compound_int(block_entity, "x"),
+            compound_int(block_entity, "y"),
+            compound_int(block_entity, "z"),
+        ) {
+            (Some(x), Some(y), Some(z)) => BlockPos { x, y, z },
+            _ => throw_explain!("Block entity is missing its position."),
+        };
+        let id = match compound_string(block_entity, "id") {
+            Some(id) => id,
+            None => throw_explain!(format!("Block entity at {:?} is missing its id.", pos)),
+        };
+        block_entities.push((
+            pos,
+            BlockEntity::new(id, without_keys(block_entity, &BLOCK_ENTITY_KEYS)),
+        ));
+    }
+    Ok(block_entities)
+}
+
+fn block_entity_to_nbt(pos: &BlockPos, block_entity: &BlockEntity) -> Tag {
+    let mut entries = vec![
+        ("id".to_string(), string_tag(block_entity.id.clone())),
+        ("x".to_string(), Tag::TagInt(pos.x)),
+        ("y".to_string(), Tag::TagInt(pos.y)),
+        ("z".to_string(), Tag::TagInt(pos.z)),
+        ("keepPacked".to_string(), Tag::TagByte(0)),
+    ];
+    if let Tag::CompoundTag(data) = without_keys(&block_entity.data, &BLOCK_ENTITY_KEYS) {
+        entries.extend(data);
+    }
+    Tag::CompoundTag(entries)
+}
+
+const LIGHT_KEYS: [(LightLayer, &str); 2] = [
+    (LightLayer::Sky, "SkyLight"),
+    (LightLayer::Block, "BlockLight"),
+];
+
+/// Resolves a palette stored in a chunk file into the values of every entry.
+fn read_paletted(
+    strategy: Strategy,
+    min_bits: i32,
+    palette: &[i32],
+    data: Option<&Vec<i64>>,
+) -> Result<PaletteContainer> {
+    match palette.len() {
+        0 => throw_explain!("Paletted container has an empty palette."),
+        1 => return Ok(PaletteContainer::single(strategy, palette[0])),
+        _ => (),
+    }
+    let bits = ceil_log_2(palette.len() as i32).max(min_bits);
+    let storage = BitStorage::with_seeded_raw(
+        strategy.locked_entry_count(),
+        bits,
+        data.cloned().unwrap_or_default(),
+    )
+    .map_err(|err| err_explain!(err.0))?;
+    let mut values = Vec::with_capacity(strategy.locked_entry_count() as usize);
+    for idx in 0..strategy.locked_entry_count() {
+        let palette_idx = storage.get(idx).map_err(|err| err_explain!(err.0))?;
+        match palette.get(palette_idx as usize) {
+            Some(value) => values.push(*value),
+            None => throw_explain!(format!(
+                "Palette index {} out of bounds for {} entries.",
+                palette_idx,
+                palette.len()
+            )),
+        }
+    }
+    PaletteContainer::from_values(strategy, &values).map_err(|err| err_explain!(err.0))
+}
+
+/// Packs the values of a container into the palette and data arrays of a chunk file.
+fn write_paletted(
+    strategy: Strategy,
+    min_bits: i32,
+    container: &PaletteContainer,
+) -> Result<(Vec<i32>, Option<Vec<i64>>)> {
+    let values = container
+        .values(strategy)
+        .map_err(|err| err_explain!(err.0))?;
+    let mut palette = Vec::new();
+    for value in &values {
+        if !palette.contains(value) {
+            palette.push(*value);
+        }
+    }
+    if palette.len() <= 1 {
+        return Ok((palette, None));
+    }
+    let bits = ceil_log_2(palette.len() as i32).max(min_bits);
+    let mut storage = BitStorage::new(strategy.locked_entry_count(), bits);
+    for (idx, value) in values.iter().enumerate() {
+        let palette_idx = palette.iter().position(|other| other == value).unwrap_or(0);
+        storage
+            .set(idx as i32, palette_idx as i32)
+            .map_err(|err| err_explain!(err.0))?;
+    }
+    Ok((palette, Some(storage.get_raw().clone())))
+}
+
+/// Rejects data saved by a newer version than [`DATA_VERSION`], whose blocks may be unknown.
+pub(super) fn check_data_version(tag: &Tag) -> Result<()> {
+    match compound_int(tag, "DataVersion") {
+        Some(data_version) if data_version > DATA_VERSION => throw_explain!(format!(
+            "Data version {} is newer than the supported {}.",
+            data_version, DATA_VERSION
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub(super) fn block_state_from_nbt(tag: &Tag) -> Result<i32> {
+    let name = match compound_string(tag, "Name") {
+        Some(name) => name,
+        None => throw_explain!("Block state is missing its Name."),
+    };
+    let mut state = BlockState::new(name);
+    if let Some(Tag::CompoundTag(properties)) = compound_get(tag, "Properties") {
+        for (property, value) in properties {
+            if let Tag::TagString(value) = value {
+                state.set(property.clone(), value.clone());
+            }
+        }
+    }
+    match GLOBAL_REGISTRIES.blocks.state_id(&state) {
+        Some(state_id) => Ok(state_id),
+        None => throw_explain!(format!("Unknown block state {}.", state)),
+    }
+}
+
+pub(super) fn block_state_to_nbt(state_id: i32) -> Result<Tag> {
+    let state = match GLOBAL_REGISTRIES.blocks.state(state_id) {
+        Some(state) => state,
+        None => throw_explain!(format!("Unknown block state id {}.", state_id)),
+    };
+    let mut tag = vec![("Name".to_string(), string_tag(state.block))];
+    if !state.properties.is_empty() {
+        tag.push((
+            "Properties".to_string(),
+            Tag::CompoundTag(
+                state
+                    .properties
+                    .into_iter()
+                    .map(|(name, value)| (name, string_tag(value)))
+                    .collect(),
+            ),
+        ));
+    }
+    Ok(Tag::CompoundTag(tag))
+}
+
+fn biome_from_nbt(tag: &Tag) -> Result<i32> {
+    let name = match tag {
+        Tag::TagString(name) => name,
+        _ => throw_explain!("Biome palette entry is not a string."),
+    };
+    match GLOBAL_REGISTRIES.get_id(RegistryKey::Biomes, name) {
+        Some(biome) => Ok(biome),
+        None => throw_explain!(format!("Unknown biome {}.", name)),
+    }
+}
+
+fn biome_to_nbt(biome_id: i32) -> Result<Tag> {
+    match GLOBAL_REGISTRIES.get_key(RegistryKey::Biomes, biome_id) {
+        Some(biome) => Ok(string_tag(biome)),
+        None => throw_explain!(format!("Unknown biome id {}.", biome_id)),
+    }
+}
+
+impl ChunkSection {
+    fn from_nbt(tag: &Tag) -> Result<Self> {
+        let (states, biomes) = match (
+            compound_get(tag, "block_states"),
+            compound_get(tag, "biomes"),
+        ) {
+            (Some(states), Some(biomes)) => (states, biomes),
+            _ => throw_explain!("Chunk section is missing its block states or biomes."),
+        };
+        let state_palette: Vec<i32> = compound_list(states, "palette")
+            .iter()
+            .map(block_state_from_nbt)
+            .collect::<Result<_>>()?;
+        let states = read_paletted(
+            Strategy::Section,
+            4,
+            &state_palette,
+            compound_longs(states, "data"),
+        )?;
+        let biome_palette: Vec<i32> = compound_list(biomes, "palette")
+            .iter()
+            .map(biome_from_nbt)
+            .collect::<Result<_>>()?;
+        let biomes = read_paletted(
+            Strategy::Biome,
+            0,
+            &biome_palette,
+            compound_longs(biomes, "data"),
+        )?;
+        let block_count = states
+            .values(Strategy::Section)
+            .map_err(|err| err_explain!(err.0))?
+            .into_iter()
+            .filter(|state_id| !is_air_state(*state_id))
+            .count() as u16;
+        Ok(Self {
+            block_count,
+            states,
+            biomes,
+        })
+    }
+
+    fn to_nbt(&self, section_y: i32) -> Result<Tag> {
+        let (state_palette, state_data) = write_paletted(Strategy::Section, 4, &self.states)?;
+        let mut block_states = vec![(
+            "palette".to_string(),
+            list_tag(
+                COMPOUND_TAG_ID,
+                state_palette
+                    .into_iter()
+                    .map(block_state_to_nbt)
+                    .collect::<Result<_>>()?,
+            ),
+        )];
+        if let Some(data) = state_data {
+            block_states.push(("data".to_string(), Tag::TagLongArray(data)));
+        }
+
+        let (biome_palette, biome_data) = write_paletted(Strategy::Biome, 0, &self.biomes)?;
+        let mut biomes = vec![(
+            "palette".to_string(),
+            list_tag(
+                STRING_TAG_ID,
+                biome_palette
+                    .into_iter()
+                    .map(biome_to_nbt)
+                    .collect::<Result<_>>()?,
+            ),
+        )];
+        if let Some(data) = biome_data {
+            biomes.push(("data".to_string(), Tag::TagLongArray(data)));
+        }
+
+        Ok(Tag::CompoundTag(vec![
+            ("Y".to_string(), Tag::TagByte(section_y as i8 as u8)),
+            ("block_states".to_string(), Tag::CompoundTag(block_states)),
+            ("biomes".to_string(), Tag::CompoundTag(biomes)),
+        ]))
+    }
+}
+
+impl Chunk {
+    /// Converts the NBT of a chunk stored in a region file, resolving block states and biomes
+    /// through [`GLOBAL_REGISTRIES`]. <br />
+    ///
+    /// Chunks of a newer data version than [`DATA_VERSION`] as well as unknown block states
+    /// and biomes are rejected. Sections outside of `min_height..max_height` are ignored.
+    /// Light is only read from chunks marked as lit; block entities are read by
+    /// [`block_entities_from_nbt`].
+    pub fn from_nbt(tag: &Tag, min_height: i32, max_height: i32) -> Result<Chunk> {
+        check_data_version(tag)?;
+        let (chunk_x, chunk_z) = match (compound_int(tag, "xPos"), compound_int(tag, "zPos")) {
+            (Some(chunk_x), Some(chunk_z)) => (chunk_x, chunk_z),
+            _ => throw_explain!("Chunk is missing its xPos or zPos."),
+        };
+        let mut chunk = Chunk::using_world_height(chunk_x, chunk_z, min_height, max_height);
+        let min_section = Chunk::get_min_section(min_height);
+        let light_on = compound_int(tag, "isLightOn") == Some(1);
+        for section in compound_list(tag, "sections") {
+            let section_y = match compound_int(section, "Y") {
+                Some(section_y) => section_y,
+                None => throw_explain!("Chunk section is missing its Y."),
+            };
+            if light_on {
+                // light has one more section below the world
+                let light_idx = section_y - min_section + 1;
+                for (layer, key) in LIGHT_KEYS {
+                    if let Some(Tag::TagByteArray(data)) = compound_get(section, key) {
+                        let data = NibbleArray::from_bytes(data.clone())
+                            .map_err(|err| err_explain!(err.0))?;
+                        if light_idx >= 0 {
+                            chunk
+                                .light
+                                .set_section(layer, light_idx as usize, Some(data));
+                        }
+                    }
+                }
+            }
+            let idx = section_y - min_section;
+            if idx < 0 || idx as usize >= chunk.chunk_sections.len() {
+                continue;
+            }
+            // sections only holding light have no block states
+            if compound_get(section, "block_states").is_none() {
+                continue;
+            }
+            chunk.chunk_sections[idx as usize] = ChunkSection::from_nbt(section)?;
+        }
+        match compound_get(tag, "Heightmaps")
+            .map(|height_maps| HeightMaps::from_tag(Some(height_maps.clone()), chunk.height()))
+        {
+            Some(Ok(height_maps)) => chunk.height_maps = height_maps,
+            // chunks which are not fully generated may lack them
+            _ => chunk
+                .recalculate_heightmaps()
+                .map_err(|err| err_explain!(err.0))?,
+        }
+        Ok(chunk)
+    }
+
+    /// Converts the chunk and the given block entities, which are positioned in world
+    /// coordinates, into the NBT stored in region files, written as a fully generated chunk of
+    /// [`DATA_VERSION`]. <br />
+    ///
+    /// Light is stored as is; the chunk is only marked as lit when every light section is
+    /// present, otherwise the server recomputes it.
+    pub fn to_nbt<'a, I>(&self, block_entities: I) -> Result<Tag>
+    where
+        I: IntoIterator<Item = (&'a BlockPos, &'a BlockEntity)>,
+    {
+        let min_section = Chunk::get_min_section(self.min_height);
+        let light_sections = self.light.sections(LightLayer::Sky).len();
+        let mut sections = Vec::with_capacity(light_sections);
+        // light has one more section below and above the world
+        for light_idx in 0..light_sections {
+            let section_y = min_section + light_idx as i32 - 1;
+            let mut entries = match light_idx
+                .checked_sub(1)
+                .and_then(|idx| self.chunk_sections.get(idx))
+            {
+                Some(section) => match section.to_nbt(section_y)? {
+                    Tag::CompoundTag(entries) => entries,
+                    _ => throw_explain!("Chunk section was not converted to a compound."),
+                },
+                None => vec![("Y".to_string(), Tag::TagByte(section_y as i8 as u8))],
+            };
+            let has_blocks = entries.len() > 1;
+            for (layer, key) in LIGHT_KEYS {
+                if let Some(data) = self.light.section(layer, light_idx) {
+                    entries.push((key.to_string(), Tag::TagByteArray(data.as_bytes().to_vec())));
+                }
+            }
+            if has_blocks || entries.len() > 1 {
+                sections.push(Tag::CompoundTag(entries));
+            }
+        }
+        let light_on = [LightLayer::Sky, LightLayer::Block]
+            .into_iter()
+            .all(|layer| self.light.sections(layer).iter().all(Option::is_some));
+        let block_entities = block_entities
+            .into_iter()
+            .map(|(pos, block_entity)| block_entity_to_nbt(pos, block_entity))
+            .collect();
+        let height_maps = match &self.height_maps.cached_compound_tag {
+            Some(tag) => tag.clone(),
+            None => Tag::CompoundTag(vec![]),
+        };
+        Ok(Tag::CompoundTag(vec![
+            ("DataVersion".to_string(), Tag::TagInt(DATA_VERSION)),
+            ("xPos".to_string(), Tag::TagInt(self.chunk_x)),
+            ("zPos".to_string(), Tag::TagInt(self.chunk_z)),
+            ("yPos".to_string(), Tag::TagInt(min_section)),
+            ("Status".to_string(), string_tag("full")),
+            ("LastUpdate".to_string(), Tag::TagLong(0)),
+            ("InhabitedTime".to_string(), Tag::TagLong(0)),
+            ("isLightOn".to_string(), Tag::TagByte(light_on as u8)),
+            ("sections".to_string(), list_tag(COMPOUND_TAG_ID, sections)),
+            (
+                "block_entities".to_string(),
+                list_tag(COMPOUND_TAG_ID, block_entities),
+            ),
+            ("Heightmaps".to_string(), height_maps),
+        ]))
+    }
+}
+
+impl CachedLevel {
+    /// Reads the chunk and its block entities from the region into the cache, replacing any
+    /// cached state of that chunk. Returns whether the region contains the chunk.
+    pub async fn load_chunk(
+        &mut self,
+        region: &RegionFile,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Result<bool> {
+        let tag = match region.read_chunk_nbt(chunk_x, chunk_z).await? {
+            Some(tag) => tag,
+            None => return Ok(false),
+        };
+        let chunk = Chunk::from_nbt(&tag, self.world_height.min_y(), self.world_height.max_y())?;
+        let block_entities = block_entities_from_nbt(&tag)?;
+        let in_chunk = |pos: &BlockPos| {
+            Chunk::section_coord_from(pos.x) == chunk_x
+                && Chunk::section_coord_from(pos.z) == chunk_z
+        };
+        self.block_entities.retain(|pos, _| !in_chunk(pos));
+        self.dirty_block_entities.retain(|pos| !in_chunk(pos));
+        self.block_entities.extend(block_entities);
+        self.insert_chunk(chunk);
+        Ok(true)
+    }
+
+    /// Writes the cached chunk together with its block entities into the region. Returns
+    /// whether the chunk is cached.
+    pub async fn save_chunk(
+        &self,
+        region: &mut RegionFile,
+        chunk_x: i32,
+        chunk_z: i32,
+        compression: RegionCompression,
+        timestamp: u32,
+    ) -> Result<bool> {
+        let chunk = match self.chunk_cache.get(&(chunk_x, chunk_z)) {
+            Some(chunk) => chunk,
+            None => return Ok(false),
+        };
+        let tag = chunk.to_nbt(self.block_entities.iter().filter(|(pos, _)| {
+            Chunk::section_coord_from(pos.x) == chunk_x
+                && Chunk::section_coord_from(pos.z) == chunk_z
+        }))?;
+        region
+            .write_chunk_nbt(chunk_x, chunk_z, &tag, compression, timestamp)
+            .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drax::nbt::Tag;
+
+    use crate::common::chunk::anvil::{
+        compound_int, list_tag, string_tag, RegionCompression, RegionFile, COMPOUND_TAG_ID,
+        DATA_VERSION, STRING_TAG_ID,
+    };
+    use crate::common::chunk::edit::BlockEntity;
+    use crate::common::chunk::light::{LightLayer, VanillaLightProperties};
+    use crate::common::chunk::{CachedLevel, Chunk};
+    use crate::common::play::BlockPos;
+    use crate::common::registry::GLOBAL_REGISTRIES;
+
+    #[tokio::test]
+    pub async fn region_round_trip() -> drax::prelude::Result<()> {
+        let stone = GLOBAL_REGISTRIES.blocks.default_state_id("stone").unwrap();
+        let mut chunk = Chunk::new(33, -2);
+        chunk.rewrite_plane(-64, stone).unwrap();
+        chunk.set_block_id(3, 10, 7, stone).unwrap();
+
+        let mut region = RegionFile::new();
+        region
+            .write_chunk(&chunk, RegionCompression::Zlib, 1_670_000_000)
+            .await?;
+        let region = RegionFile::from_bytes(&region.to_bytes()?)?;
+        assert!(region.contains(1, 30));
+        assert_eq!(region.timestamp(33, -2), Some(1_670_000_000));
+
+        let read = region.read_chunk(33, -2, -64, 320).await?.unwrap();
+        assert_eq!(read.pos(), (33, -2));
+        assert_eq!(read.get_block_id(3, 10, 7).unwrap(), stone);
+        assert_eq!(read.get_block_id(15, -64, 15).unwrap(), stone);
+        assert_eq!(read.get_block_id(3, 11, 7).unwrap(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn level_round_trip() -> drax::prelude::Result<()> {
+        let blocks = &GLOBAL_REGISTRIES.blocks;
+        let chest = blocks.default_state_id("chest").unwrap();
+        let glowstone = blocks.default_state_id("glowstone").unwrap();
+        let chest_pos = BlockPos {
+            x: -30,
+            y: 70,
+            z: 5,
+        };
+        let glowstone_pos = BlockPos {
+            x: -28,
+            y: 70,
+            z: 5,
+        };
+        let chest_entity = BlockEntity::new(
+            "chest",
+            Tag::CompoundTag(vec![("Items".to_string(), Tag::TagList(0, vec![]))]),
+        );
+
+        let mut level = CachedLevel::default();
+        level.set_block(chest_pos, chest).unwrap();
+        level.set_block(glowstone_pos, glowstone).unwrap();
+        level.set_block_entity(chest_pos, chest_entity.clone());
+        let mut region = RegionFile::new();
+        // only lit chunks are marked as such
+        level
+            .save_chunk(&mut region, -2, 0, RegionCompression::Zlib, 0)
+            .await?;
+        let tag = region.read_chunk_nbt(-2, 0).await?.unwrap();
+        assert_eq!(compound_int(&tag, "isLightOn"), Some(0));
+
+        level.relight_all(&VanillaLightProperties).unwrap();
+        assert!(
+            level
+                .save_chunk(&mut region, -2, 0, RegionCompression::Zlib, 0)
+                .await?
+        );
+        assert!(
+            !level
+                .save_chunk(&mut region, 5, 5, RegionCompression::Zlib, 0)
+                .await?
+        );
+        let tag = region.read_chunk_nbt(-2, 0).await?.unwrap();
+        assert_eq!(compound_int(&tag, "isLightOn"), Some(1));
+
+        let mut loaded = CachedLevel::default();
+        assert!(loaded.load_chunk(&region, -2, 0).await?);
+        assert!(!loaded.load_chunk(&region, 5, 5).await?);
+        assert_eq!(loaded.get_block(chest_pos).unwrap(), chest);
+        assert_eq!(loaded.block_entity(chest_pos), Some(&chest_entity));
+        assert_eq!(loaded.block_entities().count(), 1);
+        let chunk = loaded.clone_cached(-2, 0);
+        assert_eq!(chunk.light(), level.clone_cached(-2, 0).light());
+        assert_eq!(chunk.get_light(LightLayer::Block, 4, 70, 5), 15);
+        Ok(())
+    }
+
+    #[test]
+    pub fn rejects_unsupported_chunks() {
+        let chunk_tag = |data_version: i32, state: &str, biome: &str| {
+            let section = Tag::CompoundTag(vec![
+                ("Y".to_string(), Tag::TagByte(0)),
+                (
+                    "block_states".to_string(),
+                    Tag::CompoundTag(vec![(
+                        "palette".to_string(),
+                        list_tag(
+                            COMPOUND_TAG_ID,
+                            vec![Tag::CompoundTag(vec![(
+                                "Name".to_string(),
+                                string_tag(state),
+                            )])],
+                        ),
+                    )]),
+                ),
+                (
+                    "biomes".to_string(),
+                    Tag::CompoundTag(vec![(
+                        "palette".to_string(),
+                        list_tag(STRING_TAG_ID, vec![string_tag(biome)]),
+                    )]),
+                ),
+            ]);
+            Tag::CompoundTag(vec![
+                ("DataVersion".to_string(), Tag::TagInt(data_version)),
+                ("xPos".to_string(), Tag::TagInt(0)),
+                ("zPos".to_string(), Tag::TagInt(0)),
+                (
+                    "sections".to_string(),
+                    list_tag(COMPOUND_TAG_ID, vec![section]),
+                ),
+            ])
+        };
+        let load = |tag: Tag| Chunk::from_nbt(&tag, -64, 320);
+        let (stone, plains, unknown) = ("minecraft:stone", "minecraft:plains", "minecraft:unknown");
+
+        let chunk = load(chunk_tag(DATA_VERSION, stone, plains)).unwrap();
+        assert_eq!(
+            chunk.get_block_id(0, 0, 0).unwrap(),
+            GLOBAL_REGISTRIES.blocks.default_state_id(stone).unwrap()
+        );
+        assert!(load(chunk_tag(DATA_VERSION + 1, stone, plains)).is_err());
+        assert!(load(chunk_tag(DATA_VERSION, unknown, plains)).is_err());
+        assert!(load(chunk_tag(DATA_VERSION, stone, unknown)).is_err());
+    }
+}
@@ -0,0 +1,466 @@
+use std::collections::BTreeMap;
+
+use drax::err_explain;
+use drax::nbt::Tag;
+use drax::prelude::{PacketComponent, Size};
+use drax::transport::buffer::var_num::size_var_int;
+
+use crate::clientbound::play::{BlockEntityInfo, ClientboundPlayRegistry, LevelChunkData};
+use crate::common::bit_storage::BitSetValidationError;
+use crate::common::chunk::light::{LightLayer, LightProperties};
+use crate::common::chunk::{CachedLevel, Chunk, Strategy};
+use crate::common::play::{BlockPos, BlockUpdate, SectionPos};
+use crate::common::registry::block::namespaced;
+use crate::common::registry::{RegistryKey, GLOBAL_REGISTRIES};
+
+/// The type and data of a block entity, without its position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEntity {
+    pub id: String,
+    pub data: Tag,
+}
+
+impl BlockEntity {
+    pub fn new<S: AsRef<str>>(id: S, data: Tag) -> Self {
+        Self {
+            id: namespaced(id.as_ref()),
+            data,
+        }
+    }
+
+    /// The id of the block entity type in the registry, if it is known.
+    pub fn type_id(&self) -> Option<i32> {
+        GLOBAL_REGISTRIES.get_id(RegistryKey::BlockEntityTypes, &self.id)
+    }
+
+    fn registered_type_id(&self) -> drax::prelude::Result<i32> {
+        self.type_id()
+            .ok_or_else(|| err_explain!(format!("Unknown block entity type {}.", self.id)))
+    }
+}
+
+fn same_block(first: i32, second: i32) -> bool {
+    let blocks = &GLOBAL_REGISTRIES.blocks;
+    blocks.block_for_state(first).map(|block| block.id())
+        == blocks.block_for_state(second).map(|block| block.id())
+}
+
+fn size_value(size: Size) -> usize {
+    match size {
+        Size::Dynamic(x) | Size::Constant(x) => x,
+    }
+}
+
+impl CachedLevel {
+    /// The block state at the given position; unknown chunks and positions outside of the
+    /// world are air.
+    pub fn get_block(&self, pos: BlockPos) -> Result<i32, BitSetValidationError> {
+        match self.chunk_cache.get(&(
+            Chunk::section_coord_from(pos.x),
+            Chunk::section_coord_from(pos.z),
+        )) {
+            Some(chunk) => chunk.get_block_id(pos.x, pos.y, pos.z),
+            None => Ok(0),
+        }
+    }
+
+    /// Sets the block state at the given position, creating the chunk if it is unknown, and
+    /// returns the previous state. The block entity at the position is removed once the block
+    /// itself changes.
+    pub fn set_block(&mut self, pos: BlockPos, state: i32) -> Result<i32, BitSetValidationError> {
+        let (chunk_x, chunk_z) = (
+            Chunk::section_coord_from(pos.x),
+            Chunk::section_coord_from(pos.z),
+        );
+        let world_height = self.world_height;
+        if pos.y < world_height.min_y() || pos.y >= world_height.max_y() {
+            return Err(BitSetValidationError("Out of range.".to_string()));
+        }
+        let chunk = self
+            .chunk_cache
+            .entry((chunk_x, chunk_z))
+            .or_insert_with(|| Chunk::in_world(chunk_x, chunk_z, world_height));
+        let previous = chunk.get_block_id(pos.x, pos.y, pos.z)?;
+        if previous != state {
+            chunk.set_block_id(pos.x, pos.y, pos.z, state)?;
+            self.dirty_blocks.insert(pos);
+            self.unlit_chunks.insert((chunk_x, chunk_z));
+            if !same_block(previous, state) && self.block_entities.remove(&pos).is_some() {
+                self.dirty_block_entities.remove(&pos);
+            }
+        }
+        Ok(previous)
+    }
+
+    /// Sets every block in the box between both corners, inclusive, and returns how many
+    /// blocks changed. The box is clamped to the world height.
+    pub fn fill(
+        &mut self,
+        from: BlockPos,
+        to: BlockPos,
+        state: i32,
+    ) -> Result<usize, BitSetValidationError> {
+        let min_y = from.y.min(to.y).max(self.world_height.min_y());
+        let max_y = from.y.max(to.y).min(self.world_height.max_y() - 1);
+        let mut changed = 0;
+        for x in from.x.min(to.x)..=from.x.max(to.x) {
+            for z in from.z.min(to.z)..=from.z.max(to.z) {
+                for y in min_y..=max_y {
+                    if self.set_block(BlockPos { x, y, z }, state)? != state {
+                        changed += 1;
+                    }
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Replaces every occurrence of a block state in the cached chunks and returns how many
+    /// blocks changed.
+    pub fn replace(&mut self, from: i32, to: i32) -> Result<usize, BitSetValidationError> {
+        if from == to {
+            return Ok(0);
+        }
+        let mut positions = Vec::new();
+        for ((chunk_x, chunk_z), chunk) in &self.chunk_cache {
+            let min_section = Chunk::get_min_section(chunk.min_height);
+            for (idx, section) in chunk.chunk_sections.iter().enumerate() {
+                let values = section.states.values(Strategy::Section)?;
+                if !values.contains(&from) {
+                    continue;
+                }
+                for (state_idx, _) in values
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, state)| *state == from)
+                {
+                    let state_idx = state_idx as i32;
+                    positions.push(BlockPos {
+                        x: Chunk::position_coord_from(*chunk_x) + (state_idx & 0xF),
+                        y: Chunk::position_coord_from(min_section + idx as i32) + (state_idx >> 8),
+                        z: Chunk::position_coord_from(*chunk_z) + ((state_idx >> 4) & 0xF),
+                    });
+                }
+            }
+        }
+        for pos in &positions {
+            self.set_block(*pos, to)?;
+        }
+        Ok(positions.len())
+    }
+
+    pub fn block_entity(&self, pos: BlockPos) -> Option<&BlockEntity> {
+        self.block_entities.get(&pos)
+    }
+
+    pub fn block_entities(&self) -> impl Iterator<Item = (&BlockPos, &BlockEntity)> {
+        self.block_entities.iter()
+    }
+
+    /// Sets the block entity at the given position, which is sent to viewers with the next
+    /// updates.
+    pub fn set_block_entity(&mut self, pos: BlockPos, block_entity: BlockEntity) {
+        self.block_entities.insert(pos, block_entity);
+        self.dirty_block_entities.insert(pos);
+    }
+
+    pub fn remove_block_entity(&mut self, pos: BlockPos) -> Option<BlockEntity> {
+        self.dirty_block_entities.remove(&pos);
+        self.block_entities.remove(&pos)
+    }
+
+    /// Marks the whole chunk to be resent, for changes not made through the level.
+    pub fn mark_chunk_dirty(&mut self, x: i32, z: i32) {
+        self.dirty_chunks.insert((x, z));
+    }
+
+    pub fn dirty_blocks(&self) -> impl Iterator<Item = &BlockPos> {
+        self.dirty_blocks.iter()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty_blocks.is_empty()
+            || !self.dirty_chunks.is_empty()
+            || !self.dirty_block_entities.is_empty()
+    }
+
+    fn chunk_packet(&self, x: i32, z: i32) -> drax::prelude::Result<ClientboundPlayRegistry> {
+        let chunk = self.clone_cached(x, z);
+        let light_data = chunk.light_update_data(true)?;
+        let mut block_entities: Vec<(&BlockPos, &BlockEntity)> = self
+            .block_entities
+            .iter()
+            .filter(|(pos, _)| {
+                Chunk::section_coord_from(pos.x) == x && Chunk::section_coord_from(pos.z) == z
+            })
+            .collect();
+        block_entities.sort_by_key(|(pos, _)| **pos);
+        let block_entities = block_entities
+            .into_iter()
+            .map(|(pos, block_entity)| {
+                Ok(BlockEntityInfo {
+                    packed_xz: (((pos.x & 0xF) << 4) | (pos.z & 0xF)) as u8,
+                    y: pos.y as i16,
+                    block_type: block_entity.registered_type_id()?,
+                    tag: Some(block_entity.data.clone()),
+                })
+            })
+            .collect::<drax::prelude::Result<Vec<_>>>()?;
+        Ok(ClientboundPlayRegistry::LevelChunkWithLight {
+            chunk_data: LevelChunkData {
+                chunk,
+                block_entities,
+            },
+            light_data,
+        })
+    }
+
+    fn chunk_packet_size(&self, x: i32, z: i32) -> drax::prelude::Result<usize> {
+        let chunk = match self.chunk_cache.get(&(x, z)) {
+            Some(chunk) => chunk,
+            None => return Ok(0),
+        };
+        let light_size: usize = [
+            chunk.light.sections(LightLayer::Sky),
+            chunk.light.sections(LightLayer::Block),
+        ]
+        .iter()
+        .flat_map(|sections| sections.iter())
+        .filter(|section| section.is_some())
+        .map(|_| 2048 + size_var_int(2048))
+        .sum();
+        Ok(size_value(Chunk::size(chunk, &mut ())?) + light_size)
+    }
+
+    /// Clears the dirty set and creates the packets updating viewers with every change. <br />
+    ///
+    /// Single changed blocks are sent as `BlockUpdate`, several changes within a section as
+    /// `SectionBlocksUpdate` and chunks are resent as a whole with `LevelChunkWithLight`
+    /// once that is smaller than the section updates. Changed block entities outside of resent
+    /// chunks follow as `BlockEntityData`. <br />
+    ///
+    /// Chunks whose blocks changed are relit with the given light properties first, so resent
+    /// chunks carry up to date light. Block entities of types missing from the registry are
+    /// rejected as they cannot be sent.
+    pub fn drain_updates<P: LightProperties>(
+        &mut self,
+        properties: &P,
+    ) -> drax::prelude::Result<Vec<ClientboundPlayRegistry>> {
+        let mut unlit_chunks: Vec<(i32, i32)> = self.unlit_chunks.drain().collect();
+        unlit_chunks.sort();
+        self.relight(&unlit_chunks, properties)
+            .map_err(|err| err_explain!(err.0))?;
+
+        let mut by_chunk: BTreeMap<(i32, i32), BTreeMap<SectionPos, Vec<BlockUpdate>>> =
+            BTreeMap::new();
+        for pos in self.dirty_blocks.drain() {
+            let chunk_pos = (
+                Chunk::section_coord_from(pos.x),
+                Chunk::section_coord_from(pos.z),
+            );
+            if self.dirty_chunks.contains(&chunk_pos) {
+                continue;
+            }
+            by_chunk
+                .entry(chunk_pos)
+                .or_default()
+                .entry(SectionPos::from(pos))
+                .or_default()
+                .push(BlockUpdate {
+                    block_id: 0,
+                    block_pos: pos,
+                });
+        }
+
+        let mut packets = Vec::new();
+        let mut dirty_chunks: Vec<(i32, i32)> = self.dirty_chunks.drain().collect();
+        for ((chunk_x, chunk_z), sections) in by_chunk {
+            let mut section_size = 0;
+            for updates in sections.values_mut() {
+                for update in updates.iter_mut() {
+                    update.block_id = self
+                        .get_block(update.block_pos)
+                        .map_err(|err| err_explain!(err.0))?;
+                    section_size +=
+                        size_value(<BlockUpdate as PacketComponent<()>>::size(update, &mut ())?);
+                }
+                section_size += 8 + 1 + size_var_int(updates.len() as i32);
+            }
+            if section_size > self.chunk_packet_size(chunk_x, chunk_z)? {
+                dirty_chunks.push((chunk_x, chunk_z));
+                continue;
+            }
+            for (section_pos, mut updates) in sections {
+                if updates.len() == 1 {
+                    let update = updates.remove(0);
+                    packets.push(ClientboundPlayRegistry::BlockUpdate {
+                        pos: update.block_pos,
+                        state: update.block_id,
+                    });
+                } else {
+                    packets.push(ClientboundPlayRegistry::SectionBlocksUpdate {
+                        section_pos,
+                        suppress_light_update: false,
+                        update_info: updates,
+                    });
+                }
+            }
+        }
+        dirty_chunks.sort();
+        for (chunk_x, chunk_z) in &dirty_chunks {
+            packets.push(self.chunk_packet(*chunk_x, *chunk_z)?);
+        }
+
+        let mut dirty_block_entities: Vec<BlockPos> = self.dirty_block_entities.drain().collect();
+        dirty_block_entities.sort();
+        for pos in dirty_block_entities {
+            let chunk_pos = (
+                Chunk::section_coord_from(pos.x),
+                Chunk::section_coord_from(pos.z),
+            );
+            if dirty_chunks.contains(&chunk_pos) {
+                continue;
+            }
+            if let Some(block_entity) = self.block_entities.get(&pos) {
+                packets.push(ClientboundPlayRegistry::BlockEntityData {
+                    pos,
+                    block_entity_type: block_entity.registered_type_id()?,
+                    tag: Some(block_entity.data.clone()),
+                });
+            }
+        }
+        Ok(packets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drax::nbt::Tag;
+
+    use crate::clientbound::play::ClientboundPlayRegistry;
+    use crate::common::chunk::edit::BlockEntity;
+    use crate::common::chunk::light::{LightLayer, VanillaLightProperties};
+    use crate::common::chunk::CachedLevel;
+    use crate::common::play::BlockPos;
+    use crate::common::registry::GLOBAL_REGISTRIES;
+
+    #[test]
+    pub fn level_editing() {
+        let blocks = &GLOBAL_REGISTRIES.blocks;
+        let stone = blocks.default_state_id("stone").unwrap();
+        let dirt = blocks.default_state_id("dirt").unwrap();
+        let mut level = CachedLevel::default();
+
+        let pos = BlockPos {
+            x: -3,
+            y: 70,
+            z: 20,
+        };
+        assert_eq!(level.set_block(pos, stone).unwrap(), 0);
+        assert_eq!(level.get_block(pos).unwrap(), stone);
+        let packets = level.drain_updates(&VanillaLightProperties).unwrap();
+        assert!(matches!(
+            packets.as_slice(),
+            [ClientboundPlayRegistry::BlockUpdate { pos: update, state }]
+                if *update == pos && *state == stone
+        ));
+        assert!(!level.is_dirty());
+
+        let changed = level
+            .fill(
+                BlockPos { x: 0, y: 0, z: 0 },
+                BlockPos { x: 1, y: 1, z: 0 },
+                stone,
+            )
+            .unwrap();
+        assert_eq!(changed, 4);
+        let packets = level.drain_updates(&VanillaLightProperties).unwrap();
+        assert!(matches!(
+            packets.as_slice(),
+            [ClientboundPlayRegistry::SectionBlocksUpdate { update_info, .. }]
+                if update_info.len() == 4
+        ));
+
+        assert_eq!(level.replace(stone, dirt).unwrap(), 5);
+        assert_eq!(
+            level.get_block(BlockPos { x: 1, y: 1, z: 0 }).unwrap(),
+            dirt
+        );
+        assert_eq!(level.get_block(pos).unwrap(), dirt);
+        level.drain_updates(&VanillaLightProperties).unwrap();
+
+        level
+            .fill(
+                BlockPos {
+                    x: 16,
+                    y: -64,
+                    z: 0,
+                },
+                BlockPos {
+                    x: 31,
+                    y: 63,
+                    z: 15,
+                },
+                stone,
+            )
+            .unwrap();
+        // sections lit by the sky make the chunk larger than the updates of a few sections
+        let packets = level.drain_updates(&VanillaLightProperties).unwrap();
+        assert!(matches!(
+            packets.as_slice(),
+            [ClientboundPlayRegistry::LevelChunkWithLight { chunk_data, .. }]
+                if chunk_data.chunk.pos() == (1, 0)
+        ));
+    }
+
+    #[test]
+    pub fn relight_changed_chunks() {
+        let glowstone = GLOBAL_REGISTRIES
+            .blocks
+            .default_state_id("glowstone")
+            .unwrap();
+        let mut level = CachedLevel::default();
+        level
+            .fill(
+                BlockPos { x: 0, y: 60, z: 0 },
+                BlockPos {
+                    x: 15,
+                    y: 63,
+                    z: 15,
+                },
+                glowstone,
+            )
+            .unwrap();
+        level.mark_chunk_dirty(0, 0);
+        let packets = level.drain_updates(&VanillaLightProperties).unwrap();
+        match packets.as_slice() {
+            [ClientboundPlayRegistry::LevelChunkWithLight {
+                chunk_data,
+                light_data,
+            }] => {
+                assert_eq!(chunk_data.chunk.get_light(LightLayer::Block, 3, 64, 3), 14);
+                assert_eq!(chunk_data.chunk.get_light(LightLayer::Sky, 3, 64, 3), 15);
+                // light spreads from y 46 to 77
+                assert_eq!(light_data.block_updates.len(), 3);
+            }
+            _ => panic!("Expected the chunk to be resent."),
+        }
+    }
+
+    #[test]
+    pub fn rejects_invalid_edits() {
+        let stone = GLOBAL_REGISTRIES.blocks.default_state_id("stone").unwrap();
+        let mut level = CachedLevel::default();
+        assert!(level
+            .set_block(BlockPos { x: 0, y: 400, z: 0 }, stone)
+            .is_err());
+        assert!(level.chunk_cache.is_empty());
+
+        let pos = BlockPos { x: 0, y: 0, z: 0 };
+        level.set_block(pos, stone).unwrap();
+        level.set_block_entity(pos, BlockEntity::new("unknown", Tag::CompoundTag(vec![])));
+        assert!(level.drain_updates(&VanillaLightProperties).is_err());
+        level.mark_chunk_dirty(0, 0);
+        assert!(level.drain_updates(&VanillaLightProperties).is_err());
+    }
+}
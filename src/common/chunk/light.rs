@@ -0,0 +1,582 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::clientbound::play::LightUpdateData;
+use crate::common::bit_set::BitSet;
+use crate::common::bit_storage::BitSetValidationError;
+use crate::common::chunk::{CachedLevel, Chunk};
+use crate::common::registry::block::BlockState;
+use crate::common::registry::GLOBAL_REGISTRIES;
+
+const NIBBLE_ARRAY_SIZE: usize = 2048;
+const MAX_LIGHT: u8 = 15;
+
+/// Half a byte of light per block of a 16x16x16 section, indexed `y << 8 | z << 4 | x` with
+/// the even index in the lower nibble.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NibbleArray {
+    data: Vec<u8>,
+}
+
+impl Default for NibbleArray {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NibbleArray {
+    pub fn new() -> Self {
+        Self::filled(0)
+    }
+
+    pub fn filled(value: u8) -> Self {
+        let value = value & 0xF;
+        Self {
+            data: vec![value << 4 | value; NIBBLE_ARRAY_SIZE],
+        }
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, BitSetValidationError> {
+        if data.len() != NIBBLE_ARRAY_SIZE {
+            return Err(BitSetValidationError(format!(
+                "Nibble array must be {} bytes but got {}.",
+                NIBBLE_ARRAY_SIZE,
+                data.len()
+            )));
+        }
+        Ok(Self { data })
+    }
+
+    const fn index(x: i32, y: i32, z: i32) -> usize {
+        ((y & 0xF) << 8 | (z & 0xF) << 4 | (x & 0xF)) as usize
+    }
+
+    pub fn get(&self, x: i32, y: i32, z: i32) -> u8 {
+        let idx = Self::index(x, y, z);
+        (self.data[idx >> 1] >> ((idx & 1) * 4)) & 0xF
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, z: i32, value: u8) {
+        let idx = Self::index(x, y, z);
+        let shift = (idx & 1) * 4;
+        self.data[idx >> 1] = (self.data[idx >> 1] & !(0xF << shift)) | ((value & 0xF) << shift);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.iter().all(|byte| *byte == 0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum LightLayer {
+    Sky,
+    Block,
+}
+
+/// The light of a chunk per section. <br />
+///
+/// Like vanilla there is one more section below and above the world; sections without data
+/// have not been lit yet and read as dark.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ChunkLight {
+    sky: Vec<Option<NibbleArray>>,
+    block: Vec<Option<NibbleArray>>,
+}
+
+impl ChunkLight {
+    pub fn new(sections: usize) -> Self {
+        Self {
+            sky: vec![None; sections + 2],
+            block: vec![None; sections + 2],
+        }
+    }
+
+    pub fn sections(&self, layer: LightLayer) -> &[Option<NibbleArray>] {
+        match layer {
+            LightLayer::Sky => &self.sky,
+            LightLayer::Block => &self.block,
+        }
+    }
+
+    fn sections_mut(&mut self, layer: LightLayer) -> &mut Vec<Option<NibbleArray>> {
+        match layer {
+            LightLayer::Sky => &mut self.sky,
+            LightLayer::Block => &mut self.block,
+        }
+    }
+
+    pub fn section(&self, layer: LightLayer, idx: usize) -> Option<&NibbleArray> {
+        self.sections(layer).get(idx)?.as_ref()
+    }
+
+    pub fn set_section(&mut self, layer: LightLayer, idx: usize, data: Option<NibbleArray>) {
+        if let Some(section) = self.sections_mut(layer).get_mut(idx) {
+            *section = data;
+        }
+    }
+
+    fn reset(&mut self, layer: LightLayer) {
+        let sections = self.sections_mut(layer);
+        let len = sections.len();
+        for (idx, section) in sections.iter_mut().enumerate() {
+            *section = Some(match layer {
+                LightLayer::Sky if idx == len - 1 => NibbleArray::filled(MAX_LIGHT),
+                _ => NibbleArray::new(),
+            });
+        }
+    }
+}
+
+/// How blocks interact with light.
+pub trait LightProperties {
+    /// The block light level emitted by the state.
+    fn emission(&self, state_id: i32) -> u8;
+
+    /// How much light is lost passing into the state; at least one level is always lost,
+    /// apart from full sky light travelling straight down through transparent blocks.
+    fn opacity(&self, state_id: i32) -> u8;
+}
+
+/// Light properties derived from the block states in [`GLOBAL_REGISTRIES`]. <br />
+///
+/// There is no data generator report for light, so these follow the vanilla values for light
+/// sources; opacity is looked up per state in the bundled `block_opacity.json`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct VanillaLightProperties;
+
+static VANILLA_LIGHT: std::sync::LazyLock<Vec<(u8, u8)>> = std::sync::LazyLock::new(|| {
+    // the light block level of every 1.19.3 state, indexed by state id
+    let opacity: Vec<u8> = serde_json::from_slice(include_bytes!("../registry/block_opacity.json"))
+        .expect("bundled block_opacity.json is a list of light levels");
+    let blocks = &GLOBAL_REGISTRIES.blocks;
+    let mut table = Vec::new();
+    for block in blocks.blocks() {
+        let max_state_id = block.max_state_id() as usize;
+        if table.len() <= max_state_id {
+            table.resize(max_state_id + 1, (0, MAX_LIGHT));
+        }
+        for state_id in block.min_state_id()..=block.max_state_id() {
+            if let Some(state) = blocks.state(state_id) {
+                table[state_id as usize] = (
+                    vanilla_emission(&state),
+                    opacity.get(state_id as usize).copied().unwrap_or(MAX_LIGHT),
+                );
+            }
+        }
+    }
+    table
+});
+
+fn vanilla_emission(state: &BlockState) -> u8 {
+    let name = state
+        .block
+        .split_once(':')
+        .map_or(state.block.as_str(), |(_, name)| name);
+    let lit = state.get("lit") != Some("false");
+    let level = |name: &str| {
+        state
+            .get(name)
+            .and_then(|value| value.parse::<u8>().ok())
+            .unwrap_or(0)
+    };
+    match name {
+        "glowstone"
+        | "lava"
+        | "fire"
+        | "sea_lantern"
+        | "jack_o_lantern"
+        | "beacon"
+        | "end_portal"
+        | "end_gateway"
+        | "conduit"
+        | "lantern"
+        | "shroomlight"
+        | "ochre_froglight"
+        | "verdant_froglight"
+        | "pearlescent_froglight" => 15,
+        "campfire" | "redstone_lamp" if lit => 15,
+        "cave_vines" | "cave_vines_plant" if state.get("berries") == Some("true") => 14,
+        "torch" | "wall_torch" | "end_rod" => 14,
+        "furnace" | "blast_furnace" | "smoker" if lit => 13,
+        "nether_portal" => 11,
+        "soul_torch" | "soul_wall_torch" | "soul_lantern" | "soul_fire" | "crying_obsidian" => 10,
+        "soul_campfire" if lit => 10,
+        "redstone_ore" | "deepslate_redstone_ore" if lit => 9,
+        "redstone_torch" | "redstone_wall_torch" if lit => 7,
+        "ender_chest" | "enchanting_table" | "glow_lichen" => 7,
+        "sculk_catalyst" => 6,
+        "amethyst_cluster" => 5,
+        "large_amethyst_bud" => 4,
+        "magma_block" => 3,
+        "medium_amethyst_bud" => 2,
+        "small_amethyst_bud" | "brewing_stand" | "brown_mushroom" | "dragon_egg"
+        | "end_portal_frame" | "sculk_sensor" => 1,
+        "light" => level("level"),
+        "respawn_anchor" => [0, 3, 7, 11, 15][level("charges").min(4) as usize],
+        "sea_pickle" if state.get("waterlogged") == Some("true") => 3 + 3 * level("pickles"),
+        _ if name.ends_with("candle") && lit => 3 * level("candles"),
+        _ if name.ends_with("candle_cake") && lit => 3,
+        _ => 0,
+    }
+}
+
+impl LightProperties for VanillaLightProperties {
+    fn emission(&self, state_id: i32) -> u8 {
+        VANILLA_LIGHT
+            .get(state_id as usize)
+            .map_or(0, |(emission, _)| *emission)
+    }
+
+    fn opacity(&self, state_id: i32) -> u8 {
+        VANILLA_LIGHT
+            .get(state_id as usize)
+            .map_or(MAX_LIGHT, |(_, opacity)| *opacity)
+    }
+}
+
+fn mask(bits: &[usize]) -> drax::prelude::Result<BitSet> {
+    let mut words = vec![0u64; bits.iter().max().map_or(0, |max| max / 64 + 1)];
+    for bit in bits {
+        words[bit / 64] |= 1 << (bit % 64);
+    }
+    BitSet::value_of(words)
+}
+
+fn mask_contains(mask: &BitSet, bit: usize) -> bool {
+    mask.words
+        .get(bit / 64)
+        .map_or(false, |word| word & (1 << (bit % 64)) != 0)
+}
+
+impl Chunk {
+    pub fn light(&self) -> &ChunkLight {
+        &self.light
+    }
+
+    pub fn light_mut(&mut self) -> &mut ChunkLight {
+        &mut self.light
+    }
+
+    fn light_section_index(&self, y: i32) -> i32 {
+        Chunk::section_coord_from(y) - Chunk::get_min_section(self.min_height) + 1
+    }
+
+    /// The light level at the given position; positions above the world have full sky light.
+    pub fn get_light(&self, layer: LightLayer, x: i32, y: i32, z: i32) -> u8 {
+        let idx = self.light_section_index(y);
+        if idx < 0 {
+            return 0;
+        }
+        match self.light.section(layer, idx as usize) {
+            Some(section) => section.get(x, y, z),
+            None if layer == LightLayer::Sky && y >= self.max_height => MAX_LIGHT,
+            None => 0,
+        }
+    }
+
+    pub fn set_light(
+        &mut self,
+        layer: LightLayer,
+        x: i32,
+        y: i32,
+        z: i32,
+        value: u8,
+    ) -> Result<(), BitSetValidationError> {
+        let idx = self.light_section_index(y);
+        match self
+            .light
+            .sections_mut(layer)
+            .get_mut(usize::try_from(idx).unwrap_or(usize::MAX))
+        {
+            Some(section) => {
+                section
+                    .get_or_insert_with(NibbleArray::new)
+                    .set(x, y, z, value);
+                Ok(())
+            }
+            None => Err(BitSetValidationError(format!(
+                "Light section {} is out of range.",
+                idx
+            ))),
+        }
+    }
+
+    /// Creates the light data sent along with this chunk. Lit sections without any light are
+    /// sent as empty, sections which were never lit are left out.
+    pub fn light_update_data(&self, trust_edges: bool) -> drax::prelude::Result<LightUpdateData> {
+        let mut masks = [vec![], vec![], vec![], vec![]];
+        let mut updates = [vec![], vec![]];
+        for (layer_idx, layer) in [LightLayer::Sky, LightLayer::Block].into_iter().enumerate() {
+            for (idx, section) in self.light.sections(layer).iter().enumerate() {
+                match section {
+                    Some(section) if section.is_empty() => masks[layer_idx + 2].push(idx),
+                    Some(section) => {
+                        masks[layer_idx].push(idx);
+                        updates[layer_idx].push(section.as_bytes().to_vec());
+                    }
+                    None => (),
+                }
+            }
+        }
+        let [sky_updates, block_updates] = updates;
+        Ok(LightUpdateData {
+            trust_edges,
+            sky_y_mask: mask(&masks[0])?,
+            block_y_mask: mask(&masks[1])?,
+            empty_sky_y_mask: mask(&masks[2])?,
+            empty_block_y_mask: mask(&masks[3])?,
+            sky_updates,
+            block_updates,
+        })
+    }
+
+    /// Replaces the light of every section named by the light data.
+    pub fn apply_light_update_data(
+        &mut self,
+        data: &LightUpdateData,
+    ) -> Result<(), BitSetValidationError> {
+        for (layer, y_mask, empty_mask, updates) in [
+            (
+                LightLayer::Sky,
+                &data.sky_y_mask,
+                &data.empty_sky_y_mask,
+                &data.sky_updates,
+            ),
+            (
+                LightLayer::Block,
+                &data.block_y_mask,
+                &data.empty_block_y_mask,
+                &data.block_updates,
+            ),
+        ] {
+            let mut updates = updates.iter();
+            for idx in 0..self.light.sections(layer).len() {
+                if mask_contains(y_mask, idx) {
+                    let update = updates.next().ok_or_else(|| {
+                        BitSetValidationError(format!("Missing light data for section {}.", idx))
+                    })?;
+                    self.light.set_section(
+                        layer,
+                        idx,
+                        Some(NibbleArray::from_bytes(update.clone())?),
+                    );
+                } else if mask_contains(empty_mask, idx) {
+                    self.light.set_section(layer, idx, Some(NibbleArray::new()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const DIRECTIONS: [(i32, i32, i32); 6] = [
+    (0, -1, 0),
+    (0, 1, 0),
+    (-1, 0, 0),
+    (1, 0, 0),
+    (0, 0, -1),
+    (0, 0, 1),
+];
+
+impl CachedLevel {
+    fn light_at(&self, layer: LightLayer, x: i32, y: i32, z: i32) -> Option<u8> {
+        let chunk = self.chunk_cache.get(&(x >> 4, z >> 4))?;
+        if y < chunk.min_height || y >= chunk.max_height {
+            return None;
+        }
+        Some(chunk.get_light(layer, x & 0xF, y, z & 0xF))
+    }
+
+    /// Recomputes block and sky light of every cached chunk.
+    pub fn relight_all<P: LightProperties>(
+        &mut self,
+        properties: &P,
+    ) -> Result<(), BitSetValidationError> {
+        let chunks: Vec<(i32, i32)> = self.chunk_cache.keys().copied().collect();
+        self.relight(&chunks, properties)
+    }
+
+    /// Recomputes block and sky light of the given cached chunks from scratch. <br />
+    ///
+    /// Light spreads into neighboring cached chunks and light already present at the borders
+    /// of neighbors which are not relit spreads into the relit chunks. Light is only ever
+    /// raised in those neighbors, so they should be relit as well after removing light sources
+    /// close to them.
+    pub fn relight<P: LightProperties>(
+        &mut self,
+        chunks: &[(i32, i32)],
+        properties: &P,
+    ) -> Result<(), BitSetValidationError> {
+        let relit: HashSet<(i32, i32)> = chunks
+            .iter()
+            .copied()
+            .filter(|pos| self.chunk_cache.contains_key(pos))
+            .collect();
+        for layer in [LightLayer::Block, LightLayer::Sky] {
+            let mut queue = VecDeque::new();
+            for pos in &relit {
+                self.seed_light(layer, *pos, &relit, properties, &mut queue)?;
+            }
+            self.propagate_light(layer, properties, queue)?;
+        }
+        Ok(())
+    }
+
+    fn seed_light<P: LightProperties>(
+        &mut self,
+        layer: LightLayer,
+        (chunk_x, chunk_z): (i32, i32),
+        relit: &HashSet<(i32, i32)>,
+        properties: &P,
+        queue: &mut VecDeque<(i32, i32, i32, u8)>,
+    ) -> Result<(), BitSetValidationError> {
+        let chunk = match self.chunk_cache.get_mut(&(chunk_x, chunk_z)) {
+            Some(chunk) => chunk,
+            None => return Ok(()),
+        };
+        chunk.light.reset(layer);
+        let (min_height, max_height) = (chunk.min_height, chunk.max_height);
+        let (base_x, base_z) = (
+            Chunk::position_coord_from(chunk_x),
+            Chunk::position_coord_from(chunk_z),
+        );
+        for x in 0..16 {
+            for z in 0..16 {
+                match layer {
+                    LightLayer::Block => {
+                        for y in min_height..max_height {
+                            let emission = properties.emission(chunk.get_block_id(x, y, z)?);
+                            if emission > 0 {
+                                chunk.set_light(layer, x, y, z, emission)?;
+                                queue.push_back((base_x + x, y, base_z + z, emission));
+                            }
+                        }
+                    }
+                    LightLayer::Sky => {
+                        for y in (min_height..max_height).rev() {
+                            if properties.opacity(chunk.get_block_id(x, y, z)?) > 0 {
+                                break;
+                            }
+                            chunk.set_light(layer, x, y, z, MAX_LIGHT)?;
+                            queue.push_back((base_x + x, y, base_z + z, MAX_LIGHT));
+                        }
+                    }
+                }
+            }
+        }
+
+        // light at the borders of neighbors which keep their light flows back in
+        for (dx, dz) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let neighbor = (chunk_x + dx, chunk_z + dz);
+            if relit.contains(&neighbor) || !self.chunk_cache.contains_key(&neighbor) {
+                continue;
+            }
+            for offset in 0..16 {
+                let (x, z) = match (dx, dz) {
+                    (-1, _) => (base_x - 1, base_z + offset),
+                    (1, _) => (base_x + 16, base_z + offset),
+                    (_, -1) => (base_x + offset, base_z - 1),
+                    _ => (base_x + offset, base_z + 16),
+                };
+                for y in min_height..max_height {
+                    match self.light_at(layer, x, y, z) {
+                        Some(level) if level > 1 => queue.push_back((x, y, z, level)),
+                        _ => (),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn propagate_light<P: LightProperties>(
+        &mut self,
+        layer: LightLayer,
+        properties: &P,
+        mut queue: VecDeque<(i32, i32, i32, u8)>,
+    ) -> Result<(), BitSetValidationError> {
+        while let Some((x, y, z, level)) = queue.pop_front() {
+            for (dx, dy, dz) in DIRECTIONS {
+                let (nx, ny, nz) = (x + dx, y + dy, z + dz);
+                let chunk = match self.chunk_cache.get_mut(&(nx >> 4, nz >> 4)) {
+                    Some(chunk) if ny >= chunk.min_height && ny < chunk.max_height => chunk,
+                    _ => continue,
+                };
+                let opacity = properties.opacity(chunk.get_block_id(nx & 0xF, ny, nz & 0xF)?);
+                let next =
+                    if layer == LightLayer::Sky && dy == -1 && level == MAX_LIGHT && opacity == 0 {
+                        MAX_LIGHT
+                    } else {
+                        level.saturating_sub(opacity.max(1))
+                    };
+                if next > chunk.get_light(layer, nx & 0xF, ny, nz & 0xF) {
+                    chunk.set_light(layer, nx & 0xF, ny, nz & 0xF, next)?;
+                    queue.push_back((nx, ny, nz, next));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::chunk::light::{LightLayer, LightProperties, VanillaLightProperties};
+    use crate::common::chunk::{CachedLevel, Chunk};
+    use crate::common::registry::GLOBAL_REGISTRIES;
+
+    #[test]
+    pub fn light_propagation() {
+        let blocks = &GLOBAL_REGISTRIES.blocks;
+        let stone = blocks.default_state_id("stone").unwrap();
+        let glowstone = blocks.default_state_id("glowstone").unwrap();
+
+        let mut chunk = Chunk::new(0, 0);
+        chunk.rewrite_plane(0, stone).unwrap();
+        chunk.set_block_id(15, -10, 8, glowstone).unwrap();
+        let mut level = CachedLevel::default();
+        level.insert_chunk(chunk);
+        level.insert_chunk(Chunk::new(1, 0));
+        level.relight_all(&VanillaLightProperties).unwrap();
+
+        let chunk = level.clone_cached(0, 0);
+        assert_eq!(chunk.get_light(LightLayer::Sky, 4, 1, 4), 15);
+        assert_eq!(chunk.get_light(LightLayer::Sky, 4, 0, 4), 0);
+        assert_eq!(chunk.get_light(LightLayer::Block, 15, -10, 8), 15);
+        assert_eq!(chunk.get_light(LightLayer::Block, 15, -10, 5), 12);
+        // the neighbor has no ceiling and is fully lit by the sky
+        let neighbor = level.clone_cached(1, 0);
+        assert_eq!(neighbor.get_light(LightLayer::Block, 0, -10, 8), 14);
+        assert_eq!(neighbor.get_light(LightLayer::Sky, 0, -60, 8), 15);
+        // sky light spreads in beneath the stone from the neighbor
+        assert_eq!(chunk.get_light(LightLayer::Sky, 15, -1, 8), 14);
+
+        let data = chunk.light_update_data(true).unwrap();
+        assert_eq!(
+            data.sky_updates.len() + data.empty_sky_y_mask.cardinality() as usize,
+            26
+        );
+        assert_eq!(data.block_updates.len(), 3);
+    }
+
+    #[test]
+    pub fn vanilla_opacity() {
+        let opacity = |state: &str| {
+            let state_id = GLOBAL_REGISTRIES.blocks.parse_state_id(state).unwrap();
+            VanillaLightProperties.opacity(state_id)
+        };
+        assert_eq!(opacity("stone"), 15);
+        assert_eq!(opacity("air"), 0);
+        assert_eq!(opacity("glass"), 0);
+        assert_eq!(opacity("tinted_glass"), 15);
+        assert_eq!(opacity("water"), 1);
+        assert_eq!(opacity("oak_leaves"), 1);
+        assert_eq!(opacity("oak_slab[type=bottom]"), 0);
+        assert_eq!(opacity("oak_slab[type=bottom,waterlogged=true]"), 1);
+        assert_eq!(opacity("oak_slab[type=double]"), 15);
+        assert_eq!(opacity("snow[layers=8]"), 15);
+    }
+}
@@ -0,0 +1,840 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+
+use drax::nbt::{EnsuredCompoundTag, Tag};
+use drax::prelude::{PacketComponent, Result};
+use drax::{err_explain, throw_explain};
+
+use crate::common::bit_storage::BitSetValidationError;
+use crate::common::chunk::anvil::{
+    block_state_from_nbt, block_state_to_nbt, check_data_version, compound_get, compound_int,
+    compound_list, compound_string, list_tag, string_tag, without_keys, RegionCompression,
+    COMPOUND_TAG_ID, DATA_VERSION,
+};
+use crate::common::chunk::edit::BlockEntity;
+use crate::common::chunk::{CachedLevel, Chunk};
+use crate::common::play::BlockPos;
+use crate::common::registry::GLOBAL_REGISTRIES;
+
+const INT_TAG_ID: u8 = 3;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The most blocks a schematic may hold, so that the size in a malformed file cannot allocate
+/// gigabytes.
+pub const MAX_VOLUME: i32 = 64 * 1024 * 1024;
+
+static STRUCTURE_VOID: std::sync::LazyLock<i32> = std::sync::LazyLock::new(|| {
+    GLOBAL_REGISTRIES
+        .blocks
+        .default_state_id("minecraft:structure_void")
+        .unwrap_or(-1)
+});
+
+/// The file formats a [`Schematic`] can be read from and written to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SchematicFormat {
+    /// Sponge schematic version 2 (`.schem`).
+    SpongeV2,
+    /// Sponge schematic version 3 (`.schem`).
+    SpongeV3,
+    /// The vanilla structure template format used by structure blocks (`.nbt`).
+    Structure,
+}
+
+impl SchematicFormat {
+    /// Detects the format of a decoded schematic tag; Sponge version 1 is read as version 2.
+    pub fn detect(tag: &Tag) -> Option<Self> {
+        if let Some(inner @ Tag::CompoundTag(_)) = compound_get(tag, "Schematic") {
+            if compound_int(inner, "Version").unwrap_or(3) >= 3 {
+                return Some(SchematicFormat::SpongeV3);
+            }
+        }
+        if compound_get(tag, "BlockData").is_some() || compound_get(tag, "Palette").is_some() {
+            Some(SchematicFormat::SpongeV2)
+        } else if compound_get(tag, "blocks").is_some() && compound_get(tag, "size").is_some() {
+            Some(SchematicFormat::Structure)
+        } else {
+            None
+        }
+    }
+}
+
+/// A cuboid of block states with their block entities, as stored in schematic files. <br />
+///
+/// Positions are relative to the schematic's minimum corner. The offset is applied when the
+/// schematic is pasted; structure voids keep the blocks they are pasted over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+    width: i32,
+    height: i32,
+    length: i32,
+    pub offset: BlockPos,
+    blocks: Vec<i32>,
+    block_entities: BTreeMap<BlockPos, BlockEntity>,
+}
+
+impl Schematic {
+    /// Creates a schematic of the given size filled with air; fails if the volume exceeds
+    /// [`MAX_VOLUME`] blocks.
+    pub fn new(
+        width: i32,
+        height: i32,
+        length: i32,
+    ) -> std::result::Result<Self, BitSetValidationError> {
+        Self::filled(width, height, length, 0)
+    }
+
+    fn filled(
+        width: i32,
+        height: i32,
+        length: i32,
+        state: i32,
+    ) -> std::result::Result<Self, BitSetValidationError> {
+        let (width, height, length) = (width.max(0), height.max(0), length.max(0));
+        let volume = width as i64 * height as i64 * length as i64;
+        if volume > MAX_VOLUME as i64 {
+            return Err(BitSetValidationError(format!(
+                "Schematic of {}x{}x{} blocks exceeds the limit of {} blocks.",
+                width, height, length, MAX_VOLUME
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            length,
+            offset: BlockPos { x: 0, y: 0, z: 0 },
+            blocks: vec![state; volume as usize],
+            block_entities: BTreeMap::new(),
+        })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn length(&self) -> i32 {
+        self.length
+    }
+
+    fn index(&self, pos: BlockPos) -> Option<usize> {
+        if pos.x < 0
+            || pos.y < 0
+            || pos.z < 0
+            || pos.x >= self.width
+            || pos.y >= self.height
+            || pos.z >= self.length
+        {
+            return None;
+        }
+        Some(((pos.y * self.length + pos.z) * self.width + pos.x) as usize)
+    }
+
+    fn position(&self, index: usize) -> BlockPos {
+        let index = index as i32;
+        BlockPos {
+            x: index % self.width,
+            y: index / (self.width * self.length),
+            z: (index / self.width) % self.length,
+        }
+    }
+
+    pub fn get_block(&self, pos: BlockPos) -> Option<i32> {
+        self.index(pos).map(|index| self.blocks[index])
+    }
+
+    /// Sets the block state at the given position; returns `false` if it is outside of the
+    /// schematic.
+    pub fn set_block(&mut self, pos: BlockPos, state: i32) -> bool {
+        match self.index(pos) {
+            Some(index) => {
+                self.blocks[index] = state;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Every position in the schematic with its block state.
+    pub fn blocks(&self) -> impl Iterator<Item = (BlockPos, i32)> + '_ {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(index, state)| (self.position(index), *state))
+    }
+
+    pub fn block_entity(&self, pos: BlockPos) -> Option<&BlockEntity> {
+        self.block_entities.get(&pos)
+    }
+
+    pub fn block_entities(&self) -> impl Iterator<Item = (&BlockPos, &BlockEntity)> {
+        self.block_entities.iter()
+    }
+
+    pub fn set_block_entity(&mut self, pos: BlockPos, block_entity: BlockEntity) -> bool {
+        if self.index(pos).is_none() {
+            return false;
+        }
+        self.block_entities.insert(pos, block_entity);
+        true
+    }
+
+    pub fn remove_block_entity(&mut self, pos: BlockPos) -> Option<BlockEntity> {
+        self.block_entities.remove(&pos)
+    }
+
+    /// Reads a schematic from a tag in any of the supported formats.
+    pub fn from_nbt(tag: &Tag) -> Result<Self> {
+        match SchematicFormat::detect(tag) {
+            Some(SchematicFormat::SpongeV2) => Self::from_sponge(tag, 2),
+            Some(SchematicFormat::SpongeV3) => match compound_get(tag, "Schematic") {
+                Some(inner) => Self::from_sponge(inner, 3),
+                None => throw_explain!("Sponge schematic is missing its root compound."),
+            },
+            Some(SchematicFormat::Structure) => Self::from_structure(tag),
+            None => throw_explain!("Unknown schematic format."),
+        }
+    }
+
+    pub fn to_nbt(&self, format: SchematicFormat) -> Result<Tag> {
+        match format {
+            SchematicFormat::SpongeV2 => self.to_sponge(2),
+            SchematicFormat::SpongeV3 => Ok(Tag::CompoundTag(vec![(
+                "Schematic".to_string(),
+                self.to_sponge(3)?,
+            )])),
+            SchematicFormat::Structure => self.to_structure(),
+        }
+    }
+
+    /// Reads a schematic file, which may be gzip compressed.
+    pub async fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let data = if bytes.starts_with(&GZIP_MAGIC) {
+            RegionCompression::Gzip.decompress(bytes)?
+        } else {
+            bytes.to_vec()
+        };
+        match EnsuredCompoundTag::<0>::decode(&mut (), &mut Cursor::new(data)).await? {
+            Some(tag) => Self::from_nbt(&tag),
+            None => throw_explain!("Schematic file is empty."),
+        }
+    }
+
+    /// Writes a gzip compressed schematic file, as expected by every supported format.
+    pub async fn to_bytes(&self, format: SchematicFormat) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        EnsuredCompoundTag::<0>::encode(&Some(self.to_nbt(format)?), &mut (), &mut data).await?;
+        RegionCompression::Gzip.compress(&data)
+    }
+
+    fn from_sponge(tag: &Tag, version: i32) -> Result<Self> {
+        check_data_version(tag)?;
+        let mut schematic = Self::new(
+            dimension(tag, "Width")?,
+            dimension(tag, "Height")?,
+            dimension(tag, "Length")?,
+        )
+        .map_err(|err| err_explain!(err.0))?;
+        if let Some([x, y, z]) = compound_ints(tag, "Offset") {
+            schematic.offset = BlockPos {
+                x: *x,
+                y: *y,
+                z: *z,
+            };
+        }
+
+        let blocks = if version >= 3 {
+            match compound_get(tag, "Blocks") {
+                Some(blocks) => blocks,
+                None => return Ok(schematic),
+            }
+        } else {
+            tag
+        };
+        let mut palette = HashMap::new();
+        if let Some(Tag::CompoundTag(entries)) = compound_get(blocks, "Palette") {
+            for (state, index) in entries {
+                if let Tag::TagInt(index) = index {
+                    match GLOBAL_REGISTRIES.blocks.parse_state_id(state) {
+                        Some(state) => palette.insert(*index, state),
+                        None => throw_explain!(format!(
+                            "Unknown block state {} in the schematic palette.",
+                            state
+                        )),
+                    };
+                }
+            }
+        }
+        let data = match compound_get(blocks, if version >= 3 { "Data" } else { "BlockData" }) {
+            Some(Tag::TagByteArray(data)) => read_var_ints(data)?,
+            _ => vec![],
+        };
+        if !data.is_empty() {
+            if data.len() != schematic.blocks.len() {
+                throw_explain!(format!(
+                    "Schematic has {} blocks but a volume of {}.",
+                    data.len(),
+                    schematic.blocks.len()
+                ));
+            }
+            for (block, index) in schematic.blocks.iter_mut().zip(data) {
+                match palette.get(&index) {
+                    Some(state) => *block = *state,
+                    None => {
+                        throw_explain!(format!("Palette index {} is not in the palette.", index))
+                    }
+                }
+            }
+        }
+
+        let block_entities = match compound_list(blocks, "BlockEntities") {
+            [] => compound_list(blocks, "TileEntities"),
+            block_entities => block_entities,
+        };
+        for block_entity in block_entities {
+            let (pos, id) = match (
+                compound_ints(block_entity, "Pos"),
+                compound_string(block_entity, "Id"),
+            ) {
+                (Some([x, y, z]), Some(id)) => (
+                    BlockPos {
+                        x: *x,
+                        y: *y,
+                        z: *z,
+                    },
+                    id,
+                ),
+                _ => continue,
+            };
+            let data = if version >= 3 {
+                compound_get(block_entity, "Data")
+                    .cloned()
+                    .unwrap_or(Tag::CompoundTag(vec![]))
+            } else {
+                without_keys(block_entity, &["Pos", "Id"])
+            };
+            schematic.set_block_entity(pos, BlockEntity::new(id, data));
+        }
+        Ok(schematic)
+    }
+
+    fn to_sponge(&self, version: i32) -> Result<Tag> {
+        if [self.width, self.height, self.length]
+            .into_iter()
+            .any(|dimension| dimension > u16::MAX as i32)
+        {
+            throw_explain!(format!(
+                "Schematic of {}x{}x{} blocks does not fit the dimensions of a Sponge schematic.",
+                self.width, self.height, self.length
+            ))
+        }
+        let mut palette: Vec<i32> = Vec::new();
+        let mut data = Vec::with_capacity(self.blocks.len());
+        for state in &self.blocks {
+            let index = match palette.iter().position(|other| other == state) {
+                Some(index) => index,
+                None => {
+                    palette.push(*state);
+                    palette.len() - 1
+                }
+            };
+            write_var_int(&mut data, index as i32);
+        }
+        let palette_tag = Tag::CompoundTag(
+            palette
+                .iter()
+                .enumerate()
+                .map(|(index, state_id)| {
+                    let state = match GLOBAL_REGISTRIES.blocks.state(*state_id) {
+                        Some(state) => state,
+                        None => throw_explain!(format!("Unknown block state id {}.", state_id)),
+                    };
+                    Ok((state.to_string(), Tag::TagInt(index as i32)))
+                })
+                .collect::<Result<_>>()?,
+        );
+        let block_entities = list_tag(
+            COMPOUND_TAG_ID,
+            self.block_entities
+                .iter()
+                .map(|(pos, block_entity)| {
+                    let mut tag = vec![
+                        (
+                            "Pos".to_string(),
+                            Tag::TagIntArray(vec![pos.x, pos.y, pos.z]),
+                        ),
+                        ("Id".to_string(), string_tag(block_entity.id.clone())),
+                    ];
+                    if version >= 3 {
+                        tag.push(("Data".to_string(), block_entity.data.clone()));
+                    } else if let Tag::CompoundTag(entries) = &block_entity.data {
+                        tag.extend(entries.iter().cloned());
+                    }
+                    Tag::CompoundTag(tag)
+                })
+                .collect(),
+        );
+
+        let mut tag = vec![
+            ("Version".to_string(), Tag::TagInt(version)),
+            ("DataVersion".to_string(), Tag::TagInt(DATA_VERSION)),
+            // dimensions are unsigned shorts stored in signed tags
+            ("Width".to_string(), Tag::TagShort(self.width as i16)),
+            ("Height".to_string(), Tag::TagShort(self.height as i16)),
+            ("Length".to_string(), Tag::TagShort(self.length as i16)),
+            (
+                "Offset".to_string(),
+                Tag::TagIntArray(vec![self.offset.x, self.offset.y, self.offset.z]),
+            ),
+        ];
+        if version >= 3 {
+            tag.push((
+                "Blocks".to_string(),
+                Tag::CompoundTag(vec![
+                    ("Palette".to_string(), palette_tag),
+                    ("Data".to_string(), Tag::TagByteArray(data)),
+                    ("BlockEntities".to_string(), block_entities),
+                ]),
+            ));
+        } else {
+            tag.extend([
+                ("PaletteMax".to_string(), Tag::TagInt(palette.len() as i32)),
+                ("Palette".to_string(), palette_tag),
+                ("BlockData".to_string(), Tag::TagByteArray(data)),
+                ("BlockEntities".to_string(), block_entities),
+            ]);
+        }
+        Ok(Tag::CompoundTag(tag))
+    }
+
+    fn from_structure(tag: &Tag) -> Result<Self> {
+        check_data_version(tag)?;
+        let size = int_list(compound_list(tag, "size"));
+        let mut schematic = match size.as_slice() {
+            [width, height, length] => Self::filled(*width, *height, *length, *STRUCTURE_VOID)
+                .map_err(|err| err_explain!(err.0))?,
+            _ => throw_explain!("Structure has an invalid size."),
+        };
+        let palette = match compound_list(tag, "palette") {
+            [] => match compound_list(tag, "palettes").first() {
+                Some(Tag::TagList(_, palette)) => palette.as_slice(),
+                _ => &[],
+            },
+            palette => palette,
+        };
+        let palette: Vec<i32> = palette
+            .iter()
+            .map(block_state_from_nbt)
+            .collect::<Result<_>>()?;
+
+        for block in compound_list(tag, "blocks") {
+            let pos = match int_list(compound_list(block, "pos")).as_slice() {
+                [x, y, z] => BlockPos {
+                    x: *x,
+                    y: *y,
+                    z: *z,
+                },
+                _ => throw_explain!("Structure block has an invalid position."),
+            };
+            let state =
+                match compound_int(block, "state").and_then(|state| palette.get(state as usize)) {
+                    Some(state) => *state,
+                    None => throw_explain!("Structure block state is not in the palette."),
+                };
+            if !schematic.set_block(pos, state) {
+                throw_explain!(format!(
+                    "Structure block at {}, {}, {} is out of bounds.",
+                    pos.x, pos.y, pos.z
+                ));
+            }
+            if let Some(nbt) = compound_get(block, "nbt") {
+                if let Some(id) = compound_string(nbt, "id") {
+                    schematic
+                        .set_block_entity(pos, BlockEntity::new(id, without_keys(nbt, &["id"])));
+                }
+            }
+        }
+        Ok(schematic)
+    }
+
+    fn to_structure(&self) -> Result<Tag> {
+        let mut palette: Vec<i32> = Vec::new();
+        let mut blocks = Vec::new();
+        for (pos, state) in self.blocks() {
+            if state == *STRUCTURE_VOID {
+                continue;
+            }
+            let index = match palette.iter().position(|other| *other == state) {
+                Some(index) => index,
+                None => {
+                    palette.push(state);
+                    palette.len() - 1
+                }
+            };
+            let mut block = vec![
+                (
+                    "pos".to_string(),
+                    list_tag(
+                        INT_TAG_ID,
+                        vec![Tag::TagInt(pos.x), Tag::TagInt(pos.y), Tag::TagInt(pos.z)],
+                    ),
+                ),
+                ("state".to_string(), Tag::TagInt(index as i32)),
+            ];
+            if let Some(block_entity) = self.block_entities.get(&pos) {
+                let mut nbt = vec![("id".to_string(), string_tag(block_entity.id.clone()))];
+                if let Tag::CompoundTag(entries) = &block_entity.data {
+                    nbt.extend(entries.iter().cloned());
+                }
+                block.push(("nbt".to_string(), Tag::CompoundTag(nbt)));
+            }
+            blocks.push(Tag::CompoundTag(block));
+        }
+        Ok(Tag::CompoundTag(vec![
+            ("DataVersion".to_string(), Tag::TagInt(DATA_VERSION)),
+            (
+                "size".to_string(),
+                list_tag(
+                    INT_TAG_ID,
+                    vec![
+                        Tag::TagInt(self.width),
+                        Tag::TagInt(self.height),
+                        Tag::TagInt(self.length),
+                    ],
+                ),
+            ),
+            (
+                "palette".to_string(),
+                list_tag(
+                    COMPOUND_TAG_ID,
+                    palette
+                        .into_iter()
+                        .map(block_state_to_nbt)
+                        .collect::<Result<_>>()?,
+                ),
+            ),
+            ("blocks".to_string(), list_tag(COMPOUND_TAG_ID, blocks)),
+            ("entities".to_string(), list_tag(COMPOUND_TAG_ID, vec![])),
+        ]))
+    }
+}
+
+/// Sponge stores dimensions as unsigned shorts.
+fn dimension(tag: &Tag, name: &str) -> Result<i32> {
+    match compound_int(tag, name) {
+        Some(value) => Ok(value & 0xFFFF),
+        None => throw_explain!(format!("Schematic is missing its {}.", name)),
+    }
+}
+
+fn compound_ints<'a>(tag: &'a Tag, name: &str) -> Option<&'a [i32; 3]> {
+    match compound_get(tag, name)? {
+        Tag::TagIntArray(values) => values.as_slice().try_into().ok(),
+        _ => None,
+    }
+}
+
+fn int_list(values: &[Tag]) -> Vec<i32> {
+    values
+        .iter()
+        .filter_map(|value| match value {
+            Tag::TagInt(value) => Some(*value),
+            _ => None,
+        })
+        .collect()
+}
+
+fn read_var_ints(data: &[u8]) -> Result<Vec<i32>> {
+    let mut values = Vec::with_capacity(data.len());
+    let mut value = 0;
+    let mut shift = 0;
+    for byte in data {
+        value |= ((byte & 0x7F) as i32) << shift;
+        if byte & 0x80 == 0 {
+            values.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+            if shift >= 32 {
+                throw_explain!("Schematic block data contains a var int which is too big.");
+            }
+        }
+    }
+    if shift != 0 {
+        throw_explain!("Schematic block data ends within a var int.");
+    }
+    Ok(values)
+}
+
+fn write_var_int(data: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            data.push(value as u8);
+            return;
+        }
+        data.push((value & 0x7F | 0x80) as u8);
+        value >>= 7;
+    }
+}
+
+impl CachedLevel {
+    /// Pastes the schematic with its minimum corner at `at` plus the schematic's offset and
+    /// returns how many blocks changed. Structure voids and blocks outside of the world are
+    /// skipped.
+    pub fn paste(
+        &mut self,
+        schematic: &Schematic,
+        at: BlockPos,
+    ) -> std::result::Result<usize, BitSetValidationError> {
+        let origin = BlockPos {
+            x: at.x + schematic.offset.x,
+            y: at.y + schematic.offset.y,
+            z: at.z + schematic.offset.z,
+        };
+        let world_height = self.world_height;
+        let mut changed = 0;
+        for (pos, state) in schematic.blocks() {
+            let target = BlockPos {
+                x: origin.x + pos.x,
+                y: origin.y + pos.y,
+                z: origin.z + pos.z,
+            };
+            if state == *STRUCTURE_VOID
+                || target.y < world_height.min_y()
+                || target.y >= world_height.max_y()
+            {
+                continue;
+            }
+            if self.set_block(target, state)? != state {
+                changed += 1;
+            }
+        }
+        for (pos, block_entity) in schematic.block_entities() {
+            let target = BlockPos {
+                x: origin.x + pos.x,
+                y: origin.y + pos.y,
+                z: origin.z + pos.z,
+            };
+            if target.y >= world_height.min_y() && target.y < world_height.max_y() {
+                self.set_block_entity(target, block_entity.clone());
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Copies the box between both corners, inclusive, into a schematic.
+    pub fn copy(
+        &self,
+        from: BlockPos,
+        to: BlockPos,
+    ) -> std::result::Result<Schematic, BitSetValidationError> {
+        let min = BlockPos {
+            x: from.x.min(to.x),
+            y: from.y.min(to.y),
+            z: from.z.min(to.z),
+        };
+        let mut schematic = Schematic::new(
+            from.x.abs_diff(to.x) as i32 + 1,
+            from.y.abs_diff(to.y) as i32 + 1,
+            from.z.abs_diff(to.z) as i32 + 1,
+        )?;
+        for index in 0..schematic.blocks.len() {
+            let pos = schematic.position(index);
+            let source = BlockPos {
+                x: min.x + pos.x,
+                y: min.y + pos.y,
+                z: min.z + pos.z,
+            };
+            schematic.blocks[index] = self.get_block(source)?;
+            if let Some(block_entity) = self.block_entity(source) {
+                schematic.block_entities.insert(pos, block_entity.clone());
+            }
+        }
+        Ok(schematic)
+    }
+}
+
+impl Chunk {
+    /// Pastes the part of the schematic overlapping this chunk, like [`CachedLevel::paste`].
+    /// Chunks do not hold block entities, so those are left out.
+    pub fn paste(
+        &mut self,
+        schematic: &Schematic,
+        at: BlockPos,
+    ) -> std::result::Result<usize, BitSetValidationError> {
+        let world_height = self.world_height();
+        let mut changed = 0;
+        for (pos, state) in schematic.blocks() {
+            let target = BlockPos {
+                x: at.x + schematic.offset.x + pos.x,
+                y: at.y + schematic.offset.y + pos.y,
+                z: at.z + schematic.offset.z + pos.z,
+            };
+            if state == *STRUCTURE_VOID
+                || Chunk::section_coord_from(target.x) != self.chunk_x
+                || Chunk::section_coord_from(target.z) != self.chunk_z
+                || target.y < world_height.min_y()
+                || target.y >= world_height.max_y()
+            {
+                continue;
+            }
+            if self.get_block_id(target.x, target.y, target.z)? != state {
+                self.set_block_id(target.x, target.y, target.z, state)?;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Copies the whole chunk into a schematic whose offset is the chunk's minimum corner.
+    pub fn to_schematic(&self) -> std::result::Result<Schematic, BitSetValidationError> {
+        let world_height = self.world_height();
+        let mut schematic = Schematic::new(16, world_height.height(), 16)?;
+        schematic.offset = BlockPos {
+            x: Chunk::position_coord_from(self.chunk_x),
+            y: world_height.min_y(),
+            z: Chunk::position_coord_from(self.chunk_z),
+        };
+        for index in 0..schematic.blocks.len() {
+            let pos = schematic.position(index);
+            schematic.blocks[index] =
+                self.get_block_id(pos.x, world_height.min_y() + pos.y, pos.z)?;
+        }
+        Ok(schematic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drax::nbt::Tag;
+
+    use crate::common::chunk::anvil::DATA_VERSION;
+    use crate::common::chunk::edit::BlockEntity;
+    use crate::common::chunk::schematic::{Schematic, SchematicFormat};
+    use crate::common::chunk::CachedLevel;
+    use crate::common::play::BlockPos;
+    use crate::common::registry::GLOBAL_REGISTRIES;
+
+    #[tokio::test]
+    pub async fn schematic_round_trip() -> drax::prelude::Result<()> {
+        let blocks = &GLOBAL_REGISTRIES.blocks;
+        let stone = blocks.default_state_id("stone").unwrap();
+        let stairs = blocks
+            .parse_state_id("oak_stairs[facing=east,half=top]")
+            .unwrap();
+        let chest = blocks.default_state_id("chest").unwrap();
+        let chest_pos = BlockPos { x: 2, y: 1, z: 0 };
+        let chest_entity = BlockEntity::new(
+            "chest",
+            Tag::CompoundTag(vec![(
+                "CustomName".to_string(),
+                Tag::TagString("{\"text\":\"Loot\"}".to_string()),
+            )]),
+        );
+
+        let mut level = CachedLevel::default();
+        level
+            .set_block(BlockPos { x: 0, y: 0, z: 0 }, stone)
+            .unwrap();
+        level
+            .set_block(BlockPos { x: 1, y: 0, z: 1 }, stairs)
+            .unwrap();
+        level.set_block(chest_pos, chest).unwrap();
+        level.set_block_entity(chest_pos, chest_entity.clone());
+        let schematic = level
+            .copy(BlockPos { x: 0, y: 0, z: 0 }, BlockPos { x: 2, y: 1, z: 1 })
+            .unwrap();
+        assert_eq!(
+            (schematic.width(), schematic.height(), schematic.length()),
+            (3, 2, 2)
+        );
+        assert_eq!(schematic.block_entity(chest_pos), Some(&chest_entity));
+
+        for format in [
+            SchematicFormat::SpongeV2,
+            SchematicFormat::SpongeV3,
+            SchematicFormat::Structure,
+        ] {
+            let bytes = schematic.to_bytes(format).await?;
+            let read = Schematic::from_bytes(&bytes).await?;
+            assert_eq!(read, schematic);
+
+            let mut pasted = CachedLevel::default();
+            let at = BlockPos {
+                x: 30,
+                y: 64,
+                z: -5,
+            };
+            assert_eq!(pasted.paste(&read, at).unwrap(), 3);
+            assert_eq!(
+                pasted
+                    .get_block(BlockPos {
+                        x: 31,
+                        y: 64,
+                        z: -4
+                    })
+                    .unwrap(),
+                stairs
+            );
+            assert_eq!(
+                pasted.block_entity(BlockPos {
+                    x: 32,
+                    y: 65,
+                    z: -5
+                }),
+                Some(&chest_entity)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn rejects_invalid_sponge_schematics() {
+        let sponge = |size: i32, state: &str| {
+            Tag::CompoundTag(vec![
+                ("Version".to_string(), Tag::TagInt(2)),
+                ("DataVersion".to_string(), Tag::TagInt(DATA_VERSION)),
+                ("Width".to_string(), Tag::TagInt(size)),
+                ("Height".to_string(), Tag::TagInt(size)),
+                ("Length".to_string(), Tag::TagInt(size)),
+                (
+                    "Palette".to_string(),
+                    Tag::CompoundTag(vec![(state.to_string(), Tag::TagInt(0))]),
+                ),
+            ])
+        };
+        assert!(Schematic::from_nbt(&sponge(2, "minecraft:stone")).is_ok());
+        assert!(Schematic::from_nbt(&sponge(65535, "minecraft:stone")).is_err());
+        assert!(Schematic::from_nbt(&sponge(2, "minecraft:not_a_block")).is_err());
+        assert!(Schematic::new(1 << 16, 1 << 16, 1).is_err());
+        assert!(Schematic::new(2000, 2000, 500).is_err());
+
+        let mut newer = sponge(2, "minecraft:stone");
+        if let Tag::CompoundTag(entries) = &mut newer {
+            entries[1].1 = Tag::TagInt(DATA_VERSION + 1);
+        }
+        assert!(Schematic::from_nbt(&newer).is_err());
+    }
+
+    #[test]
+    pub fn rejects_unwritable_schematics() {
+        let wide = Schematic::new(70000, 1, 1).unwrap();
+        assert!(wide.to_nbt(SchematicFormat::SpongeV2).is_err());
+        assert!(wide.to_nbt(SchematicFormat::Structure).is_ok());
+
+        let mut unknown = Schematic::new(1, 1, 1).unwrap();
+        assert!(unknown.set_block(BlockPos { x: 0, y: 0, z: 0 }, i32::MAX));
+        for format in [
+            SchematicFormat::SpongeV2,
+            SchematicFormat::SpongeV3,
+            SchematicFormat::Structure,
+        ] {
+            assert!(unknown.to_nbt(format).is_err());
+        }
+    }
+}
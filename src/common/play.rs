@@ -339,6 +339,7 @@ registry! {
             Uuid {}
         },
 
+        #[derive(Clone)]
         struct ChatBind {
             chat_type: VarInt,
             name: JsonDelegate<Chat>,
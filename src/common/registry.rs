@@ -1,5 +1,16 @@
+use drax::err_explain;
+use drax::prelude::TransportError;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::common::registry::block::BlockRegistry;
+
+pub mod block;
+mod reports;
 
 #[macro_export]
 macro_rules! lock_static {
@@ -11,14 +22,97 @@ macro_rules! lock_static {
 
 lock_static!(GLOBAL_REGISTRIES -> GlobalRegistry => create);
 
+static INSTALLED_REGISTRIES: Mutex<Option<GlobalRegistry>> = Mutex::new(None);
+static GLOBAL_REGISTRIES_CREATED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug)]
+pub enum RegistryError {
+    Malformed(serde_json::Error),
+    MissingRegistry(RegistryKey),
+    Inconsistent(String),
+    Io(String, std::io::Error),
+    AlreadyInitialized,
+}
+
+impl std::error::Error for RegistryError {}
+
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::Malformed(err) => write!(f, "Malformed registry data. {}", err),
+            RegistryError::MissingRegistry(key) => write!(f, "Missing registry {:?}.", key),
+            RegistryError::Inconsistent(reason) => {
+                write!(f, "Inconsistent registry data. {}", reason)
+            }
+            RegistryError::Io(path, err) => write!(f, "Failed to read {}. {}", path, err),
+            RegistryError::AlreadyInitialized => {
+                write!(f, "Global registries were already initialized.")
+            }
+        }
+    }
+}
+
+impl From<serde_json::Error> for RegistryError {
+    fn from(value: serde_json::Error) -> Self {
+        RegistryError::Malformed(value)
+    }
+}
+
+impl From<RegistryError> for TransportError {
+    fn from(value: RegistryError) -> Self {
+        err_explain!(value.to_string())
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
 pub enum RegistryKey {
     Blocks,
     BlockStates,
     Items,
+    EntityTypes,
+    Biomes,
+    ParticleTypes,
+    /// Not bundled; only populated when loaded from a data generator report.
+    SoundEvents,
+    Menus,
+    MobEffects,
+    Enchantments,
+    BlockEntityTypes,
 }
 
 impl RegistryKey {
+    pub const VALUES: [RegistryKey; 11] = [
+        RegistryKey::Blocks,
+        RegistryKey::BlockStates,
+        RegistryKey::Items,
+        RegistryKey::EntityTypes,
+        RegistryKey::Biomes,
+        RegistryKey::ParticleTypes,
+        RegistryKey::SoundEvents,
+        RegistryKey::Menus,
+        RegistryKey::MobEffects,
+        RegistryKey::Enchantments,
+        RegistryKey::BlockEntityTypes,
+    ];
+
+    /// The name of the registry in the vanilla `registries.json` report; block states are
+    /// derived from `blocks.json` instead.
+    pub const fn report_name(self) -> Option<&'static str> {
+        match self {
+            RegistryKey::Blocks => Some("minecraft:block"),
+            RegistryKey::BlockStates => None,
+            RegistryKey::Items => Some("minecraft:item"),
+            RegistryKey::EntityTypes => Some("minecraft:entity_type"),
+            RegistryKey::Biomes => Some("minecraft:worldgen/biome"),
+            RegistryKey::ParticleTypes => Some("minecraft:particle_type"),
+            RegistryKey::SoundEvents => Some("minecraft:sound_event"),
+            RegistryKey::Menus => Some("minecraft:menu"),
+            RegistryKey::MobEffects => Some("minecraft:mob_effect"),
+            RegistryKey::Enchantments => Some("minecraft:enchantment"),
+            RegistryKey::BlockEntityTypes => Some("minecraft:block_entity_type"),
+        }
+    }
+
     pub fn global<I: InheritKeyType>(self, key: I) -> Option<I::OtherIdentifier> {
         GLOBAL_REGISTRIES.get(self, key)
     }
@@ -26,23 +120,96 @@ impl RegistryKey {
 
 pub struct GlobalRegistry {
     pub registries: HashMap<RegistryKey, Registry>,
+    pub blocks: BlockRegistry,
 }
 
 impl GlobalRegistry {
+    /// Creates the registries backing [`GLOBAL_REGISTRIES`]; these are the ones passed to
+    /// [`GlobalRegistry::install`] or otherwise the bundled ones. <br />
+    ///
+    /// Panics if the bundled registries fail to load, as nothing can be looked up without them.
     pub fn create() -> GlobalRegistry {
+        let mut installed = INSTALLED_REGISTRIES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        GLOBAL_REGISTRIES_CREATED.store(true, Ordering::SeqCst);
+        match installed.take() {
+            Some(registries) => registries,
+            None => Self::bundled()
+                .unwrap_or_else(|err| panic!("Failed to load the bundled registries. {}", err)),
+        }
+    }
+
+    /// Makes these registries the ones backing [`GLOBAL_REGISTRIES`]. <br />
+    ///
+    /// This has to happen at startup, before the global registries are first accessed.
+    pub fn install(self) -> Result<(), RegistryError> {
+        let mut installed = INSTALLED_REGISTRIES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if GLOBAL_REGISTRIES_CREATED.load(Ordering::SeqCst) {
+            return Err(RegistryError::AlreadyInitialized);
+        }
+        *installed = Some(self);
+        Ok(())
+    }
+
+    /// Loads the registries from the output of the vanilla data generator's `--reports`,
+    /// reading `blocks.json` and `registries.json` from the given directory. <br />
+    ///
+    /// Registries missing from the report, such as biomes which are data driven, keep their
+    /// bundled contents.
+    pub fn from_reports_dir<P: AsRef<Path>>(path: P) -> Result<GlobalRegistry, RegistryError> {
+        let path = path.as_ref();
+        let mut global = Self::bundled()?;
+        global.registries.extend(reports::read_registries(path)?);
+        let (block_states, blocks) = reports::read_blocks(
+            path,
+            Self::registry_in(&global.registries, RegistryKey::Blocks)?,
+        )?;
+        global
+            .registries
+            .insert(RegistryKey::BlockStates, block_states);
+        global.blocks = blocks;
+        Ok(global)
+    }
+
+    pub fn bundled() -> Result<GlobalRegistry, RegistryError> {
         let mut registries = HashMap::new();
         macro_rules! inherit_registry {
             ($ident:ident -> $from:literal) => {
                 registries.insert(
                     RegistryKey::$ident,
-                    Registry::from_json_bytes(include_bytes!($from)),
+                    Registry::from_json_bytes(include_bytes!($from))?,
                 );
             };
         }
         inherit_registry!(Blocks -> "./registry/blocks.json");
         inherit_registry!(BlockStates -> "./registry/block_states.json");
         inherit_registry!(Items -> "./registry/items.json");
-        GlobalRegistry { registries }
+        inherit_registry!(EntityTypes -> "./registry/entity_types.json");
+        inherit_registry!(Biomes -> "./registry/biomes.json");
+        inherit_registry!(ParticleTypes -> "./registry/particle_types.json");
+        inherit_registry!(Menus -> "./registry/menus.json");
+        inherit_registry!(MobEffects -> "./registry/mob_effects.json");
+        inherit_registry!(Enchantments -> "./registry/enchantments.json");
+        inherit_registry!(BlockEntityTypes -> "./registry/block_entity_types.json");
+        registries.insert(RegistryKey::SoundEvents, Registry::default());
+        let blocks = BlockRegistry::from_registries(
+            Self::registry_in(&registries, RegistryKey::Blocks)?,
+            Self::registry_in(&registries, RegistryKey::BlockStates)?,
+            include_bytes!("./registry/block_properties.json"),
+        )?;
+        Ok(GlobalRegistry { registries, blocks })
+    }
+
+    fn registry_in(
+        registries: &HashMap<RegistryKey, Registry>,
+        key: RegistryKey,
+    ) -> Result<&Registry, RegistryError> {
+        registries
+            .get(&key)
+            .ok_or(RegistryError::MissingRegistry(key))
     }
 
     pub fn get_id(&self, registry: RegistryKey, key: &str) -> Option<i32> {
@@ -74,17 +241,12 @@ pub struct Registry {
     to_key: HashMap<i32, String>,
 }
 
-pub enum KeyType {
-    String(String),
-    Int(i32),
-}
-
+/// A key which can be looked up in a [`Registry`]; string keys resolve to their numeric id
+/// and numeric ids resolve to their string key.
 pub trait InheritKeyType {
     type OtherIdentifier;
 
-    fn parse_out(&self, other: KeyType) -> Self::OtherIdentifier;
-
-    fn create_key_type(&self) -> KeyType;
+    fn lookup(&self, registry: &Registry) -> Option<Self::OtherIdentifier>;
 }
 
 macro_rules! impl_str_key_type {
@@ -92,15 +254,8 @@ macro_rules! impl_str_key_type {
         impl InheritKeyType for $string_ref {
             type OtherIdentifier = i32;
 
-            fn parse_out(&self, other: KeyType) -> Self::OtherIdentifier {
-                match other {
-                    KeyType::Int(idx) => idx,
-                    _ => panic!("KeyType::parse_out: expected KeyType::Int"),
-                }
-            }
-
-            fn create_key_type(&self) -> KeyType {
-                KeyType::String(self.to_string())
+            fn lookup(&self, registry: &Registry) -> Option<Self::OtherIdentifier> {
+                registry.get_id(self)
             }
         }
     };
@@ -112,21 +267,8 @@ impl_str_key_type!(&str);
 impl InheritKeyType for i32 {
     type OtherIdentifier = String;
 
-    fn parse_out(&self, other: KeyType) -> Self::OtherIdentifier {
-        match other {
-            KeyType::String(key) => key,
-            _ => panic!("KeyType::parse_out: expected KeyType::String"),
-        }
-    }
-
-    fn create_key_type(&self) -> KeyType {
-        KeyType::Int(*self)
-    }
-}
-
-impl From<i32> for KeyType {
-    fn from(value: i32) -> Self {
-        Self::Int(value)
+    fn lookup(&self, registry: &Registry) -> Option<Self::OtherIdentifier> {
+        registry.get_key(*self)
     }
 }
 
@@ -145,22 +287,85 @@ impl Registry {
     }
 
     pub fn get<I: InheritKeyType>(&self, key_type: I) -> Option<I::OtherIdentifier> {
-        match key_type.create_key_type() {
-            KeyType::String(key) => self
-                .get_id(&key)
-                .map(|idx| key_type.parse_out(KeyType::Int(idx))),
-            KeyType::Int(idx) => self
-                .get_key(idx)
-                .map(|key| key_type.parse_out(KeyType::String(key))),
-        }
+        key_type.lookup(self)
     }
 
-    pub fn from_json_bytes(slice: &[u8]) -> Registry {
+    pub fn from_json_bytes(slice: &[u8]) -> Result<Registry, RegistryError> {
         let mut reg = Registry::default();
-        let items: Vec<RegistryItem> = serde_json::from_slice(slice).unwrap();
+        let items: Vec<RegistryItem> = serde_json::from_slice(slice)?;
         for item in items {
-            reg.register(format!("{}", item.key), item.idx);
+            reg.register(item.key, item.idx);
         }
-        reg
+        Ok(reg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::registry::{GlobalRegistry, Registry, RegistryError, RegistryKey};
+
+    #[test]
+    pub fn bundled_registries_load() {
+        let registries = GlobalRegistry::bundled().unwrap();
+        assert_eq!(registries.get(RegistryKey::Items, "minecraft:air"), Some(0));
+        assert_eq!(
+            registries.get(RegistryKey::EntityTypes, 117),
+            Some("minecraft:player".to_string())
+        );
+        assert!(matches!(
+            Registry::from_json_bytes(b"[{\"key\": 1}]"),
+            Err(RegistryError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    pub fn registries_from_reports() {
+        let dir = std::env::temp_dir().join(format!("mcprotocol-reports-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("registries.json"),
+            r#"{
+                "minecraft:block": {"entries": {
+                    "minecraft:air": {"protocol_id": 0},
+                    "minecraft:grass_block": {"protocol_id": 1}
+                }},
+                "minecraft:sound_event": {"entries": {
+                    "minecraft:ambient.cave": {"protocol_id": 0}
+                }}
+            }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("blocks.json"),
+            r#"{
+                "minecraft:air": {"states": [{"id": 0, "default": true}]},
+                "minecraft:grass_block": {
+                    "properties": {"snowy": ["true", "false"]},
+                    "states": [
+                        {"id": 1, "properties": {"snowy": "true"}},
+                        {"id": 2, "properties": {"snowy": "false"}, "default": true}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let registries = GlobalRegistry::from_reports_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            registries.get(RegistryKey::SoundEvents, "minecraft:ambient.cave"),
+            Some(0)
+        );
+        assert_eq!(
+            registries.get(RegistryKey::BlockStates, "minecraft:grass_block:1"),
+            Some(2)
+        );
+        assert_eq!(registries.blocks.parse_state_id("grass_block"), Some(2));
+        assert_eq!(
+            registries.get(RegistryKey::Biomes, "minecraft:plains"),
+            GlobalRegistry::bundled()
+                .unwrap()
+                .get(RegistryKey::Biomes, "minecraft:plains")
+        );
     }
 }
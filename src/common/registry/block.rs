@@ -0,0 +1,398 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::common::registry::{Registry, RegistryError};
+
+pub(crate) fn namespaced(key: &str) -> String {
+    if key.contains(':') {
+        key.to_string()
+    } else {
+        format!("minecraft:{}", key)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct BlockProperty {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BlockPropertiesItem {
+    key: String,
+    properties: Vec<BlockProperty>,
+    default: i32,
+}
+
+/// A block along with the values of its properties, written as
+/// `minecraft:oak_stairs[facing=east,half=top]`. <br />
+///
+/// Properties which are left out take the value of the block's default state when resolved
+/// through [`BlockRegistry::state_id`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct BlockState {
+    pub block: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl BlockState {
+    pub fn new<S: AsRef<str>>(block: S) -> Self {
+        Self {
+            block: namespaced(block.as_ref()),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    pub fn with<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn set<N: Into<String>, V: Into<String>>(&mut self, name: N, value: V) {
+        self.properties.insert(name.into(), value.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.properties.get(name).map(|value| value.as_str())
+    }
+
+    /// Parses the `block[name=value,...]` notation; the namespace defaults to `minecraft`.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let (block, properties) = match input.find('[') {
+            None => (input, None),
+            Some(start) => (&input[..start], Some(input[start + 1..].strip_suffix(']')?)),
+        };
+        if block.is_empty() {
+            return None;
+        }
+        let mut state = Self::new(block);
+        for property in properties
+            .into_iter()
+            .flat_map(|properties| properties.split(','))
+            .map(str::trim)
+            .filter(|property| !property.is_empty())
+        {
+            let (name, value) = property.split_once('=')?;
+            state.set(name.trim(), value.trim());
+        }
+        Some(state)
+    }
+}
+
+impl Display for BlockState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.block)?;
+        if self.properties.is_empty() {
+            return Ok(());
+        }
+        write!(f, "[")?;
+        for (idx, (name, value)) in self.properties.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}={}", name, value)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// The states of a single block. <br />
+///
+/// States are laid out as the cartesian product of the block's properties, sorted by name,
+/// with the last property changing the fastest; this matches the vanilla state id order.
+#[derive(Debug, Clone)]
+pub struct BlockDefinition {
+    key: String,
+    id: i32,
+    min_state_id: i32,
+    default_offset: i32,
+    properties: Vec<BlockProperty>,
+}
+
+impl BlockDefinition {
+    pub fn new(
+        key: String,
+        id: i32,
+        min_state_id: i32,
+        default_offset: i32,
+        properties: Vec<BlockProperty>,
+    ) -> Self {
+        Self {
+            key,
+            id,
+            min_state_id,
+            default_offset,
+            properties,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The id of the block itself in the `Blocks` registry.
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn min_state_id(&self) -> i32 {
+        self.min_state_id
+    }
+
+    pub fn max_state_id(&self) -> i32 {
+        self.min_state_id + self.state_count() - 1
+    }
+
+    pub fn default_state_id(&self) -> i32 {
+        self.min_state_id + self.default_offset
+    }
+
+    pub fn default_state(&self) -> BlockState {
+        self.state_at(self.default_offset)
+    }
+
+    pub fn contains_state(&self, state_id: i32) -> bool {
+        (self.min_state_id..=self.max_state_id()).contains(&state_id)
+    }
+
+    pub fn properties(&self) -> &[BlockProperty] {
+        &self.properties
+    }
+
+    pub fn state_count(&self) -> i32 {
+        self.properties
+            .iter()
+            .map(|property| property.values.len() as i32)
+            .product()
+    }
+
+    fn state_at(&self, offset: i32) -> BlockState {
+        let mut state = BlockState::new(&self.key);
+        let mut remaining = offset;
+        for property in self.properties.iter().rev() {
+            let len = property.values.len() as i32;
+            state.set(
+                property.name.clone(),
+                property.values[(remaining % len) as usize].clone(),
+            );
+            remaining /= len;
+        }
+        state
+    }
+
+    fn offset_of(&self, properties: &BTreeMap<String, String>) -> Option<i32> {
+        if properties.keys().any(|name| {
+            !self
+                .properties
+                .iter()
+                .any(|property| &property.name == name)
+        }) {
+            return None;
+        }
+        let defaults = self.default_state();
+        let mut offset = 0;
+        for property in &self.properties {
+            let value = properties
+                .get(&property.name)
+                .or_else(|| defaults.properties.get(&property.name))?;
+            let idx = property.values.iter().position(|other| other == value)?;
+            offset = offset * property.values.len() as i32 + idx as i32;
+        }
+        Some(offset)
+    }
+}
+
+/// Links blocks to their states and converts between state ids and [`BlockState`]s.
+#[derive(Default)]
+pub struct BlockRegistry {
+    blocks: Vec<BlockDefinition>,
+    by_key: HashMap<String, usize>,
+}
+
+impl BlockRegistry {
+    /// Creates the registry from the `Blocks` and `BlockStates` registries and the bundled
+    /// property definitions; blocks without a definition have a single state.
+    pub fn from_registries(
+        blocks: &Registry,
+        block_states: &Registry,
+        properties_slice: &[u8],
+    ) -> Result<BlockRegistry, RegistryError> {
+        let items: Vec<BlockPropertiesItem> = serde_json::from_slice(properties_slice)?;
+        let mut properties: HashMap<String, BlockPropertiesItem> = items
+            .into_iter()
+            .map(|item| (item.key.clone(), item))
+            .collect();
+
+        let mut definitions = vec![];
+        let mut idx = 0;
+        while let Some(key) = blocks.get_key(idx) {
+            let id = idx;
+            idx += 1;
+            let min_state_id = match block_states.get_id(&key) {
+                Some(min_state_id) => min_state_id,
+                None => continue,
+            };
+            let (block_properties, default_offset) = match properties.remove(&key) {
+                Some(item) => (item.properties, item.default),
+                None => (vec![], 0),
+            };
+            definitions.push(BlockDefinition {
+                key,
+                id,
+                min_state_id,
+                default_offset,
+                properties: block_properties,
+            });
+        }
+        Self::from_definitions(definitions)
+    }
+
+    /// Creates the registry from individual block definitions, validating that their state
+    /// ranges do not overlap.
+    pub fn from_definitions(
+        mut definitions: Vec<BlockDefinition>,
+    ) -> Result<BlockRegistry, RegistryError> {
+        definitions.sort_by_key(|block| block.min_state_id);
+        let mut registry = BlockRegistry::default();
+        for block in definitions {
+            if let Some(property) = block
+                .properties
+                .iter()
+                .find(|property| property.values.is_empty())
+            {
+                return Err(RegistryError::Inconsistent(format!(
+                    "Property {} of {} has no values.",
+                    property.name, block.key
+                )));
+            }
+            if block.default_offset < 0 || block.default_offset >= block.state_count() {
+                return Err(RegistryError::Inconsistent(format!(
+                    "Default state of {} is out of range.",
+                    block.key
+                )));
+            }
+            if let Some(previous) = registry.blocks.last() {
+                if previous.max_state_id() >= block.min_state_id {
+                    return Err(RegistryError::Inconsistent(format!(
+                        "States of {} overlap with {}.",
+                        block.key, previous.key
+                    )));
+                }
+            }
+            registry
+                .by_key
+                .insert(block.key.clone(), registry.blocks.len());
+            registry.blocks.push(block);
+        }
+        Ok(registry)
+    }
+
+    pub fn block(&self, key: &str) -> Option<&BlockDefinition> {
+        self.blocks.get(*self.by_key.get(&namespaced(key))?)
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &BlockDefinition> {
+        self.blocks.iter()
+    }
+
+    /// Finds the block owning the given state id.
+    pub fn block_for_state(&self, state_id: i32) -> Option<&BlockDefinition> {
+        let idx = match self
+            .blocks
+            .binary_search_by_key(&state_id, |block| block.min_state_id)
+        {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+        let block = &self.blocks[idx];
+        if block.contains_state(state_id) {
+            Some(block)
+        } else {
+            None
+        }
+    }
+
+    pub fn default_state_id(&self, key: &str) -> Option<i32> {
+        self.block(key).map(BlockDefinition::default_state_id)
+    }
+
+    /// Returns the first and last state id of the block, inclusive.
+    pub fn state_range(&self, key: &str) -> Option<(i32, i32)> {
+        self.block(key)
+            .map(|block| (block.min_state_id, block.max_state_id()))
+    }
+
+    /// Returns the default state id of the block owning the given state.
+    pub fn default_state_for(&self, state_id: i32) -> Option<i32> {
+        self.block_for_state(state_id)
+            .map(BlockDefinition::default_state_id)
+    }
+
+    /// Resolves a state id into the block and all of its property values.
+    pub fn state(&self, state_id: i32) -> Option<BlockState> {
+        let block = self.block_for_state(state_id)?;
+        Some(block.state_at(state_id - block.min_state_id))
+    }
+
+    /// Resolves a block state into its id; unknown properties or values yield `None`.
+    pub fn state_id(&self, state: &BlockState) -> Option<i32> {
+        let block = self.block(&state.block)?;
+        Some(block.min_state_id + block.offset_of(&state.properties)?)
+    }
+
+    /// Parses and resolves a state such as `minecraft:oak_stairs[facing=east,half=top]`.
+    pub fn parse_state_id(&self, input: &str) -> Option<i32> {
+        self.state_id(&BlockState::parse(input)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::registry::block::BlockState;
+    use crate::common::registry::GLOBAL_REGISTRIES;
+
+    #[test]
+    pub fn block_state_round_trip() {
+        let blocks = &GLOBAL_REGISTRIES.blocks;
+        assert_eq!(blocks.parse_state_id("grass_block"), Some(9));
+        assert_eq!(blocks.state(8).unwrap().get("snowy"), Some("true"));
+
+        let stairs = BlockState::parse("minecraft:oak_stairs[facing=east,half=top]").unwrap();
+        let id = blocks.state_id(&stairs).unwrap();
+        let resolved = blocks.state(id).unwrap();
+        assert_eq!(
+            resolved.to_string(),
+            "minecraft:oak_stairs[facing=east,half=top,shape=straight,waterlogged=false]"
+        );
+        assert_eq!(blocks.state_id(&resolved), Some(id));
+        assert_eq!(blocks.state_id(&stairs.with("facing", "up")), None);
+    }
+
+    #[test]
+    pub fn block_state_ranges() {
+        let blocks = &GLOBAL_REGISTRIES.blocks;
+        assert_eq!(blocks.default_state_id("minecraft:stone"), Some(1));
+        assert_eq!(blocks.state_range("grass_block"), Some((8, 9)));
+        assert_eq!(blocks.default_state_for(8), Some(9));
+
+        let stairs = blocks.block("oak_stairs").unwrap();
+        assert_eq!(stairs.max_state_id() - stairs.min_state_id() + 1, 80);
+        for state_id in [stairs.min_state_id(), stairs.max_state_id()] {
+            assert_eq!(
+                blocks.block_for_state(state_id).unwrap().key(),
+                "minecraft:oak_stairs"
+            );
+        }
+        assert_eq!(
+            blocks
+                .block_for_state(stairs.max_state_id() + 1)
+                .unwrap()
+                .id(),
+            stairs.id() + 1
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde_derive::Deserialize;
+
+use crate::common::registry::block::{BlockDefinition, BlockProperty, BlockRegistry};
+use crate::common::registry::{Registry, RegistryError, RegistryKey};
+
+#[derive(Deserialize)]
+struct ReportRegistry {
+    entries: HashMap<String, ReportEntry>,
+}
+
+#[derive(Deserialize)]
+struct ReportEntry {
+    protocol_id: i32,
+}
+
+#[derive(Deserialize)]
+struct ReportBlock {
+    // a sorted map matches the vanilla property order, which is sorted by name
+    #[serde(default)]
+    properties: BTreeMap<String, Vec<String>>,
+    states: Vec<ReportBlockState>,
+}
+
+#[derive(Deserialize)]
+struct ReportBlockState {
+    id: i32,
+    #[serde(default)]
+    default: bool,
+}
+
+fn read_report(path: &Path) -> Result<Vec<u8>, RegistryError> {
+    std::fs::read(path).map_err(|err| RegistryError::Io(path.display().to_string(), err))
+}
+
+/// Reads every registry present in `registries.json`, keyed by their registry key.
+pub(crate) fn read_registries(
+    reports_dir: &Path,
+) -> Result<HashMap<RegistryKey, Registry>, RegistryError> {
+    let report: HashMap<String, ReportRegistry> =
+        serde_json::from_slice(&read_report(&reports_dir.join("registries.json"))?)?;
+    let mut registries = HashMap::new();
+    for key in RegistryKey::VALUES {
+        let report_registry = match key.report_name().and_then(|name| report.get(name)) {
+            Some(report_registry) => report_registry,
+            None => continue,
+        };
+        let mut registry = Registry::default();
+        for (entry_key, entry) in &report_registry.entries {
+            registry.register(entry_key.clone(), entry.protocol_id);
+        }
+        registries.insert(key, registry);
+    }
+    Ok(registries)
+}
+
+/// Reads `blocks.json` into the flat `BlockStates` registry and the [`BlockRegistry`].
+pub(crate) fn read_blocks(
+    reports_dir: &Path,
+    blocks: &Registry,
+) -> Result<(Registry, BlockRegistry), RegistryError> {
+    let report: HashMap<String, ReportBlock> =
+        serde_json::from_slice(&read_report(&reports_dir.join("blocks.json"))?)?;
+    let mut block_states = Registry::default();
+    let mut definitions = Vec::with_capacity(report.len());
+    for (key, block) in report {
+        let id = blocks.get_id(&key).ok_or_else(|| {
+            RegistryError::Inconsistent(format!("Block {} is not in the block registry.", key))
+        })?;
+        let min_state_id = block
+            .states
+            .iter()
+            .map(|state| state.id)
+            .min()
+            .ok_or_else(|| RegistryError::Inconsistent(format!("Block {} has no states.", key)))?;
+        let default_state_id = block
+            .states
+            .iter()
+            .find(|state| state.default)
+            .map(|state| state.id)
+            .unwrap_or(min_state_id);
+
+        block_states.register(key.clone(), min_state_id);
+        for offset in 1..block.states.len() as i32 {
+            block_states.register(format!("{}:{}", key, offset), min_state_id + offset);
+        }
+
+        let definition = BlockDefinition::new(
+            key,
+            id,
+            min_state_id,
+            default_state_id - min_state_id,
+            block
+                .properties
+                .into_iter()
+                .map(|(name, values)| BlockProperty { name, values })
+                .collect(),
+        );
+        if definition.state_count() != block.states.len() as i32 {
+            return Err(RegistryError::Inconsistent(format!(
+                "Block {} lists {} states but its properties describe {}.",
+                definition.key(),
+                block.states.len(),
+                definition.state_count()
+            )));
+        }
+        definitions.push(definition);
+    }
+    Ok((block_states, BlockRegistry::from_definitions(definitions)?))
+}
@@ -0,0 +1,490 @@
+use std::marker::PhantomData;
+
+use drax::prelude::{AsyncRead, AsyncWrite, Result};
+use drax::throw_explain;
+
+use crate::clientbound::login::ClientboundLoginRegistry;
+use crate::clientbound::play::ClientboundPlayRegistry;
+use crate::clientbound::status::ClientboundStatusRegistry;
+#[cfg(feature = "crypto")]
+use crate::crypto::EncryptedStream;
+use crate::framing::{FramedReader, FramedWriter, PacketFramer};
+use crate::handshaking::{ConnectionProtocol, HandshakingRegistry};
+use crate::serverbound::login::ServerBoundLoginRegsitry;
+use crate::serverbound::play::ServerboundPlayRegistry;
+use crate::serverbound::status::ServerboundStatusRegistry;
+
+/// The phase a connection is currently in; determines which registry packets belong to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ConnectionState {
+    Handshaking,
+    Status,
+    Login,
+    Play,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Transition {
+    None,
+    State(ConnectionState),
+    Compression(i32),
+}
+
+/// Any packet sent from the client to the server, tagged with the phase it belongs to.
+#[derive(Debug)]
+pub enum ServerboundPacket {
+    Handshaking(HandshakingRegistry),
+    Status(ServerboundStatusRegistry),
+    Login(ServerBoundLoginRegsitry),
+    Play(ServerboundPlayRegistry),
+}
+
+impl ServerboundPacket {
+    pub fn state(&self) -> ConnectionState {
+        match self {
+            ServerboundPacket::Handshaking(_) => ConnectionState::Handshaking,
+            ServerboundPacket::Status(_) => ConnectionState::Status,
+            ServerboundPacket::Login(_) => ConnectionState::Login,
+            ServerboundPacket::Play(_) => ConnectionState::Play,
+        }
+    }
+
+    fn transition(&self) -> Result<Transition> {
+        match self {
+            ServerboundPacket::Handshaking(HandshakingRegistry::ClientIntention {
+                intention,
+                ..
+            }) => match intention {
+                ConnectionProtocol::Status {} => Ok(Transition::State(ConnectionState::Status)),
+                ConnectionProtocol::Login {} => Ok(Transition::State(ConnectionState::Login)),
+                ConnectionProtocol::Play {} => {
+                    throw_explain!("Clients cannot intend to skip directly to the play phase.")
+                }
+            },
+            _ => Ok(Transition::None),
+        }
+    }
+}
+
+/// Any packet sent from the server to the client, tagged with the phase it belongs to.
+#[derive(Debug)]
+pub enum ClientboundPacket {
+    Status(ClientboundStatusRegistry),
+    Login(ClientboundLoginRegistry),
+    Play(ClientboundPlayRegistry),
+}
+
+impl ClientboundPacket {
+    pub fn state(&self) -> ConnectionState {
+        match self {
+            ClientboundPacket::Status(_) => ConnectionState::Status,
+            ClientboundPacket::Login(_) => ConnectionState::Login,
+            ClientboundPacket::Play(_) => ConnectionState::Play,
+        }
+    }
+
+    fn transition(&self) -> Result<Transition> {
+        match self {
+            ClientboundPacket::Login(ClientboundLoginRegistry::LoginCompression { threshold }) => {
+                Ok(Transition::Compression(*threshold))
+            }
+            ClientboundPacket::Login(ClientboundLoginRegistry::LoginGameProfile { .. }) => {
+                Ok(Transition::State(ConnectionState::Play))
+            }
+            _ => Ok(Transition::None),
+        }
+    }
+}
+
+/// Marks a connection as the server end; it reads [`ServerboundPacket`]s and writes
+/// [`ClientboundPacket`]s.
+#[derive(Debug, Copy, Clone)]
+pub struct ServerSide;
+
+/// Marks a connection as the client end; it reads [`ClientboundPacket`]s and writes
+/// [`ServerboundPacket`]s.
+#[derive(Debug, Copy, Clone)]
+pub struct ClientSide;
+
+/// A framed connection which decodes and encodes with the registry of its current state. <br />
+///
+/// The state advances automatically as packets pass through: a `ClientIntention` moves into
+/// the status or login phase, `LoginCompression` enables compression and `LoginGameProfile`
+/// moves into the play phase. Packets which do not belong to the current phase are rejected.
+pub struct Connection<S, R, W> {
+    state: ConnectionState,
+    reader: FramedReader<R>,
+    writer: FramedWriter<W>,
+    _side: PhantomData<S>,
+}
+
+impl<S, R: AsyncRead + Unpin + Send + Sync, W: AsyncWrite + Unpin + Send + Sync>
+    Connection<S, R, W>
+{
+    pub fn new(read: R, write: W) -> Self {
+        Self::from_framed(
+            ConnectionState::Handshaking,
+            FramedReader::new(read),
+            FramedWriter::new(write),
+        )
+    }
+
+    /// Resumes a connection in an arbitrary state, e.g. after a proxy hands it off.
+    pub fn from_framed(
+        state: ConnectionState,
+        reader: FramedReader<R>,
+        writer: FramedWriter<W>,
+    ) -> Self {
+        Self {
+            state,
+            reader,
+            writer,
+            _side: PhantomData,
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    pub fn framer(&self) -> &PacketFramer {
+        self.reader.framer()
+    }
+
+    pub fn into_framed(self) -> (FramedReader<R>, FramedWriter<W>) {
+        (self.reader, self.writer)
+    }
+
+    /// Wraps both directions in AES/CFB8 encryption, keeping the state and compression. <br />
+    ///
+    /// Servers enable encryption once they read the `Key` packet, clients once they wrote it.
+    #[cfg(feature = "crypto")]
+    pub fn enable_encryption(
+        self,
+        shared_secret: &[u8],
+    ) -> Result<Connection<S, EncryptedStream<R>, EncryptedStream<W>>> {
+        let reader_framer = *self.reader.framer();
+        let writer_framer = *self.writer.framer();
+        Ok(Connection::from_framed(
+            self.state,
+            FramedReader::with_framer(
+                EncryptedStream::new(self.reader.into_inner(), shared_secret)?,
+                reader_framer,
+            ),
+            FramedWriter::with_framer(
+                EncryptedStream::new(self.writer.into_inner(), shared_secret)?,
+                writer_framer,
+            ),
+        ))
+    }
+
+    fn ensure_state(&self, state: ConnectionState) -> Result<()> {
+        if self.state != state {
+            throw_explain!(format!(
+                "Packet for the {:?} phase is not valid in the {:?} phase.",
+                state, self.state
+            ))
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, transition: Transition) {
+        match transition {
+            Transition::None => {}
+            Transition::State(state) => self.state = state,
+            Transition::Compression(threshold) => {
+                self.reader.framer_mut().set_compression_threshold(threshold);
+                self.writer.framer_mut().set_compression_threshold(threshold);
+            }
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin + Send + Sync, W: AsyncWrite + Unpin + Send + Sync>
+    Connection<ServerSide, R, W>
+{
+    pub async fn read_packet(&mut self) -> Result<ServerboundPacket> {
+        let ctx = &mut ();
+        let packet = match self.state {
+            ConnectionState::Handshaking => ServerboundPacket::Handshaking(
+                self.reader.read_packet::<(), HandshakingRegistry>(ctx).await?,
+            ),
+            ConnectionState::Status => ServerboundPacket::Status(
+                self.reader
+                    .read_packet::<(), ServerboundStatusRegistry>(ctx)
+                    .await?,
+            ),
+            ConnectionState::Login => ServerboundPacket::Login(
+                self.reader
+                    .read_packet::<(), ServerBoundLoginRegsitry>(ctx)
+                    .await?,
+            ),
+            ConnectionState::Play => ServerboundPacket::Play(
+                self.reader
+                    .read_packet::<(), ServerboundPlayRegistry>(ctx)
+                    .await?,
+            ),
+        };
+        let transition = packet.transition()?;
+        self.apply(transition);
+        Ok(packet)
+    }
+
+    pub async fn write_packet(&mut self, packet: ClientboundPacket) -> Result<()> {
+        self.ensure_state(packet.state())?;
+        let transition = packet.transition()?;
+        let ctx = &mut ();
+        match &packet {
+            ClientboundPacket::Status(inner) => {
+                self.writer
+                    .write_packet::<(), ClientboundStatusRegistry>(ctx, inner)
+                    .await?
+            }
+            ClientboundPacket::Login(inner) => {
+                self.writer
+                    .write_packet::<(), ClientboundLoginRegistry>(ctx, inner)
+                    .await?
+            }
+            ClientboundPacket::Play(inner) => {
+                self.writer
+                    .write_packet::<(), ClientboundPlayRegistry>(ctx, inner)
+                    .await?
+            }
+        }
+        self.apply(transition);
+        Ok(())
+    }
+}
+
+impl<R: AsyncRead + Unpin + Send + Sync, W: AsyncWrite + Unpin + Send + Sync>
+    Connection<ClientSide, R, W>
+{
+    pub async fn read_packet(&mut self) -> Result<ClientboundPacket> {
+        let ctx = &mut ();
+        let packet = match self.state {
+            ConnectionState::Handshaking => {
+                throw_explain!("Servers do not send packets in the handshaking phase.")
+            }
+            ConnectionState::Status => ClientboundPacket::Status(
+                self.reader
+                    .read_packet::<(), ClientboundStatusRegistry>(ctx)
+                    .await?,
+            ),
+            ConnectionState::Login => ClientboundPacket::Login(
+                self.reader
+                    .read_packet::<(), ClientboundLoginRegistry>(ctx)
+                    .await?,
+            ),
+            ConnectionState::Play => ClientboundPacket::Play(
+                self.reader
+                    .read_packet::<(), ClientboundPlayRegistry>(ctx)
+                    .await?,
+            ),
+        };
+        let transition = packet.transition()?;
+        self.apply(transition);
+        Ok(packet)
+    }
+
+    pub async fn write_packet(&mut self, packet: ServerboundPacket) -> Result<()> {
+        self.ensure_state(packet.state())?;
+        let transition = packet.transition()?;
+        let ctx = &mut ();
+        match &packet {
+            ServerboundPacket::Handshaking(inner) => {
+                self.writer
+                    .write_packet::<(), HandshakingRegistry>(ctx, inner)
+                    .await?
+            }
+            ServerboundPacket::Status(inner) => {
+                self.writer
+                    .write_packet::<(), ServerboundStatusRegistry>(ctx, inner)
+                    .await?
+            }
+            ServerboundPacket::Login(inner) => {
+                self.writer
+                    .write_packet::<(), ServerBoundLoginRegsitry>(ctx, inner)
+                    .await?
+            }
+            ServerboundPacket::Play(inner) => {
+                self.writer
+                    .write_packet::<(), ServerboundPlayRegistry>(ctx, inner)
+                    .await?
+            }
+        }
+        self.apply(transition);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drax::prelude::Uuid;
+    use tokio::io::{DuplexStream, ReadHalf, WriteHalf};
+
+    use crate::clientbound::login::ClientboundLoginRegistry;
+    use crate::clientbound::status::ClientboundStatusRegistry;
+    use crate::common::GameProfile;
+    use crate::connection::{
+        ClientSide, ClientboundPacket, Connection, ConnectionState, ServerSide, ServerboundPacket,
+    };
+    use crate::handshaking::{ConnectionProtocol, HandshakingRegistry};
+    use crate::serverbound::login::ServerBoundLoginRegsitry;
+    use crate::serverbound::play::ServerboundPlayRegistry;
+    use crate::serverbound::status::ServerboundStatusRegistry;
+
+    type Half<S> = Connection<S, ReadHalf<DuplexStream>, WriteHalf<DuplexStream>>;
+
+    fn pipe() -> (Half<ClientSide>, Half<ServerSide>) {
+        let (client, server) = tokio::io::duplex(4096);
+        let (client_read, client_write) = tokio::io::split(client);
+        let (server_read, server_write) = tokio::io::split(server);
+        (
+            Connection::new(client_read, client_write),
+            Connection::new(server_read, server_write),
+        )
+    }
+
+    fn intention(intention: ConnectionProtocol) -> ServerboundPacket {
+        ServerboundPacket::Handshaking(HandshakingRegistry::ClientIntention {
+            protocol_version: 761,
+            host_name: "localhost".to_string(),
+            port: 25565,
+            intention,
+        })
+    }
+
+    fn login_success() -> ClientboundPacket {
+        ClientboundPacket::Login(ClientboundLoginRegistry::LoginGameProfile {
+            game_profile: GameProfile {
+                id: Uuid::nil(),
+                name: "Steve".to_string(),
+                properties: vec![],
+            },
+        })
+    }
+
+    #[tokio::test]
+    pub async fn handshake_to_status() -> drax::prelude::Result<()> {
+        let (mut client, mut server) = pipe();
+        client
+            .write_packet(intention(ConnectionProtocol::Status {}))
+            .await?;
+        assert_eq!(client.state(), ConnectionState::Status);
+        assert!(matches!(
+            server.read_packet().await?,
+            ServerboundPacket::Handshaking(_)
+        ));
+        assert_eq!(server.state(), ConnectionState::Status);
+
+        client
+            .write_packet(ServerboundPacket::Status(ServerboundStatusRegistry::Ping {
+                payload: 42,
+            }))
+            .await?;
+        assert!(matches!(
+            server.read_packet().await?,
+            ServerboundPacket::Status(ServerboundStatusRegistry::Ping { payload: 42 })
+        ));
+        server
+            .write_packet(ClientboundPacket::Status(ClientboundStatusRegistry::Pong {
+                payload: 42,
+            }))
+            .await?;
+        assert!(matches!(
+            client.read_packet().await?,
+            ClientboundPacket::Status(ClientboundStatusRegistry::Pong { payload: 42 })
+        ));
+
+        // login packets are rejected before they reach the wire
+        assert!(server.write_packet(login_success()).await.is_err());
+        assert_eq!(server.state(), ConnectionState::Status);
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn handshake_to_play() -> drax::prelude::Result<()> {
+        let (mut client, mut server) = pipe();
+        assert!(client
+            .write_packet(ServerboundPacket::Play(
+                ServerboundPlayRegistry::KeepAlive { keep_alive_id: 1 }
+            ))
+            .await
+            .is_err());
+
+        client
+            .write_packet(intention(ConnectionProtocol::Login {}))
+            .await?;
+        server.read_packet().await?;
+        assert_eq!(server.state(), ConnectionState::Login);
+
+        client
+            .write_packet(ServerboundPacket::Login(ServerBoundLoginRegsitry::Hello {
+                name: "Steve".to_string(),
+                profile_id: None,
+            }))
+            .await?;
+        assert!(matches!(
+            server.read_packet().await?,
+            ServerboundPacket::Login(ServerBoundLoginRegsitry::Hello { name, .. }) if name == "Steve"
+        ));
+
+        server
+            .write_packet(ClientboundPacket::Login(
+                ClientboundLoginRegistry::LoginCompression { threshold: 64 },
+            ))
+            .await?;
+        client.read_packet().await?;
+        assert_eq!(client.framer().threshold(), Some(64));
+        assert_eq!(server.framer().threshold(), Some(64));
+
+        server.write_packet(login_success()).await?;
+        assert_eq!(server.state(), ConnectionState::Play);
+        assert!(matches!(
+            client.read_packet().await?,
+            ClientboundPacket::Login(ClientboundLoginRegistry::LoginGameProfile { .. })
+        ));
+        assert_eq!(client.state(), ConnectionState::Play);
+
+        client
+            .write_packet(ServerboundPacket::Play(
+                ServerboundPlayRegistry::KeepAlive { keep_alive_id: 7 },
+            ))
+            .await?;
+        assert!(matches!(
+            server.read_packet().await?,
+            ServerboundPacket::Play(ServerboundPlayRegistry::KeepAlive { keep_alive_id: 7 })
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "crypto")]
+    #[tokio::test]
+    pub async fn encryption_in_place() -> drax::prelude::Result<()> {
+        let (mut client, mut server) = pipe();
+        client
+            .write_packet(intention(ConnectionProtocol::Login {}))
+            .await?;
+        server.read_packet().await?;
+        server
+            .write_packet(ClientboundPacket::Login(
+                ClientboundLoginRegistry::LoginCompression { threshold: 64 },
+            ))
+            .await?;
+        client.read_packet().await?;
+
+        let shared_secret = crate::crypto::generate_shared_secret();
+        let mut client = client.enable_encryption(&shared_secret)?;
+        let mut server = server.enable_encryption(&shared_secret)?;
+        assert_eq!(client.framer().threshold(), Some(64));
+
+        server.write_packet(login_success()).await?;
+        assert!(matches!(
+            client.read_packet().await?,
+            ClientboundPacket::Login(ClientboundLoginRegistry::LoginGameProfile { game_profile })
+                if game_profile.name == "Steve"
+        ));
+        assert_eq!(client.state(), ConnectionState::Play);
+        Ok(())
+    }
+}
@@ -0,0 +1,344 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use aes::cipher::inout::InOutBuf;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use aes::Aes128;
+use drax::prelude::{AsyncRead, AsyncWrite, Result};
+use drax::{err_explain, throw_explain};
+use rand::RngCore;
+use rsa::pkcs8::{DecodePublicKey, EncodePublicKey};
+use rsa::{Pkcs1v15Encrypt, PublicKey, RsaPrivateKey, RsaPublicKey};
+use tokio::io::ReadBuf;
+
+use crate::clientbound::login::ClientboundLoginRegistry;
+
+pub type Aes128Cfb8Enc = cfb8::Encryptor<Aes128>;
+pub type Aes128Cfb8Dec = cfb8::Decryptor<Aes128>;
+
+/// The size of the RSA key vanilla servers generate for the login key exchange.
+pub const KEY_PAIR_BITS: usize = 1024;
+/// The size of the challenge vanilla servers send in their hello packet.
+pub const CHALLENGE_SIZE: usize = 4;
+
+fn encrypt_in_place(cipher: &mut Aes128Cfb8Enc, data: &mut [u8]) {
+    let (blocks, _) = InOutBuf::from(data).into_chunks();
+    cipher.encrypt_blocks_inout_mut(blocks);
+}
+
+fn decrypt_in_place(cipher: &mut Aes128Cfb8Dec, data: &mut [u8]) {
+    let (blocks, _) = InOutBuf::from(data).into_chunks();
+    cipher.decrypt_blocks_inout_mut(blocks);
+}
+
+/// Wraps a stream with AES/CFB8 encryption, keyed with the shared secret from
+/// [`crate::serverbound::login::ServerBoundLoginRegsitry::Key`]. <br />
+///
+/// Both directions use the shared secret as key and IV; use [`tokio::io::split`] to obtain
+/// separate halves for a framed reader and writer.
+pub struct EncryptedStream<S> {
+    inner: S,
+    encryptor: Aes128Cfb8Enc,
+    decryptor: Aes128Cfb8Dec,
+    pending: Vec<u8>,
+    /// A failure to flush bytes which were already accepted, reported by the next poll.
+    error: Option<io::Error>,
+}
+
+impl<S> EncryptedStream<S> {
+    pub fn new(inner: S, shared_secret: &[u8]) -> Result<Self> {
+        let encryptor = Aes128Cfb8Enc::new_from_slices(shared_secret, shared_secret)
+            .map_err(|_| err_explain!("Shared secret must be exactly 16 bytes."))?;
+        let decryptor = Aes128Cfb8Dec::new_from_slices(shared_secret, shared_secret)
+            .map_err(|_| err_explain!("Shared secret must be exactly 16 bytes."))?;
+        Ok(Self {
+            inner,
+            encryptor,
+            decryptor,
+            pending: Vec::new(),
+            error: None,
+        })
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: AsyncWrite + Unpin> EncryptedStream<S> {
+    fn poll_drain_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(err) = self.error.take() {
+            return Poll::Ready(Err(err));
+        }
+        while !self.pending.is_empty() {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for EncryptedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        decrypt_in_place(&mut this.decryptor, &mut buf.filled_mut()[filled..]);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for EncryptedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        // the cipher state advances on encryption, so bytes are only accepted once everything
+        // encrypted before them has been handed to the inner stream
+        ready!(this.poll_drain_pending(cx))?;
+        let offset = this.pending.len();
+        this.pending.extend_from_slice(buf);
+        encrypt_in_place(&mut this.encryptor, &mut this.pending[offset..]);
+        // the bytes are accepted once encrypted; a retry would encrypt them a second time
+        if let Poll::Ready(Err(err)) = this.poll_drain_pending(cx) {
+            this.error = Some(err);
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain_pending(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// The server side RSA key pair used to receive the client's shared secret.
+pub struct LoginKeyPair {
+    private_key: RsaPrivateKey,
+    public_key_der: Vec<u8>,
+}
+
+impl LoginKeyPair {
+    pub fn generate() -> Result<Self> {
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_PAIR_BITS)
+            .map_err(|err| err_explain!(format!("Failed to generate RSA key: {}", err)))?;
+        let public_key_der = RsaPublicKey::from(&private_key)
+            .to_public_key_der()
+            .map_err(|err| err_explain!(format!("Failed to encode RSA key: {}", err)))?
+            .as_bytes()
+            .to_vec();
+        Ok(Self {
+            private_key,
+            public_key_der,
+        })
+    }
+
+    /// The X.509 encoded public key, as sent in [`ClientboundLoginRegistry::Hello`].
+    pub fn public_key_der(&self) -> &[u8] {
+        &self.public_key_der
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.private_key
+            .decrypt(Pkcs1v15Encrypt, data)
+            .map_err(|err| err_explain!(format!("Failed to decrypt login data: {}", err)))
+    }
+
+    /// Creates a hello packet with a fresh challenge; the challenge is returned alongside the
+    /// packet so it can be verified with [`LoginKeyPair::verify_challenge`].
+    pub fn hello(&self, server_id: String) -> (ClientboundLoginRegistry, Vec<u8>) {
+        let challenge = generate_challenge();
+        (
+            ClientboundLoginRegistry::Hello {
+                server_id,
+                public_key: self.public_key_der.clone(),
+                challenge: challenge.clone(),
+            },
+            challenge,
+        )
+    }
+
+    /// Decrypts and compares the client's encrypted challenge against the one sent.
+    pub fn verify_challenge(&self, challenge: &[u8], encrypted_challenge: &[u8]) -> Result<()> {
+        if self.decrypt(encrypted_challenge)? != challenge {
+            throw_explain!("Challenge response did not match.")
+        }
+        Ok(())
+    }
+
+    /// Decrypts the shared secret sent by the client.
+    pub fn decrypt_shared_secret(&self, key_bytes: &[u8]) -> Result<Vec<u8>> {
+        let shared_secret = self.decrypt(key_bytes)?;
+        if shared_secret.len() != 16 {
+            throw_explain!("Shared secret must be exactly 16 bytes.")
+        }
+        Ok(shared_secret)
+    }
+}
+
+pub fn generate_challenge() -> Vec<u8> {
+    let mut challenge = vec![0u8; CHALLENGE_SIZE];
+    rand::thread_rng().fill_bytes(&mut challenge);
+    challenge
+}
+
+pub fn generate_shared_secret() -> [u8; 16] {
+    let mut shared_secret = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut shared_secret);
+    shared_secret
+}
+
+/// Encrypts data with the server's X.509 encoded public key, as done client side for the
+/// shared secret and challenge.
+pub fn encrypt_with_public_key(public_key_der: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let public_key = RsaPublicKey::from_public_key_der(public_key_der)
+        .map_err(|err| err_explain!(format!("Failed to decode RSA key: {}", err)))?;
+    public_key
+        .encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, data)
+        .map_err(|err| err_explain!(format!("Failed to encrypt login data: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use aes::cipher::KeyIvInit;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::clientbound::login::ClientboundLoginRegistry;
+    use crate::crypto::{
+        encrypt_with_public_key, generate_shared_secret, Aes128Cfb8Dec, Aes128Cfb8Enc,
+        EncryptedStream, LoginKeyPair,
+    };
+
+    // NIST SP 800-38A, F.3.7 CFB8-AES128
+    const KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+        0x3c,
+    ];
+    const IV: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+    const PLAINTEXT: [u8; 18] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17,
+        0x2a, 0xae, 0x2d,
+    ];
+    const CIPHERTEXT: [u8; 18] = [
+        0x3b, 0x79, 0x42, 0x4c, 0x9c, 0x0d, 0xd4, 0x36, 0xba, 0xce, 0x9e, 0x0e, 0xd4, 0x58, 0x6a,
+        0x4f, 0x32, 0xb9,
+    ];
+
+    fn nist_stream<S>(inner: S) -> EncryptedStream<S> {
+        EncryptedStream {
+            inner,
+            encryptor: Aes128Cfb8Enc::new_from_slices(&KEY, &IV).unwrap(),
+            decryptor: Aes128Cfb8Dec::new_from_slices(&KEY, &IV).unwrap(),
+            pending: Vec::new(),
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    pub async fn cfb8_vectors() -> drax::prelude::Result<()> {
+        let (local, mut remote) = tokio::io::duplex(64);
+        let mut stream = nist_stream(local);
+
+        // written in two parts to check the cipher state carries over between writes
+        stream.write_all(&PLAINTEXT[..5]).await?;
+        stream.write_all(&PLAINTEXT[5..]).await?;
+        stream.flush().await?;
+        let mut encrypted = [0u8; 18];
+        remote.read_exact(&mut encrypted).await?;
+        assert_eq!(encrypted, CIPHERTEXT);
+
+        remote.write_all(&CIPHERTEXT).await?;
+        let mut decrypted = [0u8; 18];
+        stream.read_exact(&mut decrypted).await?;
+        assert_eq!(decrypted, PLAINTEXT);
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn encrypted_round_trip() -> drax::prelude::Result<()> {
+        let shared_secret = generate_shared_secret();
+        // a small buffer forces partial writes of the encrypted bytes
+        let (client, server) = tokio::io::duplex(16);
+        let mut client = EncryptedStream::new(client, &shared_secret)?;
+        let mut server = EncryptedStream::new(server, &shared_secret)?;
+
+        let message: Vec<u8> = (0..1024).map(|i| (i * 7) as u8).collect();
+        let mut received = vec![0u8; message.len()];
+        let (written, read) = tokio::join!(
+            async {
+                client.write_all(&message).await?;
+                client.flush().await
+            },
+            server.read_exact(&mut received)
+        );
+        written?;
+        read?;
+        assert_eq!(received, message);
+
+        assert!(EncryptedStream::new(tokio::io::sink(), &[0u8; 8]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn login_key_exchange() -> drax::prelude::Result<()> {
+        let key_pair = LoginKeyPair::generate()?;
+        let (hello, challenge) = key_pair.hello("".to_string());
+        let public_key = match hello {
+            ClientboundLoginRegistry::Hello {
+                public_key,
+                challenge: sent,
+                ..
+            } => {
+                assert_eq!(sent, challenge);
+                public_key
+            }
+            _ => panic!("Expected a hello packet."),
+        };
+        assert_eq!(public_key, key_pair.public_key_der());
+
+        let shared_secret = generate_shared_secret();
+        let encrypted_secret = encrypt_with_public_key(&public_key, &shared_secret)?;
+        assert_ne!(encrypted_secret, shared_secret);
+        assert_eq!(
+            key_pair.decrypt_shared_secret(&encrypted_secret)?,
+            shared_secret
+        );
+
+        let encrypted_challenge = encrypt_with_public_key(&public_key, &challenge)?;
+        key_pair.verify_challenge(&challenge, &encrypted_challenge)?;
+        assert!(key_pair
+            .verify_challenge(&[0, 0, 0, 0], &encrypted_challenge)
+            .is_err());
+        let short_secret = encrypt_with_public_key(&public_key, &[1, 2, 3])?;
+        assert!(key_pair.decrypt_shared_secret(&short_secret).is_err());
+        Ok(())
+    }
+}
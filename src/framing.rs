@@ -0,0 +1,364 @@
+use std::io::{Cursor, Read, Write};
+
+use drax::prelude::{
+    AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DraxReadExt, DraxWriteExt, PacketComponent,
+    Result,
+};
+use drax::transport::buffer::var_num::size_var_int;
+use drax::{err_explain, throw_explain};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+/// The largest frame length a 3 byte VarInt prefix can describe.
+pub const MAX_FRAME_SIZE: usize = 2097151;
+/// The largest uncompressed packet a vanilla peer will accept.
+pub const MAX_DECOMPRESSED_SIZE: usize = 8388608;
+
+/// Shared framing configuration for a connection. <br />
+///
+/// Frames are prefixed with their VarInt length; once compression is enabled through
+/// [`crate::clientbound::login::ClientboundLoginRegistry::LoginCompression`] every frame
+/// additionally carries the VarInt length of the uncompressed data, or `0` if the packet was
+/// below the threshold and sent as is.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PacketFramer {
+    compression_threshold: Option<i32>,
+    max_frame_size: usize,
+    max_decompressed_size: usize,
+}
+
+impl Default for PacketFramer {
+    fn default() -> Self {
+        Self {
+            compression_threshold: None,
+            max_frame_size: MAX_FRAME_SIZE,
+            max_decompressed_size: MAX_DECOMPRESSED_SIZE,
+        }
+    }
+}
+
+impl PacketFramer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables compression for packets of at least `threshold` bytes; a negative threshold
+    /// disables compression, mirroring vanilla semantics.
+    pub fn compression_threshold(mut self, threshold: i32) -> Self {
+        self.set_compression_threshold(threshold);
+        self
+    }
+
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    pub fn max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = max_decompressed_size;
+        self
+    }
+
+    pub fn set_compression_threshold(&mut self, threshold: i32) {
+        self.compression_threshold = if threshold < 0 {
+            None
+        } else {
+            Some(threshold)
+        };
+    }
+
+    pub fn threshold(&self) -> Option<i32> {
+        self.compression_threshold
+    }
+
+    /// Wraps the given packet bytes into a complete frame, including the length prefix.
+    pub async fn frame(&self, packet: &[u8]) -> Result<Vec<u8>> {
+        if packet.len() > self.max_decompressed_size {
+            throw_explain!(format!(
+                "Packet of size {} exceeds the maximum of {}.",
+                packet.len(),
+                self.max_decompressed_size
+            ))
+        }
+        let mut body = Vec::with_capacity(packet.len() + 5);
+        match self.compression_threshold {
+            Some(threshold) if packet.len() >= threshold as usize => {
+                body.write_var_int(packet.len() as i32).await?;
+                let mut encoder = ZlibEncoder::new(body, Compression::default());
+                encoder.write_all(packet)?;
+                body = encoder.finish()?;
+            }
+            Some(_) => {
+                body.write_var_int(0).await?;
+                body.extend_from_slice(packet);
+            }
+            None => body.extend_from_slice(packet),
+        }
+        if body.len() > self.max_frame_size {
+            throw_explain!(format!(
+                "Frame of size {} exceeds the maximum of {}.",
+                body.len(),
+                self.max_frame_size
+            ))
+        }
+        let mut frame = Vec::with_capacity(body.len() + size_var_int(body.len() as i32));
+        frame.write_var_int(body.len() as i32).await?;
+        frame.extend_from_slice(&body);
+        Ok(frame)
+    }
+
+    /// Unwraps the body of a frame (without its length prefix) into the raw packet bytes.
+    pub async fn unframe(&self, body: Vec<u8>) -> Result<Vec<u8>> {
+        let threshold = match self.compression_threshold {
+            None => return Ok(body),
+            Some(threshold) => threshold,
+        };
+        let mut cursor = Cursor::new(body);
+        let data_length = cursor.read_var_int().await?;
+        let offset = cursor.position() as usize;
+        let mut body = cursor.into_inner();
+        if data_length == 0 {
+            body.drain(..offset);
+            return Ok(body);
+        }
+        if data_length < threshold {
+            throw_explain!(format!(
+                "Badly compressed packet; size of {} is below the threshold of {}.",
+                data_length, threshold
+            ))
+        }
+        if data_length as usize > self.max_decompressed_size {
+            throw_explain!(format!(
+                "Badly compressed packet; size of {} is larger than the maximum of {}.",
+                data_length, self.max_decompressed_size
+            ))
+        }
+        let mut packet = Vec::with_capacity(data_length as usize);
+        ZlibDecoder::new(&body[offset..])
+            .take(data_length as u64 + 1)
+            .read_to_end(&mut packet)
+            .map_err(|err| err_explain!(format!("Failed to inflate packet: {}", err)))?;
+        if packet.len() != data_length as usize {
+            throw_explain!(format!(
+                "Inflated packet of size {} did not match the declared size of {}.",
+                packet.len(),
+                data_length
+            ))
+        }
+        Ok(packet)
+    }
+}
+
+/// Reads length prefixed, optionally compressed, frames from an underlying reader.
+pub struct FramedReader<R> {
+    framer: PacketFramer,
+    read: R,
+}
+
+impl<R: AsyncRead + Unpin + Send + Sync> FramedReader<R> {
+    pub fn new(read: R) -> Self {
+        Self::with_framer(read, PacketFramer::default())
+    }
+
+    pub fn with_framer(read: R, framer: PacketFramer) -> Self {
+        Self { framer, read }
+    }
+
+    pub fn framer(&self) -> &PacketFramer {
+        &self.framer
+    }
+
+    pub fn framer_mut(&mut self) -> &mut PacketFramer {
+        &mut self.framer
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.read
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.read
+    }
+
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+
+    /// Reads the next frame and returns the raw packet bytes, starting with the packet id.
+    pub async fn read_frame(&mut self) -> Result<Vec<u8>> {
+        let frame_length = self.read.read_var_int().await?;
+        if frame_length <= 0 {
+            throw_explain!(format!("Invalid frame length of {}.", frame_length))
+        }
+        if frame_length as usize > self.framer.max_frame_size {
+            throw_explain!(format!(
+                "Frame of size {} exceeds the maximum of {}.",
+                frame_length, self.framer.max_frame_size
+            ))
+        }
+        let mut body = vec![0u8; frame_length as usize];
+        self.read.read_exact(&mut body).await?;
+        self.framer.unframe(body).await
+    }
+
+    /// Reads the next frame and decodes it as the given packet component, typically a registry.
+    pub async fn read_packet<C: Send + Sync, P: PacketComponent<C>>(
+        &mut self,
+        context: &mut C,
+    ) -> Result<P::ComponentType> {
+        let packet = self.read_frame().await?;
+        let length = packet.len() as u64;
+        let mut cursor = Cursor::new(packet);
+        let component = P::decode(context, &mut cursor).await?;
+        if cursor.position() != length {
+            throw_explain!(format!(
+                "Packet was not fully consumed; {} bytes remained.",
+                length - cursor.position()
+            ))
+        }
+        Ok(component)
+    }
+}
+
+/// Writes length prefixed, optionally compressed, frames to an underlying writer.
+pub struct FramedWriter<W> {
+    framer: PacketFramer,
+    write: W,
+}
+
+impl<W: AsyncWrite + Unpin + Send + Sync> FramedWriter<W> {
+    pub fn new(write: W) -> Self {
+        Self::with_framer(write, PacketFramer::default())
+    }
+
+    pub fn with_framer(write: W, framer: PacketFramer) -> Self {
+        Self { framer, write }
+    }
+
+    pub fn framer(&self) -> &PacketFramer {
+        &self.framer
+    }
+
+    pub fn framer_mut(&mut self) -> &mut PacketFramer {
+        &mut self.framer
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.write
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.write
+    }
+
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+
+    /// Frames and writes the raw packet bytes, which should start with the packet id.
+    pub async fn write_frame(&mut self, packet: &[u8]) -> Result<()> {
+        let frame = self.framer.frame(packet).await?;
+        self.write.write_all(&frame).await?;
+        self.write.flush().await?;
+        Ok(())
+    }
+
+    /// Encodes the given packet component, typically a registry, and writes it as a frame.
+    pub async fn write_packet<C: Send + Sync, P: PacketComponent<C>>(
+        &mut self,
+        context: &mut C,
+        packet: &P::ComponentType,
+    ) -> Result<()> {
+        let mut buffer = Vec::new();
+        P::encode(packet, context, &mut buffer).await?;
+        self.write_frame(&buffer).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framing::{FramedReader, FramedWriter, PacketFramer};
+    use crate::handshaking::{ConnectionProtocol, HandshakingRegistry};
+    use crate::serverbound::status::ServerboundStatusRegistry;
+
+    #[tokio::test]
+    pub async fn round_trip_uncompressed() -> drax::prelude::Result<()> {
+        let (client, server) = tokio::io::duplex(1024);
+        let mut writer = FramedWriter::new(client);
+        let mut reader = FramedReader::new(server);
+
+        writer
+            .write_packet::<(), HandshakingRegistry>(
+                &mut (),
+                &HandshakingRegistry::ClientIntention {
+                    protocol_version: 761,
+                    host_name: "localhost".to_string(),
+                    port: 25565,
+                    intention: ConnectionProtocol::Status {},
+                },
+            )
+            .await?;
+
+        let packet = reader
+            .read_packet::<(), HandshakingRegistry>(&mut ())
+            .await?;
+        assert!(matches!(
+            packet,
+            HandshakingRegistry::ClientIntention {
+                protocol_version: 761,
+                port: 25565,
+                intention: ConnectionProtocol::Status {},
+                ..
+            }
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn round_trip_compressed() -> drax::prelude::Result<()> {
+        let (client, server) = tokio::io::duplex(1 << 16);
+        let framer = PacketFramer::new().compression_threshold(256);
+        let mut writer = FramedWriter::with_framer(client, framer);
+        let mut reader = FramedReader::with_framer(server, framer);
+
+        let small = vec![7u8; 16];
+        let large = vec![9u8; 4096];
+        writer.write_frame(&small).await?;
+        writer.write_frame(&large).await?;
+        writer
+            .write_packet::<(), ServerboundStatusRegistry>(
+                &mut (),
+                &ServerboundStatusRegistry::Ping { payload: 42 },
+            )
+            .await?;
+
+        assert_eq!(reader.read_frame().await?, small);
+        assert_eq!(reader.read_frame().await?, large);
+        assert!(matches!(
+            reader
+                .read_packet::<(), ServerboundStatusRegistry>(&mut ())
+                .await?,
+            ServerboundStatusRegistry::Ping { payload: 42 }
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn rejects_oversized_frames() -> drax::prelude::Result<()> {
+        let (client, server) = tokio::io::duplex(1 << 16);
+        let mut writer = FramedWriter::new(client);
+        let mut reader =
+            FramedReader::with_framer(server, PacketFramer::new().max_frame_size(1024));
+
+        writer.write_frame(&vec![0u8; 2048]).await?;
+        assert!(reader.read_frame().await.is_err());
+
+        let mut limited = FramedWriter::with_framer(
+            tokio::io::sink(),
+            PacketFramer::new().max_decompressed_size(1024),
+        );
+        assert!(limited.write_frame(&vec![0u8; 2048]).await.is_err());
+        Ok(())
+    }
+}
@@ -165,5 +165,13 @@ macro_rules! registry {
 
 pub mod clientbound;
 pub mod common;
+#[cfg(all(feature = "login", feature = "play"))]
+pub mod connection;
+#[cfg(feature = "crypto")]
+pub mod crypto;
+pub mod framing;
 pub mod handshaking;
 pub mod serverbound;
+pub mod status;
+#[cfg(feature = "play")]
+pub mod version;
@@ -0,0 +1,295 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use drax::prelude::{AsyncRead, AsyncWrite, AsyncWriteExt, Result};
+use drax::throw_explain;
+
+use crate::clientbound::status::{ClientboundStatusRegistry, StatusResponse};
+use crate::framing::{FramedReader, FramedWriter};
+use crate::handshaking::{ConnectionProtocol, HandshakingRegistry};
+use crate::serverbound::status::ServerboundStatusRegistry;
+use crate::status::legacy::{write_legacy_response, LegacyPing};
+
+pub mod legacy;
+
+/// The protocol version announced when querying a server's status.
+pub const QUERY_PROTOCOL_VERSION: i32 = 761;
+
+/// Performs a full server list ping over the given stream. <br />
+///
+/// Returns the server's status response along with the round trip time of the ping.
+pub async fn query<S: AsyncRead + AsyncWrite + Unpin + Send + Sync>(
+    stream: S,
+    host: &str,
+    port: u16,
+) -> Result<(StatusResponse, Duration)> {
+    let (read, write) = tokio::io::split(stream);
+    let mut reader = FramedReader::new(read);
+    let mut writer = FramedWriter::new(write);
+    let ctx = &mut ();
+
+    writer
+        .write_packet::<(), HandshakingRegistry>(
+            ctx,
+            &HandshakingRegistry::ClientIntention {
+                protocol_version: QUERY_PROTOCOL_VERSION,
+                host_name: host.to_string(),
+                port,
+                intention: ConnectionProtocol::Status {},
+            },
+        )
+        .await?;
+    writer
+        .write_packet::<(), ServerboundStatusRegistry>(ctx, &ServerboundStatusRegistry::Request {})
+        .await?;
+
+    let response = match reader
+        .read_packet::<(), ClientboundStatusRegistry>(ctx)
+        .await?
+    {
+        ClientboundStatusRegistry::Response { response } => response,
+        ClientboundStatusRegistry::Pong { .. } => {
+            throw_explain!("Expected a status response but received a pong.")
+        }
+    };
+
+    let payload = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or_default();
+    let start = Instant::now();
+    writer
+        .write_packet::<(), ServerboundStatusRegistry>(
+            ctx,
+            &ServerboundStatusRegistry::Ping { payload },
+        )
+        .await?;
+    match reader
+        .read_packet::<(), ClientboundStatusRegistry>(ctx)
+        .await?
+    {
+        ClientboundStatusRegistry::Pong { payload: pong } if pong == payload => {
+            Ok((response, start.elapsed()))
+        }
+        ClientboundStatusRegistry::Pong { payload: pong } => throw_explain!(format!(
+            "Pong payload {} did not match ping payload {}.",
+            pong, payload
+        )),
+        ClientboundStatusRegistry::Response { .. } => {
+            throw_explain!("Expected a pong but received a status response.")
+        }
+    }
+}
+
+/// Answers server list pings with a fixed [`StatusResponse`].
+#[derive(Debug, Clone)]
+pub struct StatusResponder {
+    response: StatusResponse,
+}
+
+impl StatusResponder {
+    pub fn new(response: StatusResponse) -> Self {
+        Self { response }
+    }
+
+    pub fn response(&self) -> &StatusResponse {
+        &self.response
+    }
+
+    pub fn response_mut(&mut self) -> &mut StatusResponse {
+        &mut self.response
+    }
+
+    /// Reads the handshake from the stream and answers the status request and ping.
+    pub async fn respond<S: AsyncRead + AsyncWrite + Unpin + Send + Sync>(
+        &self,
+        stream: S,
+    ) -> Result<()> {
+        let (read, write) = tokio::io::split(stream);
+        let mut reader = FramedReader::new(read);
+        let mut writer = FramedWriter::new(write);
+
+        match reader
+            .read_packet::<(), HandshakingRegistry>(&mut ())
+            .await?
+        {
+            HandshakingRegistry::ClientIntention {
+                intention: ConnectionProtocol::Status {},
+                ..
+            } => {}
+            HandshakingRegistry::ClientIntention { .. } => {
+                throw_explain!("Client did not intend to request the status.")
+            }
+        }
+
+        self.respond_after_handshake(&mut reader, &mut writer).await
+    }
+
+    /// Answers a legacy ping, read through [`legacy::LegacyPingKind::read`], and closes the stream.
+    pub async fn respond_legacy<S: AsyncWrite + Unpin + Send + Sync>(
+        &self,
+        mut stream: S,
+        ping: &LegacyPing,
+    ) -> Result<()> {
+        write_legacy_response(&mut stream, &self.response, ping.kind).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// Answers the status request and ping on a connection whose handshake was already read.
+    pub async fn respond_after_handshake<
+        R: AsyncRead + Unpin + Send + Sync,
+        W: AsyncWrite + Unpin + Send + Sync,
+    >(
+        &self,
+        reader: &mut FramedReader<R>,
+        writer: &mut FramedWriter<W>,
+    ) -> Result<()> {
+        let ctx = &mut ();
+        match reader
+            .read_packet::<(), ServerboundStatusRegistry>(ctx)
+            .await?
+        {
+            ServerboundStatusRegistry::Request {} => {}
+            ServerboundStatusRegistry::Ping { .. } => {
+                throw_explain!("Received a ping before the status request.")
+            }
+        }
+        writer
+            .write_packet::<(), ClientboundStatusRegistry>(
+                ctx,
+                &ClientboundStatusRegistry::Response {
+                    response: self.response.clone(),
+                },
+            )
+            .await?;
+
+        match reader
+            .read_packet::<(), ServerboundStatusRegistry>(ctx)
+            .await?
+        {
+            ServerboundStatusRegistry::Ping { payload } => {
+                writer
+                    .write_packet::<(), ClientboundStatusRegistry>(
+                        ctx,
+                        &ClientboundStatusRegistry::Pong { payload },
+                    )
+                    .await
+            }
+            ServerboundStatusRegistry::Request {} => {
+                throw_explain!("Received a second status request.")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clientbound::status::{StatusPlayers, StatusResponse, StatusVersion};
+    use crate::common::chat::Chat;
+    use crate::handshaking::{ConnectionProtocol, HandshakingRegistry};
+    use crate::status::legacy::LegacyPingKind;
+    use crate::status::{query, StatusResponder};
+
+    fn response() -> StatusResponse {
+        StatusResponse {
+            description: Chat::text("A Minecraft Server"),
+            players: StatusPlayers {
+                max: 20,
+                online: 3,
+                sample: vec![],
+            },
+            version: StatusVersion {
+                name: "1.19.3".to_string(),
+                protocol: 761,
+            },
+            favicon: None,
+            enforces_secure_chat: Some(true),
+        }
+    }
+
+    #[tokio::test]
+    pub async fn query_responder() -> drax::prelude::Result<()> {
+        let (client, server) = tokio::io::duplex(1024);
+        let responder = StatusResponder::new(response());
+
+        let (queried, responded) =
+            tokio::join!(query(client, "localhost", 25565), responder.respond(server));
+        responded?;
+        let (status, _) = queried?;
+        assert_eq!(status.players.online, 3);
+        assert_eq!(status.version.protocol, 761);
+        assert_eq!(status.enforces_secure_chat, Some(true));
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn responder_rejects_login() -> drax::prelude::Result<()> {
+        let (client, server) = tokio::io::duplex(1024);
+        let responder = StatusResponder::new(response());
+
+        let mut writer = crate::framing::FramedWriter::new(client);
+        writer
+            .write_packet::<(), HandshakingRegistry>(
+                &mut (),
+                &HandshakingRegistry::ClientIntention {
+                    protocol_version: 761,
+                    host_name: "localhost".to_string(),
+                    port: 25565,
+                    intention: ConnectionProtocol::Login {},
+                },
+            )
+            .await?;
+        assert!(responder.respond(server).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn legacy_ping() -> drax::prelude::Result<()> {
+        assert_eq!(LegacyPingKind::detect(&[0xFE]), Some(LegacyPingKind::Beta));
+        assert_eq!(
+            LegacyPingKind::detect(&[0xFE, 0x01]),
+            Some(LegacyPingKind::V1_4)
+        );
+        assert_eq!(
+            LegacyPingKind::detect(&[0xFE, 0x01, 0xFA, 0x00]),
+            Some(LegacyPingKind::V1_6)
+        );
+        assert_eq!(LegacyPingKind::detect(&[0xFE, 0x01, 0x00]), None);
+        assert_eq!(LegacyPingKind::detect(&[0x10, 0x00]), None);
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        let responder = StatusResponder::new(response());
+        let utf16 = |string: &str, out: &mut Vec<u8>| {
+            out.extend_from_slice(&(string.len() as u16).to_be_bytes());
+            string
+                .encode_utf16()
+                .for_each(|unit| out.extend_from_slice(&unit.to_be_bytes()));
+        };
+        let mut request = vec![0xFE, 0x01, 0xFA];
+        utf16("MC|PingHost", &mut request);
+        request.extend_from_slice(&(7 + 2 * 9u16).to_be_bytes());
+        request.push(78);
+        utf16("localhost", &mut request);
+        request.extend_from_slice(&25565i32.to_be_bytes());
+        tokio::io::AsyncWriteExt::write_all(&mut client, &request).await?;
+
+        let (mut read, write) = tokio::io::split(server);
+        let ping = LegacyPingKind::V1_6.read(&mut read).await?;
+        assert_eq!(ping.host, Some(("localhost".to_string(), 25565, 78)));
+        responder.respond_legacy(write, &ping).await?;
+
+        let mut kick = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut client, &mut kick).await?;
+        let units: Vec<u16> = kick[3..]
+            .chunks(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect();
+        assert_eq!(kick[0], 0xFF);
+        assert_eq!(u16::from_be_bytes([kick[1], kick[2]]) as usize, units.len());
+        assert_eq!(
+            String::from_utf16(&units).unwrap(),
+            "\u{a7}1\x00761\x001.19.3\x00A Minecraft Server\x003\x0020"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use drax::prelude::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, Result};
+use drax::throw_explain;
+
+use crate::clientbound::status::StatusResponse;
+use crate::common::chat::{strip_legacy_codes, SECTION_SIGN};
+
+const LEGACY_PING_ID: u8 = 0xFE;
+const LEGACY_PING_PAYLOAD: u8 = 0x01;
+const LEGACY_PLUGIN_MESSAGE_ID: u8 = 0xFA;
+const LEGACY_KICK_ID: u8 = 0xFF;
+const PING_HOST_CHANNEL: &str = "MC|PingHost";
+
+/// The generation of a legacy (pre-Netty) server list ping.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum LegacyPingKind {
+    /// Beta 1.8 to 1.3; a lone `0xFE`.
+    Beta,
+    /// 1.4 and 1.5; `0xFE 0x01`.
+    V1_4,
+    /// 1.6; `0xFE 0x01` followed by an `MC|PingHost` plugin message.
+    V1_6,
+}
+
+/// A fully read legacy ping request.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LegacyPing {
+    pub kind: LegacyPingKind,
+    /// The host, port and protocol version the client connected with; only sent by 1.6 clients.
+    pub host: Option<(String, i32, u8)>,
+}
+
+impl LegacyPingKind {
+    /// Inspects the bytes available at the start of a connection, for example obtained through
+    /// `TcpStream::peek`, and determines whether they start a legacy ping. <br />
+    ///
+    /// Mirrors vanilla detection; a modern handshake whose length happens to encode as
+    /// `0xFE 0x01` is told apart by its packet id following instead of a plugin message.
+    pub fn detect(prefix: &[u8]) -> Option<Self> {
+        match prefix {
+            [LEGACY_PING_ID] => Some(LegacyPingKind::Beta),
+            [LEGACY_PING_ID, LEGACY_PING_PAYLOAD] => Some(LegacyPingKind::V1_4),
+            [LEGACY_PING_ID, LEGACY_PING_PAYLOAD, LEGACY_PLUGIN_MESSAGE_ID, ..] => {
+                Some(LegacyPingKind::V1_6)
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes the ping request previously detected by [`LegacyPingKind::detect`].
+    pub async fn read<R: AsyncRead + Unpin + Send + Sync + ?Sized>(
+        self,
+        read: &mut R,
+    ) -> Result<LegacyPing> {
+        if read.read_u8().await? != LEGACY_PING_ID {
+            throw_explain!("Legacy ping did not start with 0xFE.")
+        }
+        if matches!(self, LegacyPingKind::Beta) {
+            return Ok(LegacyPing {
+                kind: self,
+                host: None,
+            });
+        }
+        if read.read_u8().await? != LEGACY_PING_PAYLOAD {
+            throw_explain!("Legacy ping did not carry the 0x01 payload.")
+        }
+        if matches!(self, LegacyPingKind::V1_4) {
+            return Ok(LegacyPing {
+                kind: self,
+                host: None,
+            });
+        }
+        if read.read_u8().await? != LEGACY_PLUGIN_MESSAGE_ID {
+            throw_explain!("Legacy ping did not carry a plugin message.")
+        }
+        let channel = read_utf16(read).await?;
+        if channel != PING_HOST_CHANNEL {
+            throw_explain!(format!("Unexpected legacy ping channel {}.", channel))
+        }
+        let _data_length = read.read_u16().await?;
+        let protocol_version = read.read_u8().await?;
+        let host = read_utf16(read).await?;
+        let port = read.read_i32().await?;
+        Ok(LegacyPing {
+            kind: self,
+            host: Some((host, port, protocol_version)),
+        })
+    }
+}
+
+async fn read_utf16<R: AsyncRead + Unpin + Send + Sync + ?Sized>(read: &mut R) -> Result<String> {
+    let length = read.read_u16().await?;
+    let mut units = Vec::with_capacity(length as usize);
+    for _ in 0..length {
+        units.push(read.read_u16().await?);
+    }
+    match String::from_utf16(&units) {
+        Ok(string) => Ok(string),
+        Err(_) => throw_explain!("Legacy ping contained invalid UTF-16."),
+    }
+}
+
+/// Creates the kick string a legacy client parses as the server's status.
+pub fn legacy_status_string(response: &StatusResponse, kind: LegacyPingKind) -> String {
+    let motd = response.description.to_legacy();
+    match kind {
+        LegacyPingKind::Beta => format!(
+            "{}\u{a7}{}\u{a7}{}",
+            strip_legacy_codes(&motd, SECTION_SIGN).replace(SECTION_SIGN, ""),
+            response.players.online,
+            response.players.max
+        ),
+        LegacyPingKind::V1_4 | LegacyPingKind::V1_6 => format!(
+            "\u{a7}1\0{}\0{}\0{}\0{}\0{}",
+            response.version.protocol,
+            response.version.name,
+            motd,
+            response.players.online,
+            response.players.max
+        ),
+    }
+}
+
+/// Encodes the legacy kick packet carrying the server's status.
+pub fn encode_legacy_response(response: &StatusResponse, kind: LegacyPingKind) -> Vec<u8> {
+    let units: Vec<u16> = legacy_status_string(response, kind)
+        .encode_utf16()
+        .collect();
+    let mut packet = Vec::with_capacity(3 + units.len() * 2);
+    packet.push(LEGACY_KICK_ID);
+    packet.extend_from_slice(&(units.len() as u16).to_be_bytes());
+    for unit in units {
+        packet.extend_from_slice(&unit.to_be_bytes());
+    }
+    packet
+}
+
+/// Writes the legacy kick packet carrying the server's status.
+pub async fn write_legacy_response<W: AsyncWrite + Unpin + Send + Sync + ?Sized>(
+    write: &mut W,
+    response: &StatusResponse,
+    kind: LegacyPingKind,
+) -> Result<()> {
+    write
+        .write_all(&encode_legacy_response(response, kind))
+        .await?;
+    write.flush().await?;
+    Ok(())
+}
@@ -0,0 +1,952 @@
+use std::io::Cursor;
+
+use drax::prelude::{
+    AsyncReadExt, AsyncWriteExt, DraxReadExt, DraxWriteExt, PacketComponent, Result,
+};
+use drax::throw_explain;
+
+use crate::clientbound::play::{
+    ClientboundPlayRegistry, EntityDataItem, EntityDataList, EntityDataValue,
+};
+use crate::serverbound::login::ServerBoundLoginRegsitry;
+use crate::serverbound::play::ServerboundPlayRegistry;
+
+mod signed_chat;
+
+/// A released protocol version, keyed by the `protocol_version` sent in
+/// [`crate::handshaking::HandshakingRegistry::ClientIntention`]. <br />
+///
+/// The registries in this crate are laid out for [`ProtocolVersion::CANONICAL`]; the other
+/// versions are translated to and from that layout by a [`VersionedCodec`]. <br />
+///
+/// 1.19 to 1.19.2 sign chat with an earlier model. Their chat and login packets are translated
+/// without the signatures, and their player info packets have no canonical counterpart.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ProtocolVersion {
+    /// 1.19
+    V1_19,
+    /// 1.19.1 and 1.19.2
+    V1_19_2,
+    /// 1.19.3
+    V1_19_3,
+    /// 1.19.4
+    V1_19_4,
+    /// 1.20 and 1.20.1
+    V1_20,
+}
+
+impl ProtocolVersion {
+    pub const CANONICAL: ProtocolVersion = ProtocolVersion::V1_19_3;
+
+    pub const VALUES: [ProtocolVersion; 5] = [
+        ProtocolVersion::V1_19,
+        ProtocolVersion::V1_19_2,
+        ProtocolVersion::V1_19_3,
+        ProtocolVersion::V1_19_4,
+        ProtocolVersion::V1_20,
+    ];
+
+    pub fn from_protocol(protocol: i32) -> Option<Self> {
+        Self::VALUES
+            .into_iter()
+            .find(|version| version.protocol() == protocol)
+    }
+
+    pub const fn protocol(self) -> i32 {
+        match self {
+            ProtocolVersion::V1_19 => 759,
+            ProtocolVersion::V1_19_2 => 760,
+            ProtocolVersion::V1_19_3 => 761,
+            ProtocolVersion::V1_19_4 => 762,
+            ProtocolVersion::V1_20 => 763,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            ProtocolVersion::V1_19 => "1.19",
+            ProtocolVersion::V1_19_2 => "1.19.2",
+            ProtocolVersion::V1_19_3 => "1.19.3",
+            ProtocolVersion::V1_19_4 => "1.19.4",
+            ProtocolVersion::V1_20 => "1.20.1",
+        }
+    }
+
+    fn clientbound_play_spans(self) -> &'static [IdSpan] {
+        match self {
+            ProtocolVersion::V1_19 => CLIENTBOUND_PLAY_1_19,
+            ProtocolVersion::V1_19_2 => CLIENTBOUND_PLAY_1_19_2,
+            ProtocolVersion::V1_19_3 => CLIENTBOUND_PLAY_1_19_3,
+            ProtocolVersion::V1_19_4 | ProtocolVersion::V1_20 => CLIENTBOUND_PLAY_1_19_4,
+        }
+    }
+
+    fn serverbound_play_spans(self) -> &'static [IdSpan] {
+        match self {
+            ProtocolVersion::V1_19 => SERVERBOUND_PLAY_1_19,
+            ProtocolVersion::V1_19_2 => SERVERBOUND_PLAY_1_19_2,
+            ProtocolVersion::V1_19_3 => SERVERBOUND_PLAY_1_19_3,
+            ProtocolVersion::V1_19_4 | ProtocolVersion::V1_20 => SERVERBOUND_PLAY_1_19_4,
+        }
+    }
+
+    fn entity_data_serializer_spans(self) -> &'static [IdSpan] {
+        match self {
+            ProtocolVersion::V1_19 | ProtocolVersion::V1_19_2 => ENTITY_DATA_SERIALIZERS_1_19,
+            ProtocolVersion::V1_19_3 => ENTITY_DATA_SERIALIZERS_1_19_3,
+            ProtocolVersion::V1_19_4 | ProtocolVersion::V1_20 => ENTITY_DATA_SERIALIZERS_1_19_4,
+        }
+    }
+
+    fn particle_spans(self) -> &'static [IdSpan] {
+        match self {
+            ProtocolVersion::V1_19 | ProtocolVersion::V1_19_2 | ProtocolVersion::V1_19_3 => {
+                PARTICLES_1_19_3
+            }
+            ProtocolVersion::V1_19_4 => PARTICLES_1_19_4,
+            ProtocolVersion::V1_20 => PARTICLES_1_20,
+        }
+    }
+
+    fn clientbound_play_layout(self, canonical_id: i32) -> LayoutChange {
+        match (self, canonical_id) {
+            (ProtocolVersion::V1_19_3, _) => LayoutChange::None,
+            (_, SET_ENTITY_DATA_ID) => LayoutChange::EntityDataIds,
+            (ProtocolVersion::V1_19, SYSTEM_CHAT_ID) => LayoutChange::SignedChat,
+            (ProtocolVersion::V1_19 | ProtocolVersion::V1_19_2, id) => match id {
+                PLAYER_CHAT_ID | SERVER_DATA_ID => LayoutChange::SignedChat,
+                SOUND_ENTITY_ID | SOUND_ID => LayoutChange::SoundId,
+                _ => LayoutChange::None,
+            },
+            (_, LEVEL_PARTICLES_ID) => LayoutChange::LeadingParticleId,
+            (ProtocolVersion::V1_20, CLIENT_LOGIN_ID | RESPAWN_ID) => LayoutChange::TrailingVarInt,
+            (ProtocolVersion::V1_20, OPEN_SIGN_EDITOR_ID) => LayoutChange::FrontText,
+            (_, PLAYER_POSITION_ID) => LayoutChange::DroppedTrailingBool,
+            _ => LayoutChange::None,
+        }
+    }
+
+    fn serverbound_play_layout(self, canonical_id: i32) -> LayoutChange {
+        match (self, canonical_id) {
+            (
+                ProtocolVersion::V1_19 | ProtocolVersion::V1_19_2,
+                CHAT_ACK_ID | CHAT_COMMAND_ID | CHAT_ID,
+            ) => LayoutChange::SignedChat,
+            (ProtocolVersion::V1_20, SIGN_UPDATE_ID) => LayoutChange::FrontText,
+            _ => LayoutChange::None,
+        }
+    }
+
+    fn serverbound_login_layout(self, id: i32) -> LayoutChange {
+        match (self, id) {
+            (ProtocolVersion::V1_19 | ProtocolVersion::V1_19_2, HELLO_ID | KEY_ID) => {
+                LayoutChange::SignedChat
+            }
+            _ => LayoutChange::None,
+        }
+    }
+}
+
+impl TryFrom<i32> for ProtocolVersion {
+    type Error = drax::prelude::TransportError;
+
+    fn try_from(protocol: i32) -> Result<Self> {
+        match Self::from_protocol(protocol) {
+            Some(version) => Ok(version),
+            None => throw_explain!(format!("Unknown protocol version {}.", protocol)),
+        }
+    }
+}
+
+const LEVEL_PARTICLES_ID: i32 = 0x22;
+const CLIENT_LOGIN_ID: i32 = 0x24;
+const OPEN_SIGN_EDITOR_ID: i32 = 0x2D;
+const PLAYER_CHAT_ID: i32 = 0x31;
+const PLAYER_POSITION_ID: i32 = 0x38;
+const RESPAWN_ID: i32 = 0x3D;
+const SERVER_DATA_ID: i32 = 0x41;
+const SET_ENTITY_DATA_ID: i32 = 0x4E;
+const SOUND_ENTITY_ID: i32 = 0x5D;
+const SOUND_ID: i32 = 0x5E;
+const SYSTEM_CHAT_ID: i32 = 0x60;
+
+const CHAT_ACK_ID: i32 = 0x03;
+const CHAT_COMMAND_ID: i32 = 0x04;
+const CHAT_ID: i32 = 0x05;
+const SIGN_UPDATE_ID: i32 = 0x2E;
+
+const HELLO_ID: i32 = 0x00;
+const KEY_ID: i32 = 0x01;
+
+/// The length of the packed block position both sign packets start with.
+const BLOCK_POS_LENGTH: usize = 8;
+
+/// The canonical id of the particle entity data serializer, which is followed by a particle id.
+const PARTICLE_SERIALIZER_ID: i32 = 16;
+
+/// A run of registry ids in a version's ordering.
+enum IdSpan {
+    /// Canonical ids in the half open range, in order.
+    Canonical(i32, i32),
+    /// An entry with no canonical counterpart.
+    Absent(&'static str),
+}
+
+const CLIENTBOUND_PLAY_1_19: &[IdSpan] = &[
+    IdSpan::Canonical(0x00, 0x0C),
+    IdSpan::Absent("chat_preview"),
+    IdSpan::Canonical(0x0C, 0x14),
+    IdSpan::Canonical(0x15, 0x16),
+    IdSpan::Absent("custom_sound"),
+    IdSpan::Canonical(0x17, 0x18),
+    IdSpan::Canonical(0x19, 0x35),
+    IdSpan::Absent("player_info"),
+    IdSpan::Canonical(0x37, 0x4D),
+    IdSpan::Absent("set_display_chat_preview"),
+    IdSpan::Canonical(0x4D, 0x67),
+    IdSpan::Canonical(0x68, 0x6B),
+];
+
+const CLIENTBOUND_PLAY_1_19_2: &[IdSpan] = &[
+    IdSpan::Canonical(0x00, 0x0C),
+    IdSpan::Absent("chat_preview"),
+    IdSpan::Canonical(0x0C, 0x16),
+    IdSpan::Absent("custom_sound"),
+    IdSpan::Absent("delete_chat"),
+    IdSpan::Canonical(0x17, 0x18),
+    IdSpan::Canonical(0x19, 0x31),
+    IdSpan::Absent("player_chat_header"),
+    IdSpan::Canonical(0x31, 0x35),
+    IdSpan::Absent("player_info"),
+    IdSpan::Canonical(0x37, 0x4D),
+    IdSpan::Absent("set_display_chat_preview"),
+    IdSpan::Canonical(0x4D, 0x67),
+    IdSpan::Canonical(0x68, 0x6B),
+];
+
+const CLIENTBOUND_PLAY_1_19_3: &[IdSpan] = &[IdSpan::Canonical(0x00, 0x6B)];
+
+const CLIENTBOUND_PLAY_1_19_4: &[IdSpan] = &[
+    IdSpan::Absent("bundle_delimiter"),
+    IdSpan::Canonical(0x00, 0x0C),
+    IdSpan::Absent("chunks_biomes"),
+    IdSpan::Canonical(0x0C, 0x16),
+    IdSpan::Absent("damage_event"),
+    IdSpan::Canonical(0x16, 0x1E),
+    IdSpan::Absent("hurt_animation"),
+    IdSpan::Canonical(0x1E, 0x6B),
+];
+
+const SERVERBOUND_PLAY_1_19: &[IdSpan] = &[
+    IdSpan::Canonical(0x00, 0x03),
+    IdSpan::Canonical(0x04, 0x06),
+    IdSpan::Absent("chat_preview"),
+    IdSpan::Canonical(0x06, 0x20),
+    IdSpan::Canonical(0x21, 0x33),
+];
+
+const SERVERBOUND_PLAY_1_19_2: &[IdSpan] = &[
+    IdSpan::Canonical(0x00, 0x06),
+    IdSpan::Absent("chat_preview"),
+    IdSpan::Canonical(0x06, 0x20),
+    IdSpan::Canonical(0x21, 0x33),
+];
+
+const SERVERBOUND_PLAY_1_19_3: &[IdSpan] = &[IdSpan::Canonical(0x00, 0x33)];
+
+const SERVERBOUND_PLAY_1_19_4: &[IdSpan] = &[
+    IdSpan::Canonical(0x00, 0x06),
+    // chat session update moved next to the other chat packets
+    IdSpan::Canonical(0x20, 0x21),
+    IdSpan::Canonical(0x06, 0x20),
+    IdSpan::Canonical(0x21, 0x33),
+];
+
+// 1.19.3 added the long serializer; 1.19.4 added the block state serializer before the optional
+// one, and the sniffer state and display entity serializers at the end
+const ENTITY_DATA_SERIALIZERS_1_19: &[IdSpan] =
+    &[IdSpan::Canonical(0, 2), IdSpan::Canonical(3, 24)];
+
+const ENTITY_DATA_SERIALIZERS_1_19_3: &[IdSpan] = &[IdSpan::Canonical(0, 24)];
+
+const ENTITY_DATA_SERIALIZERS_1_19_4: &[IdSpan] = &[
+    IdSpan::Canonical(0, 14),
+    IdSpan::Absent("block_state"),
+    IdSpan::Canonical(14, 24),
+    IdSpan::Absent("sniffer_state"),
+    IdSpan::Absent("vector3"),
+    IdSpan::Absent("quaternion"),
+];
+
+const PARTICLES_1_19_3: &[IdSpan] = &[IdSpan::Canonical(0, 93)];
+
+const PARTICLES_1_19_4: &[IdSpan] = &[
+    IdSpan::Canonical(0, 29),
+    IdSpan::Absent("dripping_cherry_leaves"),
+    IdSpan::Absent("falling_cherry_leaves"),
+    IdSpan::Absent("landing_cherry_leaves"),
+    IdSpan::Canonical(29, 93),
+];
+
+const PARTICLES_1_20: &[IdSpan] = &[
+    IdSpan::Canonical(0, 29),
+    IdSpan::Absent("cherry_leaves"),
+    IdSpan::Canonical(29, 93),
+    IdSpan::Absent("egg_crack"),
+];
+
+/// Bidirectional mapping between a version's ids of a registry, such as its packets, and the
+/// canonical ids.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PacketIdTable {
+    kind: &'static str,
+    to_canonical: Vec<std::result::Result<i32, &'static str>>,
+    from_canonical: Vec<Option<i32>>,
+}
+
+impl PacketIdTable {
+    fn from_spans(kind: &'static str, spans: &[IdSpan]) -> Self {
+        let mut to_canonical = Vec::new();
+        for span in spans {
+            match span {
+                IdSpan::Canonical(start, end) => to_canonical.extend((*start..*end).map(Ok)),
+                IdSpan::Absent(name) => to_canonical.push(Err(*name)),
+            }
+        }
+        let canonical_len = to_canonical
+            .iter()
+            .filter_map(|id| id.as_ref().ok())
+            .max()
+            .map(|max| *max as usize + 1)
+            .unwrap_or(0);
+        let mut from_canonical = vec![None; canonical_len];
+        for (version_id, canonical_id) in to_canonical.iter().enumerate() {
+            if let Ok(canonical_id) = canonical_id {
+                from_canonical[*canonical_id as usize] = Some(version_id as i32);
+            }
+        }
+        Self {
+            kind,
+            to_canonical,
+            from_canonical,
+        }
+    }
+
+    pub fn to_canonical(&self, version_id: i32) -> Result<i32> {
+        match self.to_canonical.get(version_id as usize) {
+            Some(Ok(canonical_id)) if version_id >= 0 => Ok(*canonical_id),
+            Some(Err(name)) if version_id >= 0 => throw_explain!(format!(
+                "{} {} ({}) has no canonical representation.",
+                self.kind, version_id, name
+            )),
+            _ => throw_explain!(format!("Unknown {} id {}.", self.kind, version_id)),
+        }
+    }
+
+    pub fn from_canonical(&self, canonical_id: i32) -> Result<i32> {
+        match self.from_canonical.get(canonical_id as usize) {
+            Some(Some(version_id)) if canonical_id >= 0 => Ok(*version_id),
+            _ => throw_explain!(format!(
+                "Canonical {} {} does not exist in this version.",
+                self.kind, canonical_id
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum LayoutChange {
+    None,
+    /// The version appends a VarInt the canonical layout lacks, e.g. the 1.20 portal cooldown.
+    TrailingVarInt,
+    /// The version drops a trailing bool of the canonical layout, e.g. the 1.19.4 dismount flag.
+    DroppedTrailingBool,
+    /// The entity data serializer ids, and the particle ids of particle values, are remapped.
+    EntityDataIds,
+    /// The packet starts with a particle id which is remapped.
+    LeadingParticleId,
+    /// The version follows the block position with an is_front_text bool, e.g. the 1.20 sign
+    /// packets. Editing the back text fails to decode.
+    FrontText,
+    /// The version sends a sound by registry id, without the offset which makes room for an
+    /// inline sound event.
+    SoundId,
+    /// The packet belongs to the chat signing model of 1.19 to 1.19.2 and is translated field
+    /// by field.
+    SignedChat,
+}
+
+/// Translates play packets, and the serverbound login packets, between a client's version and
+/// the canonical registries. <br />
+///
+/// Fields without a canonical counterpart are dropped on decode and written with their default
+/// value on encode; canonical fields missing from the version are defaulted on decode. Entity
+/// data and particles are remapped between the id tables of the versions, and those without a
+/// canonical counterpart fail to decode. Chat signatures of 1.19 to 1.19.2 can not be
+/// translated, so their messages are decoded and sent unsigned.
+#[derive(Debug, Clone)]
+pub struct VersionedCodec {
+    version: ProtocolVersion,
+    clientbound_play: PacketIdTable,
+    serverbound_play: PacketIdTable,
+    entity_data_serializers: PacketIdTable,
+    particles: PacketIdTable,
+}
+
+impl VersionedCodec {
+    pub fn new(version: ProtocolVersion) -> Self {
+        Self {
+            version,
+            clientbound_play: PacketIdTable::from_spans("packet", version.clientbound_play_spans()),
+            serverbound_play: PacketIdTable::from_spans("packet", version.serverbound_play_spans()),
+            entity_data_serializers: PacketIdTable::from_spans(
+                "entity data serializer",
+                version.entity_data_serializer_spans(),
+            ),
+            particles: PacketIdTable::from_spans("particle", version.particle_spans()),
+        }
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    pub async fn decode_clientbound_play(
+        &self,
+        packet: Vec<u8>,
+    ) -> Result<ClientboundPlayRegistry> {
+        let (canonical_id, mut body) =
+            Self::remap_id(packet, |id| self.clientbound_play.to_canonical(id)).await?;
+        match self.version.clientbound_play_layout(canonical_id) {
+            LayoutChange::None => {
+                Self::decode_exact::<ClientboundPlayRegistry>(canonical_id, body).await
+            }
+            LayoutChange::TrailingVarInt => {
+                let mut cursor = Cursor::new(Self::with_id(canonical_id, body).await?);
+                let decoded = ClientboundPlayRegistry::decode(&mut (), &mut cursor).await?;
+                cursor.read_var_int().await?;
+                Self::ensure_consumed(&cursor)?;
+                Ok(decoded)
+            }
+            LayoutChange::DroppedTrailingBool => {
+                body.push(0);
+                Self::decode_exact::<ClientboundPlayRegistry>(canonical_id, body).await
+            }
+            LayoutChange::EntityDataIds => self.decode_entity_data(body).await,
+            LayoutChange::LeadingParticleId => {
+                let (particle, body) =
+                    Self::remap_id(body, |id| self.particles.to_canonical(id)).await?;
+                let body = Self::with_id(particle, body).await?;
+                Self::decode_exact::<ClientboundPlayRegistry>(canonical_id, body).await
+            }
+            LayoutChange::FrontText => {
+                Self::remove_front_text(&mut body)?;
+                Self::decode_exact::<ClientboundPlayRegistry>(canonical_id, body).await
+            }
+            LayoutChange::SoundId => {
+                let (sound, body) = Self::remap_id(body, |id| Ok(id + 1)).await?;
+                let body = Self::with_id(sound, body).await?;
+                Self::decode_exact::<ClientboundPlayRegistry>(canonical_id, body).await
+            }
+            LayoutChange::SignedChat => {
+                signed_chat::decode_clientbound_play(self.version, canonical_id, body).await
+            }
+        }
+    }
+
+    pub async fn encode_clientbound_play(
+        &self,
+        packet: &ClientboundPlayRegistry,
+    ) -> Result<Vec<u8>> {
+        let mut canonical = Vec::new();
+        ClientboundPlayRegistry::encode(packet, &mut (), &mut canonical).await?;
+        let (version_id, mut body) =
+            Self::remap_id(canonical, |id| self.clientbound_play.from_canonical(id)).await?;
+        let canonical_id = self.clientbound_play.to_canonical(version_id)?;
+        match (self.version.clientbound_play_layout(canonical_id), packet) {
+            (LayoutChange::TrailingVarInt, _) => body.write_var_int(0).await?,
+            (LayoutChange::DroppedTrailingBool, _) => {
+                body.pop();
+            }
+            (
+                LayoutChange::EntityDataIds,
+                ClientboundPlayRegistry::SetEntityData {
+                    entity_id,
+                    packed_items,
+                },
+            ) => body = self.encode_entity_data(*entity_id, packed_items).await?,
+            (LayoutChange::LeadingParticleId, _) => {
+                let (particle, rest) =
+                    Self::remap_id(body, |id| self.particles.from_canonical(id)).await?;
+                body = Self::with_id(particle, rest).await?;
+            }
+            (LayoutChange::FrontText, _) => body.insert(BLOCK_POS_LENGTH, 1),
+            (LayoutChange::SoundId, _) => {
+                let (sound, rest) = Self::remap_id(body, |id| match id {
+                    0 => throw_explain!("Inline sound events do not exist in this version."),
+                    id => Ok(id - 1),
+                })
+                .await?;
+                body = Self::with_id(sound, rest).await?;
+            }
+            (LayoutChange::SignedChat, _) => {
+                body = signed_chat::encode_clientbound_play(self.version, packet).await?
+            }
+            _ => {}
+        }
+        Self::with_id(version_id, body).await
+    }
+
+    async fn decode_entity_data(&self, body: Vec<u8>) -> Result<ClientboundPlayRegistry> {
+        let mut cursor = Cursor::new(body);
+        let entity_id = cursor.read_var_int().await?;
+        let mut packed_items = Vec::new();
+        loop {
+            let id = cursor.read_u8().await?;
+            if id == EntityDataList::EOF_MARKER {
+                break;
+            }
+            let serializer = self
+                .entity_data_serializers
+                .to_canonical(cursor.read_var_int().await?)?;
+            let mut key = Vec::with_capacity(10);
+            key.write_var_int(serializer).await?;
+            if serializer == PARTICLE_SERIALIZER_ID {
+                let particle = self.particles.to_canonical(cursor.read_var_int().await?)?;
+                key.write_var_int(particle).await?;
+            }
+            let value =
+                EntityDataValue::decode(&mut (), &mut Cursor::new(key).chain(&mut cursor)).await?;
+            packed_items.push(EntityDataItem { id, value });
+        }
+        Self::ensure_consumed(&cursor)?;
+        Ok(ClientboundPlayRegistry::SetEntityData {
+            entity_id,
+            packed_items,
+        })
+    }
+
+    async fn encode_entity_data(
+        &self,
+        entity_id: i32,
+        packed_items: &[EntityDataItem],
+    ) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        body.write_var_int(entity_id).await?;
+        for item in packed_items {
+            if item.id == EntityDataList::EOF_MARKER {
+                throw_explain!("Entity data id 255 is reserved for the terminator.")
+            }
+            body.write_u8(item.id).await?;
+            let mut value = Vec::new();
+            EntityDataValue::encode(&item.value, &mut (), &mut value).await?;
+            let mut cursor = Cursor::new(value);
+            let serializer = cursor.read_var_int().await?;
+            body.write_var_int(self.entity_data_serializers.from_canonical(serializer)?)
+                .await?;
+            if serializer == PARTICLE_SERIALIZER_ID {
+                let particle = cursor.read_var_int().await?;
+                body.write_var_int(self.particles.from_canonical(particle)?)
+                    .await?;
+            }
+            let offset = cursor.position() as usize;
+            body.extend_from_slice(&cursor.get_ref()[offset..]);
+        }
+        body.write_u8(EntityDataList::EOF_MARKER).await?;
+        Ok(body)
+    }
+
+    pub async fn decode_serverbound_play(
+        &self,
+        packet: Vec<u8>,
+    ) -> Result<ServerboundPlayRegistry> {
+        let (canonical_id, mut body) =
+            Self::remap_id(packet, |id| self.serverbound_play.to_canonical(id)).await?;
+        match self.version.serverbound_play_layout(canonical_id) {
+            LayoutChange::FrontText => Self::remove_front_text(&mut body)?,
+            LayoutChange::SignedChat => {
+                return signed_chat::decode_serverbound_play(self.version, canonical_id, body).await
+            }
+            _ => {}
+        }
+        Self::decode_exact::<ServerboundPlayRegistry>(canonical_id, body).await
+    }
+
+    pub async fn encode_serverbound_play(
+        &self,
+        packet: &ServerboundPlayRegistry,
+    ) -> Result<Vec<u8>> {
+        let mut canonical = Vec::new();
+        ServerboundPlayRegistry::encode(packet, &mut (), &mut canonical).await?;
+        let (version_id, mut body) =
+            Self::remap_id(canonical, |id| self.serverbound_play.from_canonical(id)).await?;
+        let canonical_id = self.serverbound_play.to_canonical(version_id)?;
+        match self.version.serverbound_play_layout(canonical_id) {
+            LayoutChange::FrontText => body.insert(BLOCK_POS_LENGTH, 1),
+            LayoutChange::SignedChat => {
+                body = signed_chat::encode_serverbound_play(self.version, packet).await?
+            }
+            _ => {}
+        }
+        Self::with_id(version_id, body).await
+    }
+
+    pub async fn decode_serverbound_login(
+        &self,
+        packet: Vec<u8>,
+    ) -> Result<ServerBoundLoginRegsitry> {
+        let (id, body) = Self::remap_id(packet, Ok).await?;
+        match self.version.serverbound_login_layout(id) {
+            LayoutChange::SignedChat => {
+                signed_chat::decode_serverbound_login(self.version, id, body).await
+            }
+            _ => Self::decode_exact::<ServerBoundLoginRegsitry>(id, body).await,
+        }
+    }
+
+    pub async fn encode_serverbound_login(
+        &self,
+        packet: &ServerBoundLoginRegsitry,
+    ) -> Result<Vec<u8>> {
+        let mut canonical = Vec::new();
+        ServerBoundLoginRegsitry::encode(packet, &mut (), &mut canonical).await?;
+        let (id, mut body) = Self::remap_id(canonical, Ok).await?;
+        if self.version.serverbound_login_layout(id) == LayoutChange::SignedChat {
+            body = signed_chat::encode_serverbound_login(self.version, packet).await?;
+        }
+        Self::with_id(id, body).await
+    }
+
+    /// Removes the is_front_text flag following the block position of a sign packet.
+    fn remove_front_text(body: &mut Vec<u8>) -> Result<()> {
+        match body.get(BLOCK_POS_LENGTH) {
+            Some(1) => {
+                body.remove(BLOCK_POS_LENGTH);
+                Ok(())
+            }
+            Some(0) => throw_explain!("The back text of a sign has no canonical representation."),
+            _ => throw_explain!("Missing or invalid is_front_text flag."),
+        }
+    }
+
+    async fn remap_id<F: FnOnce(i32) -> Result<i32>>(
+        packet: Vec<u8>,
+        remap: F,
+    ) -> Result<(i32, Vec<u8>)> {
+        let mut cursor = Cursor::new(packet);
+        let id = cursor.read_var_int().await?;
+        let offset = cursor.position() as usize;
+        let mut body = cursor.into_inner();
+        body.drain(..offset);
+        Ok((remap(id)?, body))
+    }
+
+    async fn with_id(id: i32, body: Vec<u8>) -> Result<Vec<u8>> {
+        let mut packet = Vec::with_capacity(body.len() + 5);
+        packet.write_var_int(id).await?;
+        packet.extend_from_slice(&body);
+        Ok(packet)
+    }
+
+    async fn decode_exact<P: PacketComponent<()>>(
+        canonical_id: i32,
+        body: Vec<u8>,
+    ) -> Result<P::ComponentType> {
+        let mut cursor = Cursor::new(Self::with_id(canonical_id, body).await?);
+        let decoded = P::decode(&mut (), &mut cursor).await?;
+        Self::ensure_consumed(&cursor)?;
+        Ok(decoded)
+    }
+
+    fn ensure_consumed(cursor: &Cursor<Vec<u8>>) -> Result<()> {
+        let remaining = cursor.get_ref().len() as u64 - cursor.position();
+        if remaining != 0 {
+            throw_explain!(format!(
+                "Packet was not fully consumed; {} bytes remained.",
+                remaining
+            ))
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drax::prelude::{PacketComponent, Uuid};
+
+    use crate::clientbound::play::{
+        ClientboundPlayRegistry, EntityDataItem, EntityDataValue, FilterMask, Particle,
+        ParticleBase, ParticleType, Pose,
+    };
+    use crate::common::bit_set::BitSet;
+    use crate::common::chat::Chat;
+    use crate::common::play::{
+        BlockPos, ChatBind, PackedLastSeenMessages, PackedMessageBody, SimpleLocation,
+    };
+    use crate::serverbound::login::ServerBoundLoginRegsitry;
+    use crate::serverbound::play::ServerboundPlayRegistry;
+    use crate::version::{ProtocolVersion, VersionedCodec};
+
+    async fn canonical(packet: &ClientboundPlayRegistry) -> drax::prelude::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        ClientboundPlayRegistry::encode(packet, &mut (), &mut bytes).await?;
+        Ok(bytes)
+    }
+
+    fn entity_data() -> ClientboundPlayRegistry {
+        ClientboundPlayRegistry::SetEntityData {
+            entity_id: 12,
+            packed_items: vec![
+                EntityDataItem {
+                    id: 0,
+                    value: EntityDataValue::Byte { value: 3 },
+                },
+                EntityDataItem {
+                    id: 6,
+                    value: EntityDataValue::Pose {
+                        value: Pose::Swimming {},
+                    },
+                },
+                EntityDataItem {
+                    id: 9,
+                    value: EntityDataValue::Particle {
+                        value: ParticleType::Shriek { delay: 4 },
+                    },
+                },
+                EntityDataItem {
+                    id: 10,
+                    value: EntityDataValue::OptionalBlockState { value: 1 },
+                },
+            ],
+        }
+    }
+
+    fn particles() -> ClientboundPlayRegistry {
+        ClientboundPlayRegistry::LevelParticles {
+            particle: Particle {
+                particle_type: ParticleType::Shriek { delay: 4 },
+                base: ParticleBase {
+                    override_limiter: true,
+                    location: SimpleLocation {
+                        x: 0.5,
+                        y: 70.0,
+                        z: 0.5,
+                    },
+                    x_dist: 0.0,
+                    y_dist: 0.0,
+                    z_dist: 0.0,
+                    max_speed: 0.0,
+                    count: 1,
+                },
+            },
+        }
+    }
+
+    fn sign_update() -> ServerboundPlayRegistry {
+        ServerboundPlayRegistry::SignUpdate {
+            pos: BlockPos { x: 1, y: 64, z: -1 },
+            lines: [
+                "ab".to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ],
+        }
+    }
+
+    #[tokio::test]
+    pub async fn remapped_round_trips() -> drax::prelude::Result<()> {
+        // (version, set entity data id, pose serializer id, particle serializer id, optional
+        // block state serializer id, level particles id, shriek particle id)
+        let expected = [
+            (ProtocolVersion::V1_19, 0x4D, 18, 15, 13, 0x21, 92),
+            (ProtocolVersion::V1_19_2, 0x50, 18, 15, 13, 0x23, 92),
+            (ProtocolVersion::V1_19_3, 0x4E, 19, 16, 14, 0x22, 92),
+            (ProtocolVersion::V1_19_4, 0x52, 20, 17, 15, 0x26, 95),
+            (ProtocolVersion::V1_20, 0x52, 20, 17, 15, 0x26, 93),
+        ];
+        for (
+            version,
+            entity_data_id,
+            pose_serializer,
+            particle_serializer,
+            block_state_serializer,
+            particles_id,
+            shriek,
+        ) in expected
+        {
+            let codec = VersionedCodec::new(version);
+
+            let packet = entity_data();
+            let encoded = codec.encode_clientbound_play(&packet).await?;
+            assert_eq!(
+                encoded,
+                vec![
+                    entity_data_id,
+                    12,
+                    0,
+                    0,
+                    3,
+                    6,
+                    pose_serializer,
+                    3,
+                    9,
+                    particle_serializer,
+                    shriek,
+                    4,
+                    10,
+                    block_state_serializer,
+                    1,
+                    0xFF
+                ]
+            );
+            let decoded = codec.decode_clientbound_play(encoded).await?;
+            assert_eq!(canonical(&decoded).await?, canonical(&packet).await?);
+
+            let packet = particles();
+            let encoded = codec.encode_clientbound_play(&packet).await?;
+            assert_eq!(&encoded[..2], &[particles_id, shriek]);
+            let decoded = codec.decode_clientbound_play(encoded).await?;
+            assert_eq!(canonical(&decoded).await?, canonical(&packet).await?);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn rejects_unmapped_ids() -> drax::prelude::Result<()> {
+        let codec = VersionedCodec::new(ProtocolVersion::V1_19_4);
+        // a block state value, which only display entities of 1.19.4 use
+        assert!(codec
+            .decode_clientbound_play(vec![0x52, 12, 0, 14, 1, 0xFF])
+            .await
+            .is_err());
+        // a falling cherry leaves particle
+        let mut encoded = codec.encode_clientbound_play(&particles()).await?;
+        encoded[1] = 30;
+        assert!(codec.decode_clientbound_play(encoded).await.is_err());
+
+        let codec = VersionedCodec::new(ProtocolVersion::V1_20);
+        let mut encoded = codec.encode_clientbound_play(&particles()).await?;
+        encoded[1] = 29;
+        assert!(codec.decode_clientbound_play(encoded).await.is_err());
+
+        // editing the back text of a sign
+        let mut encoded = codec.encode_serverbound_play(&sign_update()).await?;
+        encoded[9] = 0;
+        assert!(codec.decode_serverbound_play(encoded).await.is_err());
+
+        let codec = VersionedCodec::new(ProtocolVersion::V1_19_2);
+        // a long value, which 1.19.2 lacks
+        let packet = ClientboundPlayRegistry::SetEntityData {
+            entity_id: 12,
+            packed_items: vec![EntityDataItem {
+                id: 0,
+                value: EntityDataValue::Long { value: 3 },
+            }],
+        };
+        assert!(codec.encode_clientbound_play(&packet).await.is_err());
+        // a challenge signed with the profile key
+        let key = vec![0x01, 1, 0xAA, 0, 0, 0, 0, 0, 0, 0, 0, 5, 1, 0xBB];
+        assert!(codec.decode_serverbound_login(key).await.is_err());
+
+        assert!(ProtocolVersion::try_from(758).is_err());
+        assert_eq!(ProtocolVersion::try_from(760)?, ProtocolVersion::V1_19_2);
+        assert_eq!(ProtocolVersion::try_from(763)?, ProtocolVersion::V1_20);
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn sign_round_trips() -> drax::prelude::Result<()> {
+        // (version, open sign editor id, sign update id)
+        let expected = [
+            (ProtocolVersion::V1_19, 0x2C, 0x2D),
+            (ProtocolVersion::V1_19_2, 0x2E, 0x2E),
+            (ProtocolVersion::V1_19_3, 0x2D, 0x2E),
+            (ProtocolVersion::V1_19_4, 0x31, 0x2E),
+            (ProtocolVersion::V1_20, 0x31, 0x2E),
+        ];
+        for (version, open_sign_editor_id, sign_update_id) in expected {
+            let codec = VersionedCodec::new(version);
+            let front_text = version == ProtocolVersion::V1_20;
+
+            let packet = ClientboundPlayRegistry::OpenSignEditor {
+                pos: BlockPos { x: 1, y: 64, z: -1 },
+            };
+            let encoded = codec.encode_clientbound_play(&packet).await?;
+            assert_eq!(encoded[0], open_sign_editor_id);
+            assert_eq!(encoded.get(9), front_text.then_some(&1));
+            let decoded = codec.decode_clientbound_play(encoded).await?;
+            assert_eq!(canonical(&decoded).await?, canonical(&packet).await?);
+
+            let encoded = codec.encode_serverbound_play(&sign_update()).await?;
+            assert_eq!(encoded[0], sign_update_id);
+            assert_eq!(encoded[9], if front_text { 1 } else { 2 });
+            let decoded = codec.decode_serverbound_play(encoded.clone()).await?;
+            assert_eq!(codec.encode_serverbound_play(&decoded).await?, encoded);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn signed_chat_round_trips() -> drax::prelude::Result<()> {
+        // (version, player chat id, chat id)
+        let expected = [
+            (ProtocolVersion::V1_19, 0x30, 0x04),
+            (ProtocolVersion::V1_19_2, 0x33, 0x05),
+        ];
+        for (version, player_chat_id, chat_id) in expected {
+            let codec = VersionedCodec::new(version);
+
+            let packet = ClientboundPlayRegistry::PlayerChat {
+                sender: Uuid::from_u128(7),
+                index: 0,
+                signature: None,
+                body: PackedMessageBody {
+                    content: "hello".to_string(),
+                    timestamp: 1,
+                    salt: 2,
+                    last_seen: PackedLastSeenMessages { messages: vec![] },
+                },
+                unsigned_content: Some(Chat::text("<Steve> hello")),
+                filter_mask: FilterMask::PassThrough {},
+                chat_bind: ChatBind {
+                    chat_type: 0,
+                    name: Chat::text("Steve"),
+                    target: None,
+                },
+            };
+            let encoded = codec.encode_clientbound_play(&packet).await?;
+            assert_eq!(encoded[0], player_chat_id);
+            let decoded = codec.decode_clientbound_play(encoded).await?;
+            assert_eq!(canonical(&decoded).await?, canonical(&packet).await?);
+
+            let packet = ServerboundPlayRegistry::Chat {
+                message: "hello".to_string(),
+                timestamp: 1,
+                salt: 2,
+                signature: None,
+                last_seen_offset: 0,
+                last_seen_set: BitSet::value_of(vec![])?,
+            };
+            let encoded = codec.encode_serverbound_play(&packet).await?;
+            assert_eq!(encoded[0], chat_id);
+            let decoded = codec.decode_serverbound_play(encoded.clone()).await?;
+            assert_eq!(codec.encode_serverbound_play(&decoded).await?, encoded);
+
+            let packet = ServerBoundLoginRegsitry::Hello {
+                name: "Steve".to_string(),
+                profile_id: Some(Uuid::from_u128(7)),
+            };
+            let encoded = codec.encode_serverbound_login(&packet).await?;
+            let decoded = codec.decode_serverbound_login(encoded.clone()).await?;
+            assert_eq!(codec.encode_serverbound_login(&decoded).await?, encoded);
+            match decoded {
+                ServerBoundLoginRegsitry::Hello { profile_id, .. } => {
+                    assert_eq!(profile_id.is_some(), version == ProtocolVersion::V1_19_2)
+                }
+                _ => panic!("Expected a hello packet."),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,470 @@
+use std::io::Cursor;
+
+use drax::prelude::{PacketComponent, Result, Uuid};
+use drax::throw_explain;
+use drax::transport::packet::option::Maybe;
+use drax::transport::packet::primitive::VarInt;
+use drax::transport::packet::serde_json::JsonDelegate;
+use drax::transport::packet::string::LimitedString;
+
+use crate::clientbound::play::{ClientboundPlayRegistry, FilterMask};
+use crate::common::bit_set::BitSet;
+use crate::common::chat::Chat;
+use crate::common::play::{ChatBind, PackedLastSeenMessages, PackedMessageBody};
+use crate::serverbound::login::ServerBoundLoginRegsitry;
+use crate::serverbound::play::ServerboundPlayRegistry;
+use crate::version::{
+    ProtocolVersion, VersionedCodec, CHAT_ACK_ID, CHAT_COMMAND_ID, CHAT_ID, HELLO_ID, KEY_ID,
+    PLAYER_CHAT_ID, SERVER_DATA_ID, SYSTEM_CHAT_ID,
+};
+
+/// The 1.19 chat type of system messages.
+const SYSTEM_CHAT_TYPE: i32 = 1;
+/// The 1.19 chat type of system messages shown above the hotbar.
+const GAME_INFO_CHAT_TYPE: i32 = 2;
+
+const MAX_MESSAGE_LENGTH: usize = 256;
+
+registry! {
+    components {
+        struct ProfilePublicKey {
+            expires_at: u64,
+            key: Vec<u8>,
+            signature: Vec<u8>
+        },
+
+        /// The 1.19 hello packet; 1.19.1 appends the profile id.
+        struct HelloV1_19 {
+            name: LimitedString<16>,
+            public_key: Maybe<ProfilePublicKey>
+        },
+
+        struct LastSeenEntry {
+            profile_id: Uuid,
+            signature: Vec<u8>
+        },
+
+        struct LastSeenUpdate {
+            last_seen: Vec<LastSeenEntry>,
+            last_received: Maybe<LastSeenEntry>
+        },
+
+        struct ArgumentSignature {
+            name: LimitedString<16>,
+            signature: Vec<u8>
+        },
+
+        /// The 1.19 chat packet; 1.19.1 appends a [`LastSeenUpdate`].
+        struct ChatV1_19 {
+            message: LimitedString<256>,
+            timestamp: u64,
+            salt: u64,
+            signature: Vec<u8>,
+            signed_preview: bool
+        },
+
+        /// The 1.19 chat command packet; 1.19.1 appends a [`LastSeenUpdate`].
+        struct ChatCommandV1_19 {
+            command: LimitedString<256>,
+            timestamp: u64,
+            salt: u64,
+            signatures: Vec<ArgumentSignature>,
+            signed_preview: bool
+        },
+
+        struct PlayerChatV1_19 {
+            signed_content: JsonDelegate<Chat>,
+            unsigned_content: Maybe<JsonDelegate<Chat>>,
+            chat_type: VarInt,
+            sender: Uuid,
+            sender_name: JsonDelegate<Chat>,
+            sender_team: Maybe<JsonDelegate<Chat>>,
+            timestamp: u64,
+            salt: u64,
+            signature: Vec<u8>
+        },
+
+        struct PlayerChatV1_19_2 {
+            previous_signature: Maybe<Vec<u8>>,
+            sender: Uuid,
+            header_signature: Vec<u8>,
+            content: LimitedString<256>,
+            decorated_content: Maybe<JsonDelegate<Chat>>,
+            timestamp: u64,
+            salt: u64,
+            last_seen: Vec<LastSeenEntry>,
+            unsigned_content: Maybe<JsonDelegate<Chat>>,
+            filter_mask: FilterMask,
+            chat_bind: ChatBind
+        },
+
+        struct SystemChatV1_19 {
+            content: JsonDelegate<Chat>,
+            chat_type: VarInt
+        },
+
+        /// The 1.19 server data packet; 1.19.1 appends the secure chat flag.
+        struct ServerDataV1_19 {
+            motd: Maybe<JsonDelegate<Chat>>,
+            icon_base_64: Maybe<String>,
+            previews_chat: bool
+        }
+    }
+}
+
+/// Decodes a clientbound play packet of 1.19 or 1.19.2 whose layout is tied to chat signing.
+pub(super) async fn decode_clientbound_play(
+    version: ProtocolVersion,
+    canonical_id: i32,
+    body: Vec<u8>,
+) -> Result<ClientboundPlayRegistry> {
+    let mut cursor = Cursor::new(body);
+    let decoded = match (version, canonical_id) {
+        (ProtocolVersion::V1_19, PLAYER_CHAT_ID) => {
+            let chat = PlayerChatV1_19::decode(&mut (), &mut cursor).await?;
+            let content = chat.signed_content.to_plain_string();
+            ClientboundPlayRegistry::PlayerChat {
+                sender: chat.sender,
+                index: 0,
+                signature: None,
+                body: unsigned_body(content, chat.timestamp, chat.salt),
+                unsigned_content: Some(chat.unsigned_content.unwrap_or(chat.signed_content)),
+                filter_mask: FilterMask::PassThrough {},
+                chat_bind: ChatBind {
+                    chat_type: chat.chat_type,
+                    name: chat.sender_name,
+                    target: chat.sender_team,
+                },
+            }
+        }
+        (_, PLAYER_CHAT_ID) => {
+            let chat = PlayerChatV1_19_2::decode(&mut (), &mut cursor).await?;
+            ClientboundPlayRegistry::PlayerChat {
+                sender: chat.sender,
+                index: 0,
+                signature: None,
+                body: unsigned_body(chat.content, chat.timestamp, chat.salt),
+                unsigned_content: chat.unsigned_content.or(chat.decorated_content),
+                filter_mask: chat.filter_mask,
+                chat_bind: chat.chat_bind,
+            }
+        }
+        (_, SYSTEM_CHAT_ID) => {
+            let chat = SystemChatV1_19::decode(&mut (), &mut cursor).await?;
+            ClientboundPlayRegistry::SystemChat {
+                content: chat.content,
+                overlay: chat.chat_type == GAME_INFO_CHAT_TYPE,
+            }
+        }
+        (_, SERVER_DATA_ID) => {
+            let data = ServerDataV1_19::decode(&mut (), &mut cursor).await?;
+            let enforces_secure_chat = match version {
+                ProtocolVersion::V1_19 => false,
+                _ => bool::decode(&mut (), &mut cursor).await?,
+            };
+            ClientboundPlayRegistry::ServerData {
+                motd: data.motd,
+                icon_base_64: data.icon_base_64,
+                enforces_secure_chat,
+            }
+        }
+        _ => throw_explain!(format!(
+            "Packet {} has no signed chat layout in {}.",
+            canonical_id,
+            version.name()
+        )),
+    };
+    VersionedCodec::ensure_consumed(&cursor)?;
+    Ok(decoded)
+}
+
+/// Encodes the body of a clientbound play packet for 1.19 or 1.19.2. Messages are sent without
+/// signatures and previews.
+pub(super) async fn encode_clientbound_play(
+    version: ProtocolVersion,
+    packet: &ClientboundPlayRegistry,
+) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    match (version, packet) {
+        (
+            ProtocolVersion::V1_19,
+            ClientboundPlayRegistry::PlayerChat {
+                sender,
+                body: message,
+                unsigned_content,
+                chat_bind,
+                ..
+            },
+        ) => {
+            let chat = PlayerChatV1_19 {
+                signed_content: Chat::text(message.content.clone()),
+                unsigned_content: unsigned_content.clone(),
+                chat_type: chat_bind.chat_type,
+                sender: *sender,
+                sender_name: chat_bind.name.clone(),
+                sender_team: chat_bind.target.clone(),
+                timestamp: message.timestamp,
+                salt: message.salt,
+                signature: Vec::new(),
+            };
+            PlayerChatV1_19::encode(&chat, &mut (), &mut body).await?;
+        }
+        (
+            _,
+            ClientboundPlayRegistry::PlayerChat {
+                sender,
+                body: message,
+                unsigned_content,
+                filter_mask,
+                chat_bind,
+                ..
+            },
+        ) => {
+            let chat = PlayerChatV1_19_2 {
+                previous_signature: None,
+                sender: *sender,
+                header_signature: Vec::new(),
+                content: message.content.clone(),
+                decorated_content: None,
+                timestamp: message.timestamp,
+                salt: message.salt,
+                last_seen: Vec::new(),
+                unsigned_content: unsigned_content.clone(),
+                filter_mask: filter_mask.clone(),
+                chat_bind: chat_bind.clone(),
+            };
+            PlayerChatV1_19_2::encode(&chat, &mut (), &mut body).await?;
+        }
+        (_, ClientboundPlayRegistry::SystemChat { content, overlay }) => {
+            let chat = SystemChatV1_19 {
+                content: content.clone(),
+                chat_type: if *overlay {
+                    GAME_INFO_CHAT_TYPE
+                } else {
+                    SYSTEM_CHAT_TYPE
+                },
+            };
+            SystemChatV1_19::encode(&chat, &mut (), &mut body).await?;
+        }
+        (
+            _,
+            ClientboundPlayRegistry::ServerData {
+                motd,
+                icon_base_64,
+                enforces_secure_chat,
+            },
+        ) => {
+            let data = ServerDataV1_19 {
+                motd: motd.clone(),
+                icon_base_64: icon_base_64.clone(),
+                previews_chat: false,
+            };
+            ServerDataV1_19::encode(&data, &mut (), &mut body).await?;
+            if version != ProtocolVersion::V1_19 {
+                bool::encode(enforces_secure_chat, &mut (), &mut body).await?;
+            }
+        }
+        _ => throw_explain!(format!(
+            "Packet has no signed chat layout in {}.",
+            version.name()
+        )),
+    }
+    Ok(body)
+}
+
+/// Decodes a serverbound play packet of 1.19 or 1.19.2 whose layout is tied to chat signing.
+/// Signatures and acknowledgements are dropped.
+pub(super) async fn decode_serverbound_play(
+    version: ProtocolVersion,
+    canonical_id: i32,
+    body: Vec<u8>,
+) -> Result<ServerboundPlayRegistry> {
+    let mut cursor = Cursor::new(body);
+    let decoded = match canonical_id {
+        CHAT_ACK_ID => {
+            LastSeenUpdate::decode(&mut (), &mut cursor).await?;
+            ServerboundPlayRegistry::ChatAck { offset: 0 }
+        }
+        CHAT_COMMAND_ID => {
+            let command = ChatCommandV1_19::decode(&mut (), &mut cursor).await?;
+            skip_last_seen(version, &mut cursor).await?;
+            ServerboundPlayRegistry::ChatCommand {
+                command: command.command,
+                timestamp: command.timestamp,
+                salt: command.salt,
+                signatures: Vec::new(),
+                last_seen_offset: 0,
+                last_seen_set: BitSet::value_of(Vec::new())?,
+            }
+        }
+        CHAT_ID => {
+            let chat = ChatV1_19::decode(&mut (), &mut cursor).await?;
+            skip_last_seen(version, &mut cursor).await?;
+            ServerboundPlayRegistry::Chat {
+                message: chat.message,
+                timestamp: chat.timestamp,
+                salt: chat.salt,
+                signature: None,
+                last_seen_offset: 0,
+                last_seen_set: BitSet::value_of(Vec::new())?,
+            }
+        }
+        _ => throw_explain!(format!(
+            "Packet {} has no signed chat layout in {}.",
+            canonical_id,
+            version.name()
+        )),
+    };
+    VersionedCodec::ensure_consumed(&cursor)?;
+    Ok(decoded)
+}
+
+/// Encodes the body of a serverbound play packet for 1.19 or 1.19.2, without signatures.
+pub(super) async fn encode_serverbound_play(
+    version: ProtocolVersion,
+    packet: &ServerboundPlayRegistry,
+) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    match packet {
+        ServerboundPlayRegistry::ChatAck { .. } => {}
+        ServerboundPlayRegistry::ChatCommand {
+            command,
+            timestamp,
+            salt,
+            ..
+        } => {
+            let command = ChatCommandV1_19 {
+                command: command.clone(),
+                timestamp: *timestamp,
+                salt: *salt,
+                signatures: Vec::new(),
+                signed_preview: false,
+            };
+            ChatCommandV1_19::encode(&command, &mut (), &mut body).await?;
+        }
+        ServerboundPlayRegistry::Chat {
+            message,
+            timestamp,
+            salt,
+            ..
+        } => {
+            let chat = ChatV1_19 {
+                message: message.clone(),
+                timestamp: *timestamp,
+                salt: *salt,
+                signature: Vec::new(),
+                signed_preview: false,
+            };
+            ChatV1_19::encode(&chat, &mut (), &mut body).await?;
+        }
+        _ => throw_explain!(format!(
+            "Packet has no signed chat layout in {}.",
+            version.name()
+        )),
+    }
+    if version != ProtocolVersion::V1_19 {
+        let update = LastSeenUpdate {
+            last_seen: Vec::new(),
+            last_received: None,
+        };
+        LastSeenUpdate::encode(&update, &mut (), &mut body).await?;
+    }
+    Ok(body)
+}
+
+/// Decodes a serverbound login packet of 1.19 or 1.19.2 which carries the profile public key.
+/// The key is dropped; a key packet signed with it is rejected as there is no verify token to
+/// check.
+pub(super) async fn decode_serverbound_login(
+    version: ProtocolVersion,
+    id: i32,
+    body: Vec<u8>,
+) -> Result<ServerBoundLoginRegsitry> {
+    let mut cursor = Cursor::new(body);
+    let decoded = match id {
+        HELLO_ID => {
+            let hello = HelloV1_19::decode(&mut (), &mut cursor).await?;
+            let profile_id = match version {
+                ProtocolVersion::V1_19 => None,
+                _ => Maybe::<Uuid>::decode(&mut (), &mut cursor).await?,
+            };
+            ServerBoundLoginRegsitry::Hello {
+                name: hello.name,
+                profile_id,
+            }
+        }
+        KEY_ID => {
+            let key_bytes = Vec::<u8>::decode(&mut (), &mut cursor).await?;
+            if !bool::decode(&mut (), &mut cursor).await? {
+                throw_explain!(
+                    "A challenge signed with the profile key has no canonical representation."
+                )
+            }
+            ServerBoundLoginRegsitry::Key {
+                key_bytes,
+                encrypted_challenge: Vec::<u8>::decode(&mut (), &mut cursor).await?,
+            }
+        }
+        _ => throw_explain!(format!(
+            "Login packet {} has no signed chat layout in {}.",
+            id,
+            version.name()
+        )),
+    };
+    VersionedCodec::ensure_consumed(&cursor)?;
+    Ok(decoded)
+}
+
+/// Encodes the body of a serverbound login packet for 1.19 or 1.19.2, without a profile public
+/// key.
+pub(super) async fn encode_serverbound_login(
+    version: ProtocolVersion,
+    packet: &ServerBoundLoginRegsitry,
+) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    match packet {
+        ServerBoundLoginRegsitry::Hello { name, profile_id } => {
+            let hello = HelloV1_19 {
+                name: name.clone(),
+                public_key: None,
+            };
+            HelloV1_19::encode(&hello, &mut (), &mut body).await?;
+            if version != ProtocolVersion::V1_19 {
+                Maybe::<Uuid>::encode(profile_id, &mut (), &mut body).await?;
+            }
+        }
+        ServerBoundLoginRegsitry::Key {
+            key_bytes,
+            encrypted_challenge,
+        } => {
+            Vec::<u8>::encode(key_bytes, &mut (), &mut body).await?;
+            bool::encode(&true, &mut (), &mut body).await?;
+            Vec::<u8>::encode(encrypted_challenge, &mut (), &mut body).await?;
+        }
+        _ => throw_explain!(format!(
+            "Login packet has no signed chat layout in {}.",
+            version.name()
+        )),
+    }
+    Ok(body)
+}
+
+async fn skip_last_seen(version: ProtocolVersion, cursor: &mut Cursor<Vec<u8>>) -> Result<()> {
+    if version != ProtocolVersion::V1_19 {
+        LastSeenUpdate::decode(&mut (), cursor).await?;
+    }
+    Ok(())
+}
+
+/// An unsigned canonical message body. 1.19 signs the decorated component, of which the body
+/// keeps the first 256 characters of text.
+fn unsigned_body(content: String, timestamp: u64, salt: u64) -> PackedMessageBody {
+    PackedMessageBody {
+        content: content.chars().take(MAX_MESSAGE_LENGTH).collect(),
+        timestamp,
+        salt,
+        last_seen: PackedLastSeenMessages {
+            messages: Vec::new(),
+        },
+    }
+}